@@ -20,6 +20,7 @@
 
 use scripty::*;
 use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -305,6 +306,13 @@ fn interactive_processing_examples() -> Result<()> {
     }
     println!("   Calculator session completed successfully");
 
+    // The same session without the thread plumbing
+    let mut results = Vec::new();
+    cmd!("bc", "-l").interact(["scale=2", "22/7", "sqrt(2)", "2^10"], |line| {
+        results.push(line)
+    })?;
+    println!("   With interact(): {}", results.join(", "));
+
     // Data transformation pipeline
     println!("\n🔄 Data transformation pipeline:");
     println!("   Command: tr a-z A-Z (uppercase conversion)");
@@ -540,6 +548,26 @@ fn output_separation_examples() -> Result<()> {
 
     println!("   Build monitoring completed successfully");
 
+    // The same with a stderr line callback instead of reader threads
+    println!("\n📡 Stderr line callback:");
+    println!("   Command: sh script (on_stderr_line while capturing stdout)");
+    let warnings = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&warnings);
+    let steps = cmd!(
+        "sh",
+        "-c",
+        "echo 'Build started'; echo 'Warning: unused variable' >&2; echo 'Build completed'"
+    )
+    .no_echo()
+    .on_stderr_line(move |line| {
+        if line.to_lowercase().contains("warning") {
+            *counter.lock().unwrap() += 1;
+        }
+    })
+    .output()?;
+    println!("   Build steps: {} completed", steps.lines().count());
+    println!("   Build warnings: {}", warnings.lock().unwrap());
+
     Ok(())
 }
 
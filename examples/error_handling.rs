@@ -13,7 +13,6 @@
 //! Related examples: 00_hello_world.rs, 01_simple_pipes.rs
 
 use scripty::*;
-use std::fs;
 
 fn main() {
     println!("🛡️ Error Handling Best Practices with scripty");
@@ -160,9 +159,9 @@ fn graceful_fallbacks() {
     // Fallback with different approaches
     let content = cmd!("cat", "/etc/os-release")
         .no_echo()
+        .or_cmd(cmd!("cat", "/etc/system-release").no_echo())
+        .or_cmd(cmd!("uname", "-a").no_echo())
         .output()
-        .or_else(|_| cmd!("cat", "/etc/system-release").no_echo().output())
-        .or_else(|_| cmd!("uname", "-a").no_echo().output())
         .unwrap_or_else(|_| "Unknown system".to_string());
 
     println!(
@@ -172,17 +171,21 @@ fn graceful_fallbacks() {
 }
 
 fn handle_multiple_operations() {
-    let temp_dir = std::env::temp_dir().join("scripty_error_example");
+    // Scratch directory, removed when `temp_dir` goes out of scope
+    let Ok(temp_dir) = fs::create_temp_dir() else {
+        println!("   ❌ Could not create a scratch directory");
+        return;
+    };
     let mut errors = Vec::new();
     let mut successes = 0;
 
-    // Create test directory
-    let _ = fs::create_dir_all(&temp_dir);
-
     // Run multiple operations, collecting errors
     let operations = vec![
-        ("Create file", cmd!("touch", temp_dir.join("test.txt"))),
-        ("List directory", cmd!("ls", &temp_dir)),
+        (
+            "Create file",
+            cmd!("touch", temp_dir.path().join("test.txt")),
+        ),
+        ("List directory", cmd!("ls", temp_dir.path())),
         ("Invalid operation", cmd!("rm", "--invalid-flag", "/")),
         ("Show date", cmd!("date")),
     ];
@@ -205,9 +208,6 @@ fn handle_multiple_operations() {
         successes,
         errors.len()
     );
-
-    // Cleanup
-    let _ = fs::remove_dir_all(&temp_dir);
 }
 
 fn custom_error_context() {
@@ -252,21 +252,19 @@ fn exit_code_handling() {
         Err(_) => println!("   ❌ File does not exist"),
     }
 
-    // Differentiating between failure types
-    let test_file = "/tmp/scripty_test_file";
-    let _ = fs::write(test_file, "test");
-
-    // This should succeed
-    if cmd!("test", "-f", test_file).no_echo().run().is_ok() {
-        println!("   ✅ Test file exists");
-    }
-
-    // This should fail with exit code 1 (not an execution error)
-    if cmd!("test", "-d", test_file).no_echo().run().is_err() {
-        println!("   ✅ Correctly identified: not a directory");
-    }
+    // Differentiating between failure types; the temporary file is removed afterwards
+    let _ = fs::with_temp_file(|test_file| -> std::io::Result<()> {
+        // This should succeed
+        if cmd!("test", "-f", test_file).no_echo().run().is_ok() {
+            println!("   ✅ Test file exists");
+        }
 
-    let _ = fs::remove_file(test_file);
+        // This should fail with exit code 1 (not an execution error)
+        if cmd!("test", "-d", test_file).no_echo().run().is_err() {
+            println!("   ✅ Correctly identified: not a directory");
+        }
+        Ok(())
+    });
 }
 
 fn debugging_helpers() {
@@ -280,13 +278,8 @@ fn debugging_helpers() {
                 Ok(output)
             }
             Err(e) => {
+                // A missing program is reported as "Command not found: '...'"
                 println!("   ❌ Failed with error: {}", e);
-
-                // Try to provide more context
-                if let Err(_which_err) = cmd!("which", "nonexistent_command").no_echo().run() {
-                    println!("   💡 Hint: Command might not be installed");
-                }
-
                 Err(e.into())
             }
         }
@@ -296,19 +289,18 @@ fn debugging_helpers() {
     let _ = debug_command(cmd!("echo", "Debug test").no_echo());
 
     // Test with environment variables for debugging
-    unsafe {
-        std::env::set_var("DEBUG", "1");
-    }
-    let output = cmd!(
-        "sh",
-        "-c",
-        "if [ \"$DEBUG\" = \"1\" ]; then echo '   🐛 Debug mode enabled'; fi"
-    )
-    .no_echo()
-    .output()
-    .unwrap_or_default();
+    // SAFETY: the example is single-threaded
+    let output = unsafe {
+        with_env("DEBUG", "1", || {
+            cmd!(
+                "sh",
+                "-c",
+                "if [ \"$DEBUG\" = \"1\" ]; then echo '   🐛 Debug mode enabled'; fi"
+            )
+            .no_echo()
+            .output()
+            .unwrap_or_default()
+        })
+    };
     print!("{}", output);
-    unsafe {
-        std::env::remove_var("DEBUG");
-    }
 }
@@ -114,9 +114,8 @@ fn file_operations(workspace: &Path) -> Result<()> {
     fs::write(&binary_file, &binary_data)?;
     println!("   ✅ Written binary data");
 
-    // Append to file (using write with read-modify-write pattern)
-    let existing = fs::read_to_string(&file_path)?;
-    fs::write(&file_path, format!("{}\nAppended line!", existing))?;
+    // Append to file without rewriting it
+    fs::append(&file_path, "Appended line!\n")?;
     println!("   ✅ Appended to file");
 
     Ok(())
@@ -249,6 +248,21 @@ fn advanced_operations(workspace: &Path) -> Result<()> {
         }
     }
 
+    // Symbolic link, stored relative to the link's directory
+    #[cfg(unix)]
+    {
+        let symlink_path = workspace.join("symlink.txt");
+        fs::symlink(file_path.file_name().unwrap(), &symlink_path)?;
+        println!(
+            "   ✅ Created symlink to {}",
+            fs::read_link(&symlink_path)?.display()
+        );
+    }
+
+    // Resolve a relative path with `..` to an absolute one
+    let resolved = fs::canonicalize(workspace.join("..").join(workspace.file_name().unwrap()))?;
+    println!("   ✅ Resolved workspace to {}", resolved.display());
+
     Ok(())
 }
 
@@ -1,19 +1,91 @@
 //! Command implementation and execution logic.
 
-use crate::cmd::{error::Error, types::*};
+use crate::cmd::types::*;
+use crate::error::{CmdError, Error};
 use std::ffi::OsStr;
-use std::io::{Read, Write};
+use std::io::{BufRead, Read, Write};
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 impl Cmd {
     /// Create a new command.
+    ///
+    /// This is what [`cmd!`](crate::cmd!) expands to, and the way to build a command
+    /// from values known only at run time, such as an argument list in a `Vec`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::Cmd;
+    /// use std::ffi::OsString;
+    ///
+    /// let mut args: Vec<OsString> = vec!["-czf".into(), "backup.tar.gz".into()];
+    /// args.extend(std::env::args_os().skip(1));
+    /// Cmd::new("tar").args(args).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub fn new(program: impl AsRef<OsStr>) -> Self {
         Self {
             program: program.as_ref().to_os_string(),
             args: Vec::new(),
             envs: Vec::new(),
+            env_removes: Vec::new(),
+            env_clear: false,
+            env_inherit: Vec::new(),
             current_dir: None,
+            umask: None,
+            #[cfg(unix)]
+            uid: None,
+            #[cfg(unix)]
+            gid: None,
+            #[cfg(unix)]
+            arg0: None,
+            #[cfg(unix)]
+            process_group: None,
+            #[cfg(unix)]
+            pre_exec: Vec::new(),
+            stdin_mode: None,
+            stdout_mode: None,
+            stderr_mode: None,
+            stderr_to_stdout: false,
             suppress_echo: false,
+            verbose_echo: false,
+            echo_writer: None,
+            kill_on_drop: false,
+            timeout: None,
+        }
+    }
+
+    /// Create a command whose program is taken from an environment variable.
+    ///
+    /// This is how build tools pick e.g. the compiler from `$CC` or the editor from
+    /// `$EDITOR`. If `var` is set to a non-blank value, that value is the program,
+    /// otherwise `default` is used. The value is split on whitespace, and every word
+    /// after the first is added as an argument, so `CC="ccache gcc"` works as expected.
+    /// No shell quoting is interpreted, so a program path containing spaces cannot be
+    /// given this way. A value that is not valid UTF-8 is used as the program as is.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::Cmd;
+    ///
+    /// Cmd::from_env("CC", "cc").args(["-O2", "-c", "main.c"]).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_env(var: impl AsRef<OsStr>, default: impl AsRef<OsStr>) -> Self {
+        let Some(value) =
+            std::env::var_os(var).filter(|value| !value.to_string_lossy().trim().is_empty())
+        else {
+            return Self::new(default);
+        };
+        match value.to_str() {
+            Some(value) => {
+                let mut words = value.split_whitespace();
+                let program = words.next().expect("value is not blank");
+                Self::new(program).args(words)
+            }
+            None => Self::new(value),
         }
     }
 
@@ -23,7 +95,7 @@ impl Cmd {
         self
     }
 
-    /// Add multiple arguments.
+    /// Add multiple arguments, e.g. from a `Vec` or a slice.
     pub fn args<I, S>(mut self, args: I) -> Self
     where
         I: IntoIterator<Item = S>,
@@ -35,134 +107,1621 @@ impl Cmd {
         self
     }
 
-    /// Set an environment variable.
-    pub fn env(mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> Self {
-        self.envs
-            .push((key.as_ref().to_os_string(), val.as_ref().to_os_string()));
-        self
+    /// Add an argument only if `cond` is true.
+    ///
+    /// Keeps fluent chains readable when optional flags depend on runtime settings.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let (verbose, dry_run) = (true, false);
+    /// cmd!("rsync", "-a", "src/", "backup/")
+    ///     .arg_if(verbose, "-v")
+    ///     .arg_if(dry_run, "--dry-run")
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn arg_if(self, cond: bool, arg: impl AsRef<OsStr>) -> Self {
+        if cond { self.arg(arg) } else { self }
+    }
+
+    /// Add multiple arguments only if `cond` is true.
+    ///
+    /// See [`arg_if`](Self::arg_if).
+    pub fn args_if<I, S>(self, cond: bool, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        if cond { self.args(args) } else { self }
+    }
+
+    /// Add the arguments of a command line given as a single string, e.g. read from a
+    /// configuration file.
+    ///
+    /// The string is split into words the way a POSIX shell does: at unquoted
+    /// whitespace, with `'...'` taken literally, `"..."` allowing `\"`, `\\`, `\$` and
+    /// `` \` `` escapes, and a backslash outside quotes escaping the next character.
+    /// Only tokenization is done: globs such as `*.rs`, variables such as `$HOME`, `~`
+    /// and operators such as `|` or `>` are passed on verbatim. A quote that is not
+    /// closed extends to the end of the string and a trailing backslash is kept as is;
+    /// use [`try_args_from_str`](Self::try_args_from_str) to report those as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// // e.g. read from a configuration file
+    /// let flags = "-n --color=auto 'hello world'";
+    /// cmd!("grep").args_from_str(flags).arg("notes.txt").run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn args_from_str(self, s: &str) -> Self {
+        let (words, _) = Self::split_words(s);
+        self.args(words)
+    }
+
+    /// Add the arguments of a command line given as a single string, failing if it is
+    /// malformed.
+    ///
+    /// Splits the string like [`args_from_str`](Self::args_from_str), but an unterminated
+    /// quote or a trailing backslash is reported as [`CmdError::InvalidArgs`] instead of
+    /// being accepted, which suits strings that come from users or configuration files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scripty::*;
+    ///
+    /// let cmd = cmd!("grep").try_args_from_str("-n 'hello world'")?;
+    /// assert_eq!(cmd.to_command_string(), "grep -n 'hello world'");
+    ///
+    /// assert!(matches!(
+    ///     cmd!("grep").try_args_from_str("-n 'hello world"),
+    ///     Err(CmdError::InvalidArgs { .. })
+    /// ));
+    /// # Ok::<(), CmdError>(())
+    /// ```
+    pub fn try_args_from_str(self, s: &str) -> Result<Self, CmdError> {
+        match Self::split_words(s) {
+            (words, None) => Ok(self.args(words)),
+            (_, Some(reason)) => Err(CmdError::InvalidArgs {
+                args: s.to_string(),
+                reason,
+            }),
+        }
+    }
+
+    /// Set an environment variable.
+    pub fn env(mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> Self {
+        let key = key.as_ref();
+        self.env_removes.retain(|removed| removed != key);
+        self.envs
+            .push((key.to_os_string(), val.as_ref().to_os_string()));
+        self
+    }
+
+    /// Set multiple environment variables.
+    ///
+    /// Accepts anything that yields key/value pairs, such as a `HashMap` or a slice of
+    /// tuples. The variables are added in key order so the echoed command line is the
+    /// same regardless of the iteration order of the collection.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::collections::HashMap;
+    ///
+    /// cmd!("make").envs([("CC", "clang"), ("CFLAGS", "-O2")]).run()?;
+    ///
+    /// let vars = HashMap::from([("RUST_LOG", "debug"), ("RUST_BACKTRACE", "1")]);
+    /// cmd!("cargo", "run").envs(&vars).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let mut vars: Vec<(K, V)> = vars.into_iter().collect();
+        vars.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+        for (key, val) in vars {
+            self = self.env(key, val);
+        }
+        self
+    }
+
+    /// Set and remove environment variables in one call.
+    ///
+    /// Each key is mapped to `Some(value)` to set it, as with [`env`](Self::env), or to
+    /// `None` to remove it, as with [`env_remove`](Self::env_remove); every other
+    /// variable is inherited as usual. The changes are applied in key order, like
+    /// [`envs`](Self::envs), and all of them are shown in the echoed command line.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("cargo", "build")
+    ///     .env_overrides([
+    ///         ("CARGO_TARGET_DIR", Some("/tmp/target")),
+    ///         ("HTTP_PROXY", None),
+    ///         ("HTTPS_PROXY", None),
+    ///     ])
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn env_overrides<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, Option<V>)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        let mut vars: Vec<(K, Option<V>)> = vars.into_iter().collect();
+        vars.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+        for (key, val) in vars {
+            self = match val {
+                Some(val) => self.env(key, val),
+                None => self.env_remove(key),
+            };
+        }
+        self
+    }
+
+    /// Remove an environment variable, whether inherited or set earlier with [`env`](Self::env).
+    ///
+    /// The echoed command line shows the removal as `env: -KEY`.
+    pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+        let key = key.as_ref();
+        self.envs.retain(|(k, _)| k != key);
+        self.env_inherit.retain(|k| k != key);
+        if !self.env_clear && !self.env_removes.iter().any(|removed| removed == key) {
+            self.env_removes.push(key.to_os_string());
+        }
+        self
+    }
+
+    /// Clear the entire environment, including variables set earlier with [`env`](Self::env).
+    ///
+    /// Variables set with [`env`](Self::env) afterwards are the only ones the command
+    /// sees, so the result does not depend on the parent's environment. Note that
+    /// `PATH` is cleared too, so the program may need to be given as a full path.
+    /// The echoed command line shows this as `env: -i`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("/usr/bin/env")
+    ///     .env_clear()
+    ///     .env("LANG", "C")
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self.envs.clear();
+        self.env_removes.clear();
+        self.env_inherit.clear();
+        self
+    }
+
+    /// Clear the environment except for the listed variables of the parent.
+    ///
+    /// The command sees only the variables named in `keys` that are set in the
+    /// parent's environment when it is spawned, plus those set with [`env`](Self::env),
+    /// which take precedence. Anything else the parent has, such as
+    /// `AWS_SECRET_ACCESS_KEY`, never reaches the child. The echoed command line
+    /// shows this as `env: -i`; with `SCRIPTY_VERBOSE` or
+    /// [`verbose_echo`](Self::verbose_echo) the kept variables follow as `env: +KEY`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("cargo", "build", "--release")
+    ///     .env_inherit_only(["PATH", "HOME", "CARGO_HOME", "RUSTUP_HOME"])
+    ///     .env("SOURCE_DATE_EPOCH", "0")
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn env_inherit_only<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<OsStr>,
+    {
+        self.env_clear = true;
+        self.env_removes.clear();
+        self.env_inherit = keys
+            .into_iter()
+            .map(|key| key.as_ref().to_os_string())
+            .collect();
+        self
+    }
+
+    /// Set the working directory.
+    ///
+    /// The directory is checked right before the command is spawned; if it does not
+    /// exist or is not a directory, running fails with
+    /// [`CmdError::CurrentDir`](crate::CmdError::CurrentDir) naming the path instead
+    /// of an opaque spawn error.
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the file mode creation mask of the command, like `umask` in a shell.
+    ///
+    /// Files and directories the command creates get the permissions they ask for
+    /// minus the bits set in `mode`, e.g. `0o002` keeps them group-writable. The mask
+    /// is set in the child process right before the program is executed, so the
+    /// parent's umask is left unchanged. Unix only; the mask is ignored on Windows.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("tar", "xf", "shared.tar", "-C", "/srv/shared")
+    ///     .umask(0o002)
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn umask(mut self, mode: u32) -> Self {
+        self.umask = Some(mode);
+        self
+    }
+
+    /// Run the command as another user, given by user ID.
+    ///
+    /// The child process switches to `uid` right before the program is executed, which
+    /// lets a script started as root drop privileges for a subcommand. Switching
+    /// requires the appropriate privileges, otherwise spawning fails. When running as
+    /// root, supplementary groups are dropped as well. Combine with [`gid`](Self::gid)
+    /// to switch the group too. The user ID is shown as `uid:` in the echoed command line.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("make", "install")
+    ///     .current_dir("/home/builder/project")
+    ///     .uid(1000)
+    ///     .gid(1000)
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Run the command with another primary group, given by group ID.
+    ///
+    /// See [`uid`](Self::uid) for details. The group ID is shown as `gid:` in the
+    /// echoed command line.
+    #[cfg(unix)]
+    pub fn gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Set the name the program sees as its own, `argv[0]`, instead of the program path.
+    ///
+    /// Multi-call binaries such as busybox decide what to do based on `argv[0]`, and
+    /// some programs change their behavior when started under another name, e.g. a
+    /// shell run as `-sh` acts as a login shell. The echoed command line still shows
+    /// the program that is executed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("/bin/busybox", "-la", "/tmp").arg0("ls").run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn arg0(mut self, name: impl AsRef<OsStr>) -> Self {
+        self.arg0 = Some(name.as_ref().to_os_string());
+        self
+    }
+
+    /// Put the command into the process group `pgid`, or into a new group of its own
+    /// if `pgid` is `0`.
+    ///
+    /// Pressing Ctrl-C in a terminal sends `SIGINT` to the whole foreground process
+    /// group, which normally includes the commands scripty runs. A command in a group of
+    /// its own is not part of it, so it keeps running when the script is interrupted and
+    /// the script can shut it down in its own way. Note that such a command can no
+    /// longer read from the terminal: it is stopped with `SIGTTIN` if it tries.
+    ///
+    /// A command leading its own group is also stopped as a whole: when its
+    /// [`timeout`](Self::timeout) expires or its handle is
+    /// [`kill`](PipelineHandle::kill)ed, the signals go to the entire group, so the
+    /// processes it started are terminated too instead of being left behind.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::Duration;
+    ///
+    /// // Stops the test runner and every process it started after ten minutes
+    /// cmd!("./run-integration-tests.sh")
+    ///     .process_group(0)
+    ///     .timeout(Duration::from_secs(600))
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn process_group(mut self, pgid: i32) -> Self {
+        self.process_group = Some(pgid);
+        self
+    }
+
+    /// Run a closure in the child process right before the program is executed.
+    ///
+    /// This is the place for setup that only the child can do, such as becoming a
+    /// session leader with `setsid`, entering namespaces or lowering resource limits
+    /// with `setrlimit`. Hooks run in the order they were added, after the working
+    /// directory, [`umask`](Self::umask), [`uid`](Self::uid) and [`gid`](Self::gid)
+    /// have been applied. If a hook returns an error, the program is not executed and
+    /// spawning fails with that error as [`CmdError::Spawn`](crate::CmdError::Spawn).
+    ///
+    /// The closure is shared between clones of the command, so it takes `&self`; it
+    /// runs once in every child spawned.
+    ///
+    /// # Safety
+    ///
+    /// The closure runs in a forked copy of this process, in which only the calling
+    /// thread exists. It must only call async-signal-safe functions: no allocation,
+    /// no locks, no `println!`. Locks held by other threads at the time of the fork
+    /// are never released in the child. See
+    /// [`CommandExt::pre_exec`](std::os::unix::process::CommandExt::pre_exec) for
+    /// the full requirements.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// // Detach the server from the terminal's session
+    /// let server = unsafe {
+    ///     cmd!("./server").pre_exec(|| {
+    ///         if libc::setsid() == -1 {
+    ///             return Err(std::io::Error::last_os_error());
+    ///         }
+    ///         Ok(())
+    ///     })
+    /// };
+    /// server.run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(unix)]
+    pub unsafe fn pre_exec(
+        mut self,
+        f: impl Fn() -> std::io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.pre_exec.push(PreExecHook(std::sync::Arc::new(f)));
+        self
+    }
+
+    /// Discard the command's stdout, like `> /dev/null`.
+    ///
+    /// This applies wherever stdout would otherwise be inherited, e.g. with `run()` or
+    /// `status()`; errors are still reported through the exit status. Methods that
+    /// capture or redirect stdout, such as `output()`, `write_to()` or piping into
+    /// another command, take precedence and still receive the output.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("npm", "install").discard_stdout().run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn discard_stdout(mut self) -> Self {
+        self.stdout_mode = Some(StdioMode::Null);
+        self
+    }
+
+    /// Discard the command's stderr, like `2> /dev/null`.
+    ///
+    /// Like [`discard_stdout`](Self::discard_stdout), this only applies where stderr
+    /// would otherwise be inherited; capturing or piping stderr takes precedence.
+    pub fn discard_stderr(mut self) -> Self {
+        self.stderr_mode = Some(StdioMode::Null);
+        self
+    }
+
+    /// Connect the command's stdin to `/dev/null`, like `< /dev/null`.
+    ///
+    /// The command sees end of file on its first read, which keeps tools that would
+    /// otherwise wait for input from hanging. No pipe or feeder thread is set up, and
+    /// this takes precedence over any input given with `input()`, `input_bytes()` or
+    /// `input_file()`. In a pipeline it only matters for the first command, since the
+    /// others read the previous command's output.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("ssh", "host", "uptime").no_input().run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn no_input(mut self) -> Self {
+        self.stdin_mode = Some(StdioMode::Null);
+        self
+    }
+
+    /// Let the command read the parent's stdin, e.g. the terminal.
+    ///
+    /// By default a command gets a pipe as its stdin, which is closed right away when
+    /// no input is given, so interactive programs see end of file. With this, full-screen
+    /// programs such as `vim` or `fzf` can read the keyboard. Like
+    /// [`no_input`](Self::no_input), this takes precedence over any input given with
+    /// `input()`, `input_bytes()` or `input_file()`, and only matters for the first
+    /// command of a pipeline.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("vim", "notes.txt").stdin_inherit().run()?;
+    /// let choice = cmd!("fzf").stdin_inherit().output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn stdin_inherit(mut self) -> Self {
+        self.stdin_mode = Some(StdioMode::Inherit);
+        self
+    }
+
+    /// Let the command write to the parent's stdout, undoing
+    /// [`discard_stdout`](Self::discard_stdout).
+    ///
+    /// This is what happens by default wherever stdout is not captured or piped.
+    /// Methods that capture stdout, such as `output()`, and piping into another
+    /// command still take precedence.
+    pub fn stdout_inherit(mut self) -> Self {
+        self.stdout_mode = Some(StdioMode::Inherit);
+        self
+    }
+
+    /// Let the command write to the parent's stderr, undoing
+    /// [`discard_stderr`](Self::discard_stderr).
+    ///
+    /// Like [`stdout_inherit`](Self::stdout_inherit), capturing or piping stderr takes
+    /// precedence. It also keeps the stderr of a command in the middle of a pipeline on
+    /// the terminal with [`Pipeline::capture_stages`].
+    pub fn stderr_inherit(mut self) -> Self {
+        self.stderr_mode = Some(StdioMode::Inherit);
+        self
+    }
+
+    /// Send the command's stderr wherever its stdout goes, like `2>&1` in a shell.
+    ///
+    /// Both streams then share a single file descriptor, so `output()` returns
+    /// everything the command printed, interleaved in exactly the order it was written.
+    /// The same holds when stdout is piped into another command, written to a file
+    /// with `output_to_file()` or inherited. The redirection is made in the child
+    /// process right before the program is executed and takes precedence over
+    /// [`discard_stderr`](Self::discard_stderr). Unix only; stderr is left alone on
+    /// Windows.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let log = cmd!("cargo", "build").combine_stderr_into_stdout().output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn combine_stderr_into_stdout(mut self) -> Self {
+        self.stderr_to_stdout = true;
+        self
+    }
+
+    /// Report progress while the command's stdout is being read.
+    ///
+    /// `callback` receives the number of bytes read so far and the time elapsed since
+    /// reading started. It is called at most every 100 ms while data arrives, and once
+    /// more with the final byte count when stdout reaches end of file, so the last call
+    /// always carries the total. Bytes are counted as they stream through; nothing is
+    /// buffered for the sake of the callback.
+    ///
+    /// Progress is reported by `output()`, `output_bytes()`, `capture()`, `filter()`,
+    /// `write_to()`, `output_to_file()` and `append_to_file()`. With the `output*`,
+    /// `capture()` and `filter()` methods the callback runs on a background reader
+    /// thread, otherwise on the calling thread.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("curl", "-sL", "https://example.com/large.iso")
+    ///     .with_progress(|bytes, elapsed| {
+    ///         let rate = bytes as f64 / elapsed.as_secs_f64().max(0.001);
+    ///         eprint!("\r{} bytes ({:.0} B/s)", bytes, rate);
+    ///     })
+    ///     .output_to_file("large.iso")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_progress(self, callback: impl FnMut(u64, Duration) + Send + 'static) -> Pipeline {
+        self.into_pipeline().with_progress(callback)
+    }
+
+    /// Call `callback` with every line the command writes to stderr, as it arrives.
+    ///
+    /// Stderr is piped instead of inherited and read on a background thread, so the
+    /// command's stdout is handled as usual: `run()` leaves it on the terminal and
+    /// `output()` captures it. Lines are passed without their line ending, with
+    /// invalid UTF-8 replaced. The callback has handled the last line by the time the
+    /// method running the command returns. Lines are reported by `run()` and by the
+    /// methods capturing stdout, such as `output()`, `output_bytes()` and `capture()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("cargo", "build")
+    ///     .on_stderr_line(|line| {
+    ///         if line.trim_start().starts_with("Compiling") {
+    ///             eprintln!("{}", line.trim());
+    ///         }
+    ///     })
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn on_stderr_line(self, callback: impl FnMut(&str) + Send + 'static) -> Pipeline {
+        self.into_pipeline().on_stderr_line(callback)
+    }
+
+    /// Record every chunk of data passing through the command's piped streams to a file.
+    ///
+    /// A diagnostic aid for debugging interactive protocols, like `tcpdump` for pipes:
+    /// each write to stdin and each read from stdout or stderr is logged with a
+    /// timestamp and its direction, so both the timing and the framing of the
+    /// exchange can be inspected. The file is created, or truncated, when the command
+    /// is spawned; if that fails the execution method returns
+    /// [`CmdError::OutputFile`](crate::CmdError::OutputFile).
+    ///
+    /// Every chunk is one line of the form `<seconds> <stream> <length> <data>`, where
+    /// `seconds` is the time since spawning with microsecond precision, `stream` is
+    /// `stdin`, `stdout` or `stderr`, and `data` is the chunk with non-printable bytes
+    /// escaped as by [`slice::escape_ascii`]:
+    ///
+    /// ```text
+    /// 0.000412 stdin 4 2+2\n
+    /// 0.001187 stdout 2 4\n
+    /// ```
+    ///
+    /// Only streams piped to or from scripty are recorded: stdin whenever input is
+    /// given or requested, and stdout or stderr when they are captured, e.g. with
+    /// `output()` or the `spawn_io_*` methods. Streams inherited from the terminal are
+    /// not. Each recorded stream is relayed through an extra thread and every chunk
+    /// is written to the file right away, so this is meant for development rather
+    /// than production use.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let result = cmd!("bc").record_io("bc.trace").input("2+2\n").output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn record_io(self, path: impl AsRef<Path>) -> Pipeline {
+        self.into_pipeline().record_io(path)
+    }
+
+    /// Kill the command if it is still running after `timeout`.
+    ///
+    /// When the timeout expires the process receives `SIGTERM`, followed by `SIGKILL`
+    /// if it has not exited after a short grace period; on Windows it is terminated with
+    /// `TerminateProcess` right away. It is always reaped, and the
+    /// execution method returns [`CmdError::Timeout`](crate::CmdError::Timeout). Output
+    /// captured before the timeout is discarded.
+    ///
+    /// Applies to `run()`, `output()`, `output_bytes()` and `status()`. In a pipeline the
+    /// shortest timeout of all its commands applies to the whole pipeline.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::Duration;
+    ///
+    /// cmd!("curl", "-sf", "http://localhost:8080/health")
+    ///     .timeout(Duration::from_secs(5))
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Convert this command into a single-command pipeline.
+    pub(crate) fn into_pipeline(self) -> Pipeline {
+        let suppress_echo = self.suppress_echo;
+        let verbose_echo = self.verbose_echo;
+        let echo_writer = self.echo_writer.clone();
+        let kill_on_drop = self.kill_on_drop;
+        let timeout = self.timeout;
+        Pipeline {
+            connections: vec![(Stage::Cmd(self), PipeMode::Stdout)],
+            input: None,
+            output_file: None,
+            suppress_echo,
+            verbose_echo,
+            echo_writer,
+            kill_on_drop,
+            timeout,
+            pipefail: true,
+            progress: None,
+            stderr_line: None,
+            record: None,
+            buffer_size: super::pipeline::STAGE_BUFFER_SIZE,
+            cancel: None,
+        }
+    }
+
+    /// Connect `next` to this command with the given pipe mode.
+    fn connect(self, next: Cmd, mode: PipeMode) -> Pipeline {
+        let suppress_echo = self.suppress_echo || next.suppress_echo;
+        let mut pipeline = self.into_pipeline().push(next, mode);
+        pipeline.suppress_echo = suppress_echo;
+        pipeline
+    }
+
+    /// Set binary input data for the command.
+    /// Accepts `Vec<u8>`, `&[u8]`, or other types that can be converted to `Vec<u8>`.
+    pub fn input_bytes(self, input: impl AsRef<[u8]>) -> Pipeline {
+        self.into_pipeline().input_bytes(input)
+    }
+
+    /// Feed the contents of a file to the command's stdin, like `cmd < path` in a shell.
+    ///
+    /// The file is opened when the command is executed, before anything is spawned. If
+    /// it cannot be opened the execution method returns
+    /// [`CmdError::InputFile`](crate::CmdError::InputFile) naming the path. The
+    /// redirection is shown in the echoed command line.
+    ///
+    /// On Linux the file is moved into the command's stdin pipe with `splice`, so the
+    /// data is not copied through this process. This applies to regular files and to
+    /// anything else the kernel can splice from, such as named pipes; other files are
+    /// copied as usual. Readers passed with [`ReadExt::pipe`](crate::ReadExt::pipe)
+    /// are always copied, so prefer `input_file()` to feed large files.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let sorted = cmd!("sort").input_file("names.txt").output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn input_file(self, path: impl AsRef<Path>) -> Pipeline {
+        self.into_pipeline().input_file(path)
+    }
+
+    /// Set text input for the command.
+    /// Optimized to convert string directly to bytes without intermediate allocation.
+    pub fn input(self, input: impl AsRef<str>) -> Pipeline {
+        self.into_pipeline().input(input)
+    }
+
+    /// Feed the items of an iterator to the command's stdin, each followed by `\n`.
+    ///
+    /// The items are written by a separate thread as the command reads them, so
+    /// generated data is streamed without joining it into one big string first. If
+    /// the command exits before reading everything, the rest of the items are
+    /// dropped without an error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let hosts = vec!["db1", "web1", "cache1"];
+    /// let sorted = cmd!("sort").input_iter(hosts).output()?;
+    ///
+    /// let numbers = cmd!("head", "-3").input_iter((1..).map(|n| n.to_string())).output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn input_iter<I, S>(self, lines: I) -> Pipeline
+    where
+        I: IntoIterator<Item = S>,
+        I::IntoIter: Send + 'static,
+        S: AsRef<str>,
+    {
+        self.into_pipeline().input_iter(lines)
+    }
+
+    /// Forward the parent's stdin to the command, closing it if no data arrives within
+    /// `timeout`.
+    ///
+    /// Meant for commands that may or may not ask for input, so that under automation
+    /// they see end of file instead of waiting forever. The heuristic is simple: if
+    /// nothing can be read from the parent's stdin within `timeout` of spawning the
+    /// command, the command's stdin is closed. Once data has arrived, everything is
+    /// forwarded until the parent's stdin ends or the command exits.
+    ///
+    /// Limitations:
+    ///
+    /// - The command reads from a pipe, not the terminal itself, so it will not detect
+    ///   a TTY. Programs that prompt through `/dev/tty`, such as `ssh` asking for a
+    ///   password, are not affected.
+    /// - The timeout runs from the start of the command, not from its first read, and
+    ///   input typed after it expires is not forwarded.
+    /// - Input read from the parent's stdin but not consumed by the command is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::Duration;
+    ///
+    /// cmd!("./configure").inherit_input_timeout(Duration::from_secs(5)).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn inherit_input_timeout(self, timeout: Duration) -> Pipeline {
+        self.into_pipeline().inherit_input_timeout(timeout)
+    }
+
+    /// Run without echoing the command.
+    pub fn no_echo(mut self) -> Self {
+        self.suppress_echo = true;
+        self
+    }
+
+    /// Echo the working directory the command runs from in front of it.
+    ///
+    /// The echo already shows the `cd:` and `env:` overrides; this adds the current
+    /// directory of the script, as an absolute path, so a command can be reproduced from
+    /// a log. Set the `SCRIPTY_VERBOSE` environment variable to do this for every command.
+    ///
+    /// ```text
+    /// scripty:cmd cwd: /home/me/project cd: web env: NODE_ENV=production npm run build
+    /// ```
+    pub fn verbose_echo(mut self) -> Self {
+        self.verbose_echo = true;
+        self
+    }
+
+    /// Echo this command to `writer` instead of stderr.
+    ///
+    /// Only this command is affected; every other command keeps echoing to stderr, or
+    /// wherever [`set_echo_writer`](crate::set_echo_writer) sends it. This takes
+    /// precedence over [`capture_echo`](crate::capture_echo) and the `log` feature, so
+    /// a noisy command can be recorded separately from the main log. Echo disabled with
+    /// `NO_ECHO` or [`no_echo`](Self::no_echo) is not written. In a pipeline, the first
+    /// command with a writer decides where the whole pipeline is echoed to.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::fs::File;
+    ///
+    /// cmd!("npm", "install").echo_to(File::create("npm.log")?).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn echo_to(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.echo_writer = Some(crate::output::EchoWriter::new(writer));
+        self
+    }
+
+    /// Pipe this command's stdout to another command's stdin.
+    ///
+    /// This is the standard Unix pipe behavior where stdout becomes stdin.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// // Standard pipe (stdout → stdin)
+    /// let output = cmd!("echo", "hello")
+    ///     .pipe(cmd!("tr", "[:lower:]", "[:upper:]"))
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn pipe(self, next: Cmd) -> Pipeline {
+        self.connect(next, PipeMode::Stdout)
+    }
+
+    /// Pipe this command's stderr to another command's stdin.
+    ///
+    /// This pipes the error output stream to the next command's input,
+    /// useful for error processing and filtering workflows.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// // Process error messages through a pipeline
+    /// let error_count = cmd!("sh", "-c", "echo 'ERROR: failed' >&2")
+    ///     .pipe_err(cmd!("wc", "-l"))
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn pipe_err(self, next: Cmd) -> Pipeline {
+        self.connect(next, PipeMode::Stderr)
+    }
+
+    /// Pipe this command's combined stdout and stderr to another command's stdin.
+    ///
+    /// This merges both output streams and pipes them to the next command,
+    /// useful for unified processing of all command output.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// // Sort all output (both stdout and stderr)
+    /// let sorted_output = cmd!("sh", "-c", "echo 'out'; echo 'err' >&2")
+    ///     .pipe_out_err(cmd!("sort"))
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn pipe_out_err(self, next: Cmd) -> Pipeline {
+        self.connect(next, PipeMode::Both)
+    }
+
+    /// Pipe this command's stdout into a Rust closure.
+    ///
+    /// The closure runs on its own thread, reading the command's output from the
+    /// [`BufRead`] and writing its own output to the [`Write`]. Whatever it writes
+    /// becomes the output of the pipeline, so `.run()` prints it and `.output()`
+    /// captures it, and further commands can be piped after it. This avoids
+    /// spawning `grep` or `sed` for transformations that are easier in Rust.
+    ///
+    /// An error returned by the closure fails the pipeline with
+    /// [`CmdError::Io`](crate::CmdError::Io).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::io::BufRead;
+    ///
+    /// let errors = cmd!("journalctl", "-b")
+    ///     .pipe_fn(|input, output| {
+    ///         for line in input.lines() {
+    ///             let line = line?;
+    ///             if line.contains("error") {
+    ///                 writeln!(output, "{}", line)?;
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn pipe_fn<F>(self, f: F) -> Pipeline
+    where
+        F: FnOnce(&mut dyn BufRead, &mut dyn Write) -> std::io::Result<()> + Send + 'static,
+    {
+        self.into_pipeline().pipe_fn(f)
+    }
+
+    /// Copy this command's stdout into `writer` while passing it on unchanged, like `tee`.
+    ///
+    /// The output still flows to the next stage of the pipeline, or is printed or
+    /// captured as usual, so this is handy for observing intermediate output without
+    /// breaking the chain. The copy runs on its own thread and `writer` is flushed once
+    /// the output ends. A failure to write to `writer` fails the pipeline with
+    /// [`CmdError::Io`](crate::CmdError::Io).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::fs::File;
+    ///
+    /// cmd!("generate")
+    ///     .tee(File::create("debug.log")?)
+    ///     .pipe(cmd!("process"))
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn tee(self, writer: impl Write + Send + 'static) -> Pipeline {
+        self.into_pipeline().tee(writer)
+    }
+
+    /// Gzip-compress the command's stdout in-process, like piping it into `gzip`.
+    ///
+    /// Requires the `flate2` feature. The compression runs on a thread as a pipeline
+    /// stage, so data is streamed and the `gzip` binary does not need to be installed.
+    /// The default level 6 is used, as with `gzip`; see
+    /// [`gzip_level`](Self::gzip_level) to choose another one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "flate2")]
+    /// # {
+    /// use scripty::cmd;
+    ///
+    /// cmd!("tar", "-c", "src").gzip().output_to_file("src.tar.gz")?;
+    /// # }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "flate2")]
+    pub fn gzip(self) -> Pipeline {
+        self.into_pipeline().gzip()
+    }
+
+    /// Gzip-compress the command's stdout in-process at the given level.
+    ///
+    /// Requires the `flate2` feature. Levels go from 0 (no compression) to 9 (best
+    /// compression), like `gzip -1` to `gzip -9`; higher values are treated as 9.
+    #[cfg(feature = "flate2")]
+    pub fn gzip_level(self, level: u32) -> Pipeline {
+        self.into_pipeline().gzip_level(level)
+    }
+
+    /// Decompress the command's gzip stdout in-process, like piping it into `gunzip`.
+    ///
+    /// Requires the `flate2` feature. Concatenated gzip members are decompressed one
+    /// after another as `gunzip` does. Invalid data fails the pipeline with
+    /// [`CmdError::Io`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "flate2")]
+    /// # {
+    /// use scripty::cmd;
+    ///
+    /// let log = cmd!("cat", "app.log.gz").gunzip().output()?;
+    /// # }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "flate2")]
+    pub fn gunzip(self) -> Pipeline {
+        self.into_pipeline().gunzip()
+    }
+
+    /// Fall back to `alternative` if this command fails, like `a || b` in a shell.
+    ///
+    /// The alternative only runs if this command fails in any way: it cannot be
+    /// spawned, exits with a non-zero status, is killed by a signal or times out.
+    /// The result is the one of the last command that ran, so `output()` returns the
+    /// output of the command that succeeded, or the alternative's error if both failed.
+    ///
+    /// Combine with [`chain`](Self::chain) for `&&`; the list is evaluated left to
+    /// right as in a shell, so `a.chain(b).or_cmd(c)` runs `c` if either `a` or `b`
+    /// fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let info = cmd!("cat", "/etc/os-release")
+    ///     .or_cmd(cmd!("cat", "/etc/system-release"))
+    ///     .or_cmd(cmd!("uname", "-a"))
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn or_cmd(self, alternative: Cmd) -> CmdList {
+        self.into_list().or_cmd(alternative)
+    }
+
+    /// Run `next` only if this command succeeds, like `a && b` in a shell.
+    ///
+    /// The result is the one of the last command that ran: the error of the first
+    /// failing command, or the result of `next`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("cargo", "build").chain(cmd!("cargo", "test")).run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn chain(self, next: Cmd) -> CmdList {
+        self.into_list().chain(next)
+    }
+
+    fn into_list(self) -> CmdList {
+        CmdList {
+            first: self,
+            rest: Vec::new(),
+        }
+    }
+
+    /// The command line as scripty echoes it, without colors and without running it.
+    ///
+    /// Arguments are quoted the same way as in the echo, which also shows the working
+    /// directory, environment changes, umask, user and group IDs, and redirections,
+    /// e.g. `cd: /tmp env: LANG=C sort 'my file.txt'`. Handy for audit logs and error
+    /// messages. The output is meant for people to read, and is not guaranteed to be
+    /// valid shell syntax. The [`Display`](std::fmt::Display) implementation formats
+    /// the same text, so a command can be embedded with `{}` or `to_string()` directly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scripty::cmd;
+    ///
+    /// let cmd = cmd!("git", "commit", "-m", "fix the build").env("GIT_AUTHOR_NAME", "CI");
+    /// assert_eq!(
+    ///     cmd.to_command_string(),
+    ///     "env: GIT_AUTHOR_NAME=CI git commit -m 'fix the build'"
+    /// );
+    /// assert_eq!(cmd!("grep", "-n", "foo").to_string(), "grep -n foo");
+    /// ```
+    pub fn to_command_string(&self) -> String {
+        self.clone().into_pipeline().to_command_string()
+    }
+
+    /// Run the command and return the exit status.
+    pub fn run(self) -> Result<(), Error> {
+        self.into_pipeline().run()
+    }
+
+    /// Run the command in a fresh temporary directory that is removed afterwards.
+    ///
+    /// The directory is created with [`fs::create_temp_dir`](crate::fs::create_temp_dir)
+    /// and replaces any working directory set with [`current_dir`](Self::current_dir).
+    /// It is removed once the command has finished, whether it succeeded or not. To
+    /// run several commands in the same scratch directory, create a
+    /// [`TempDir`](crate::fs::TempDir) yourself and pass it to `current_dir`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("cargo", "new", "--lib", "probe").run_in_temp()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn run_in_temp(self) -> Result<(), Error> {
+        let dir = crate::fs::create_temp_dir()?;
+        self.current_dir(&dir).run()
+    }
+
+    /// Run the command, terminating it if it has not finished by `deadline`.
+    ///
+    /// This is [`timeout`](Self::timeout) expressed as a point in time, which is handy
+    /// when several steps have to complete before a shared wall-clock deadline. It uses
+    /// the same termination machinery and fails with
+    /// [`CmdError::Timeout`](crate::CmdError::Timeout), reporting the time that was left
+    /// when the command started. A deadline in the past terminates the command right
+    /// away. If a timeout is also set, whichever expires first applies.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(60);
+    /// cmd!("cargo", "build").run_until(deadline)?;
+    /// cmd!("cargo", "test").run_until(deadline)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn run_until(self, deadline: Instant) -> Result<(), Error> {
+        self.into_pipeline().run_until(deadline)
+    }
+
+    /// Run the command every `interval` until it succeeds, giving up at `deadline`.
+    ///
+    /// This is the "wait until the service is ready" loop: the command is run, and if it
+    /// exits with a non-zero status or is killed by a signal, it is run again after
+    /// `interval`, until it exits successfully. It is bounded by time rather than by a
+    /// number of attempts, so use it for conditions that become true on their own; to
+    /// retry a flaky operation, use [`retry`](Self::retry). Each attempt is also bounded
+    /// by `deadline`.
+    ///
+    /// The command line is echoed once; later attempts only echo their number. Errors
+    /// other than a failed attempt, such as a program that cannot be spawned, are
+    /// returned right away. If the deadline passes first,
+    /// [`CmdError::Timeout`] is returned with the time that was allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// cmd!("docker", "compose", "up", "-d").run()?;
+    /// cmd!("pg_isready", "-h", "localhost")
+    ///     .discard_stdout()
+    ///     .poll_until_success(
+    ///         Duration::from_millis(500),
+    ///         Instant::now() + Duration::from_secs(30),
+    ///     )?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn poll_until_success(self, interval: Duration, deadline: Instant) -> Result<(), Error> {
+        let start = Instant::now();
+        let timed_out = || CmdError::Timeout {
+            program: self.program.clone(),
+            timeout: deadline.saturating_duration_since(start),
+        };
+
+        let mut attempt = 1;
+        loop {
+            let cmd = if attempt == 1 {
+                self.clone()
+            } else {
+                if !self.suppress_echo {
+                    let prefix = crate::color::theme().prefix;
+                    crate::output::conditional_eprintln_to(
+                        self.echo_writer.as_ref(),
+                        crate::output::EchoKind::Cmd,
+                        format_args!(
+                            " {prefix}{}:poll attempt {attempt}{prefix:#}",
+                            env!("CARGO_PKG_NAME")
+                        ),
+                    );
+                }
+                self.clone().no_echo()
+            };
+
+            match cmd.run_until(deadline) {
+                Ok(()) => return Ok(()),
+                Err(CmdError::NonZeroExit { .. } | CmdError::Signaled { .. }) => {}
+                Err(CmdError::Timeout { .. }) => return Err(timed_out()),
+                Err(e) => return Err(e),
+            }
+
+            if Instant::now() + interval >= deadline {
+                return Err(timed_out());
+            }
+            std::thread::sleep(interval);
+            attempt += 1;
+        }
+    }
+
+    /// Run the command again when it fails, up to `attempts` runs in total.
+    ///
+    /// Meant for commands that fail now and then, such as `curl`, `apt-get` or
+    /// `git fetch` on a flaky network. A run that exits with a non-zero status or is
+    /// killed by a signal is retried after `backoff`, and the delay doubles for every
+    /// further attempt. Other errors, such as a program that cannot be spawned, are
+    /// returned right away, and so is the error of the last attempt. Use
+    /// [`retry_if`](Self::retry_if) to choose which errors are retried. `attempts` is at
+    /// least 1.
+    ///
+    /// The command line is echoed once; later attempts only echo their number. Run the
+    /// returned [`Retry`] with `run()`, `output()` or `output_bytes()`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::Duration;
+    ///
+    /// cmd!("git", "fetch", "origin")
+    ///     .retry(3, Duration::from_secs(1))
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn retry(self, attempts: usize, backoff: Duration) -> Retry {
+        Retry {
+            cmd: self,
+            attempts: attempts.max(1),
+            backoff,
+            predicate: None,
+        }
+    }
+
+    /// Run the command again when it fails with an error `predicate` accepts.
+    ///
+    /// Like [`retry`](Self::retry), but any error for which `predicate` returns `true` is
+    /// retried, and no other.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::{CmdError, cmd};
+    /// use std::time::Duration;
+    ///
+    /// // curl exits with 7 when it cannot connect, which is worth retrying
+    /// let page = cmd!("curl", "-fsS", "http://localhost:8080/health")
+    ///     .retry_if(5, Duration::from_millis(200), |err| {
+    ///         matches!(err, CmdError::NonZeroExit { status, .. } if status.code() == Some(7))
+    ///     })
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn retry_if(
+        self,
+        attempts: usize,
+        backoff: Duration,
+        predicate: impl Fn(&CmdError) -> bool + 'static,
+    ) -> Retry {
+        Retry {
+            predicate: Some(Box::new(predicate)),
+            ..self.retry(attempts, backoff)
+        }
+    }
+
+    /// Run the command and return its exit status.
+    ///
+    /// Unlike [`run`](Self::run), a non-zero exit status is not treated as an error:
+    /// `Err` is only returned when the command cannot be spawned or waited for.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let status = cmd!("grep", "-q", "pattern", "file.txt").status()?;
+    /// match status.code() {
+    ///     Some(0) => println!("found"),
+    ///     Some(1) => println!("not found"),
+    ///     code => println!("grep failed: {:?}", code),
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn status(self) -> Result<std::process::ExitStatus, Error> {
+        self.into_pipeline().status()
+    }
+
+    /// Run the command and return its exit code.
+    ///
+    /// Like [`status`](Self::status), a non-zero exit code is not treated as an error,
+    /// for tools that report their result through it. A command terminated by a signal
+    /// has no exit code; `128 + signal` is returned instead, as the shell's `$?` does,
+    /// e.g. `137` for `SIGKILL`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// if cmd!("grep", "-q", "pattern", "file.txt").status_code()? == 0 {
+    ///     println!("found");
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn status_code(self) -> Result<i32, Error> {
+        self.into_pipeline().status_code()
+    }
+
+    /// Get binary output from the command.
+    pub fn output_bytes(self) -> Result<Vec<u8>, Error> {
+        self.into_pipeline().output_bytes()
+    }
+
+    /// Get binary output from the command, failing if it is longer than `max` bytes.
+    ///
+    /// A safety valve for programs whose output size is unpredictable: at most
+    /// `max + 1` bytes are ever held in memory. As soon as the output grows past `max`,
+    /// reading stops and [`CmdError::OutputLimit`] is returned. The pipe is closed
+    /// as with `| head -c`, so the command usually dies of `SIGPIPE` on its next write.
+    /// Use [`output_bytes_truncated`](Self::output_bytes_truncated) to keep the first
+    /// `max` bytes instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let page = cmd!("curl", "-s", "https://example.com").output_bytes_limited(1 << 20)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_bytes_limited(self, max: usize) -> Result<Vec<u8>, Error> {
+        self.into_pipeline().output_bytes_limited(max)
+    }
+
+    /// Get at most `max` bytes of the command's output, and whether any were cut off.
+    ///
+    /// Like [`output_bytes_limited`](Self::output_bytes_limited), but output beyond
+    /// the limit is dropped instead of failing: the flag is `true` when the command
+    /// printed more than `max` bytes. The exit status of a command whose output was
+    /// cut off is not checked, since it was stopped early on purpose.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let (head, truncated) = cmd!("journalctl", "-b").output_bytes_truncated(64 * 1024)?;
+    /// if truncated {
+    ///     println!("showing the first 64 KiB only");
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_bytes_truncated(self, max: usize) -> Result<(Vec<u8>, bool), Error> {
+        self.into_pipeline().output_bytes_truncated(max)
+    }
+
+    /// Get text output from the command.
+    ///
+    /// `Ok` is only returned once the command has run and exited successfully, so
+    /// `Ok("")` always means a successful command that printed nothing. A program that
+    /// cannot be found is a [`CmdError::Spawn`] and a failing one a
+    /// [`CmdError::NonZeroExit`], never an empty string.
+    pub fn output(self) -> Result<String, Error> {
+        self.into_pipeline().output()
+    }
+
+    /// Get text output from the command together with how long it took to run.
+    ///
+    /// The duration is wall-clock time from spawning the command until its output has
+    /// been read and it has exited. Failures are reported as by [`output`](Self::output).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let (_, took) = cmd!("cargo", "build", "--release").measure()?;
+    /// println!("release build took {:.1?}", took);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn measure(self) -> Result<(String, Duration), Error> {
+        self.into_pipeline().measure()
+    }
+
+    /// Run the command from async code, like [`run`](Self::run).
+    ///
+    /// Requires the `tokio` feature and must be awaited within a Tokio runtime. The
+    /// command is run on Tokio's blocking thread pool, so the runtime's worker threads
+    /// stay free while it runs, and everything the builder supports, from pipelines to
+    /// timeouts, works the same. Each running command occupies one thread of that pool,
+    /// which is limited to 512 threads by default.
+    ///
+    /// Dropping the future, e.g. when its task is aborted or it loses a `select!`,
+    /// terminates the command the same way as a [`timeout`](Self::timeout).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "tokio")]
+    /// # async fn deploy() -> Result<(), scripty::CmdError> {
+    /// use scripty::cmd;
+    ///
+    /// cmd!("git", "pull").run_async().await?;
+    /// let head = cmd!("git", "rev-parse", "HEAD").output_async().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<(), Error> {
+        self.into_pipeline().run_async().await
     }
 
-    /// Set the working directory.
-    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
-        self.current_dir = Some(dir.as_ref().to_path_buf());
-        self
+    /// Get text output from the command from async code, like [`output`](Self::output).
+    ///
+    /// Requires the `tokio` feature; see [`run_async`](Self::run_async) for details.
+    #[cfg(feature = "tokio")]
+    pub async fn output_async(self) -> Result<String, Error> {
+        self.into_pipeline().output_async().await
     }
 
-    /// Convert this command into a single-command pipeline.
-    pub(crate) fn into_pipeline(self) -> Pipeline {
-        let suppress_echo = self.suppress_echo;
-        Pipeline {
-            connections: vec![(self, PipeMode::Stdout)],
-            input: None,
-            suppress_echo,
-        }
+    /// Get binary output from the command from async code, like
+    /// [`output_bytes`](Self::output_bytes).
+    ///
+    /// Requires the `tokio` feature; see [`run_async`](Self::run_async) for details.
+    #[cfg(feature = "tokio")]
+    pub async fn output_bytes_async(self) -> Result<Vec<u8>, Error> {
+        self.into_pipeline().output_bytes_async().await
     }
 
-    /// Set binary input data for the command.
-    /// Accepts `Vec<u8>`, `&[u8]`, or other types that can be converted to `Vec<u8>`.
-    pub fn input_bytes(self, input: impl AsRef<[u8]>) -> Pipeline {
-        self.into_pipeline().input_bytes(input)
+    /// Get text output from the command, or `None` if the program is not installed.
+    ///
+    /// Handy for optional tools: a program that cannot be found yields `Ok(None)`
+    /// instead of [`CmdError::Spawn`]. Every other failure, including a non-zero exit
+    /// or a program that exists but cannot be executed, is still an `Err`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// match cmd!("git", "describe", "--tags").output_opt()? {
+    ///     Some(version) => println!("version {}", version.trim()),
+    ///     None => println!("git is not installed"),
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_opt(self) -> Result<Option<String>, Error> {
+        self.into_pipeline().output_opt()
     }
 
-    /// Set text input for the command.
-    /// Optimized to convert string directly to bytes without intermediate allocation.
-    pub fn input(self, input: impl AsRef<str>) -> Pipeline {
-        self.into_pipeline().input(input)
+    /// Get text output from the command as a list of lines.
+    ///
+    /// Stdout is split on `\n` (or `\r\n`) with the line endings removed, and the
+    /// final newline does not produce a trailing empty line, so `ls` gives exactly one
+    /// entry per file. Invalid UTF-8 is replaced as with [`output`](Self::output).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// for file in cmd!("git", "ls-files", "*.rs").output_lines()? {
+    ///     println!("{file}");
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_lines(self) -> Result<Vec<String>, Error> {
+        self.into_pipeline().output_lines()
     }
 
-    /// Run without echoing the command.
-    pub fn no_echo(mut self) -> Self {
-        self.suppress_echo = true;
-        self
+    /// Get text output from the command with leading and trailing whitespace removed.
+    ///
+    /// Meant for commands that print a single value, where the final newline is noise.
+    /// Use [`output`](Self::output) when the exact output matters.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let commit = cmd!("git", "rev-parse", "HEAD").output_trimmed()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_trimmed(self) -> Result<String, Error> {
+        self.into_pipeline().output_trimmed()
     }
 
-    /// Pipe this command's stdout to another command's stdin.
+    /// Run the command and deserialize its stdout as JSON.
     ///
-    /// This is the standard Unix pipe behavior where stdout becomes stdin.
+    /// Requires the `serde` feature. Handy for tools such as `docker inspect` or
+    /// `kubectl get -o json`. Output that is not valid JSON for `T` fails with
+    /// [`CmdError::Json`], whose message includes the part of the output where
+    /// parsing stopped.
     ///
     /// # Examples
     ///
     /// ```no_run
+    /// # #[cfg(feature = "serde")]
+    /// # {
     /// use scripty::cmd;
+    /// use std::collections::HashMap;
     ///
-    /// // Standard pipe (stdout → stdin)
-    /// let output = cmd!("echo", "hello")
-    ///     .pipe(cmd!("tr", "[:lower:]", "[:upper:]"))
-    ///     .output()?;
+    /// let metadata: serde_json::Value =
+    ///     cmd!("cargo", "metadata", "--format-version", "1").output_json()?;
+    /// let labels: HashMap<String, String> =
+    ///     cmd!("docker", "inspect", "-f", "{{json .Config.Labels}}", "app").output_json()?;
+    /// # }
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn pipe(self, next: Cmd) -> Pipeline {
-        let suppress_echo = self.suppress_echo || next.suppress_echo;
-        Pipeline {
-            connections: vec![(self, PipeMode::Stdout), (next, PipeMode::Stdout)],
-            input: None,
-            suppress_echo,
-        }
+    #[cfg(feature = "serde")]
+    pub fn output_json<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        self.into_pipeline().output_json()
     }
 
-    /// Pipe this command's stderr to another command's stdin.
+    /// Get text output from the command with ANSI escape sequences removed.
     ///
-    /// This pipes the error output stream to the next command's input,
-    /// useful for error processing and filtering workflows.
+    /// Useful for tools that force colored output, e.g. when storing clean logs or
+    /// parsing the result. Escape sequences are only stripped, not interpreted.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// use scripty::cmd;
     ///
-    /// // Process error messages through a pipeline
-    /// let error_count = cmd!("sh", "-c", "echo 'ERROR: failed' >&2")
-    ///     .pipe_err(cmd!("wc", "-l"))
-    ///     .output()?;
+    /// let log = cmd!("ls", "--color=always").output_plain()?;
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn pipe_err(self, next: Cmd) -> Pipeline {
-        let suppress_echo = self.suppress_echo || next.suppress_echo;
-        Pipeline {
-            connections: vec![(self, PipeMode::Stdout), (next, PipeMode::Stderr)],
-            input: None,
-            suppress_echo,
-        }
+    pub fn output_plain(self) -> Result<String, Error> {
+        self.into_pipeline().output_plain()
     }
 
-    /// Pipe this command's combined stdout and stderr to another command's stdin.
+    /// Run the command and capture its stdout, stderr and exit status in one go.
     ///
-    /// This merges both output streams and pipes them to the next command,
-    /// useful for unified processing of all command output.
+    /// Unlike [`output`](Self::output), a non-zero exit status is never treated as an
+    /// error; the full [`Output`] is returned so the caller decides. `Err` is only
+    /// returned for spawn or I/O failures.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// use scripty::cmd;
     ///
-    /// // Sort all output (both stdout and stderr)
-    /// let sorted_output = cmd!("sh", "-c", "echo 'out'; echo 'err' >&2")
-    ///     .pipe_out_err(cmd!("sort"))
-    ///     .output()?;
+    /// let out = cmd!("cargo", "test").capture()?;
+    /// if !out.status.success() {
+    ///     eprintln!("tests failed ({}):\n{}", out.status, out.stderr_str());
+    /// }
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    pub fn pipe_out_err(self, next: Cmd) -> Pipeline {
-        let suppress_echo = self.suppress_echo || next.suppress_echo;
-        Pipeline {
-            connections: vec![(self, PipeMode::Stdout), (next, PipeMode::Both)],
-            input: None,
-            suppress_echo,
-        }
+    pub fn capture(self) -> Result<Output, Error> {
+        self.into_pipeline().capture()
     }
 
-    /// Run the command and return the exit status.
-    pub fn run(self) -> Result<(), Error> {
-        self.into_pipeline().run()
+    /// Get text output from the command together with its exit status.
+    ///
+    /// For tools whose non-zero exit codes carry meaning, such as `diff` or `grep`: the
+    /// status is returned whatever its value, and `Err` is only returned for spawn or
+    /// I/O failures. Stderr is captured and dropped; use [`capture`](Self::capture) to
+    /// keep it as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let (diff, status) = cmd!("diff", "-u", "expected.txt", "actual.txt").output_with_status()?;
+    /// match status.code() {
+    ///     Some(0) => println!("files are identical"),
+    ///     Some(1) => print!("{}", diff),
+    ///     _ => eprintln!("diff failed: {}", status),
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_with_status(self) -> Result<(String, std::process::ExitStatus), Error> {
+        self.into_pipeline().output_with_status()
     }
 
-    /// Get binary output from the command.
-    pub fn output_bytes(self) -> Result<Vec<u8>, Error> {
-        self.into_pipeline().output_bytes()
+    /// Capture what the command writes to stdout during `window`, then stop it.
+    ///
+    /// Meant for sampling commands that never finish on their own, such as `vmstat 1`
+    /// or `tail -f`. At the end of the window the command is terminated like on a
+    /// [`timeout`](Self::timeout), and the output collected so far is returned. Being
+    /// stopped this way is the expected outcome, so the resulting signal status is not
+    /// reported as an error.
+    ///
+    /// If the command exits on its own before the window ends, its output is returned
+    /// right away, and a non-zero exit is an error as with [`output`](Self::output).
+    /// A [`timeout`](Self::timeout) shorter than the window still fails with
+    /// [`CmdError::Timeout`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::time::Duration;
+    ///
+    /// let sample = cmd!("vmstat", "1").capture_for(Duration::from_secs(5))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn capture_for(self, window: Duration) -> Result<String, Error> {
+        self.into_pipeline().capture_for(window)
     }
 
-    /// Get text output from the command.
-    pub fn output(self) -> Result<String, Error> {
-        self.into_pipeline().output()
+    /// Feed `input` to the command and return its stdout together with its exit status.
+    ///
+    /// This is the `echo data | cmd` filter pattern. Unlike [`output`](Self::output),
+    /// which returns an error for a non-zero exit status, `filter` hands the status back
+    /// so it can be inspected; `Err` is only returned for spawn or I/O failures.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let (matches, status) = cmd!("grep", "error").filter("ok\nerror: disk full\n")?;
+    /// if status.success() {
+    ///     print!("{}", matches);
+    /// } else {
+    ///     println!("no errors found");
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn filter(
+        self,
+        input: impl AsRef<[u8]>,
+    ) -> Result<(String, std::process::ExitStatus), Error> {
+        self.into_pipeline().filter(input)
     }
 
     /// Stream command's stdout to a Writer.
@@ -171,6 +1730,64 @@ impl Cmd {
         self.into_pipeline().write_to(writer)
     }
 
+    /// Stream the command's stdout as frames of exactly `size` bytes.
+    ///
+    /// Suited to binary output made of fixed-size records, which can be parsed one
+    /// at a time without loading the whole output into memory. The command is
+    /// spawned right away and each call to `next` reads one more frame.
+    ///
+    /// If the output length is not a multiple of `size`, the last frame is shorter
+    /// than `size` and holds the remaining bytes. Call [`Frames::strict`] to get a
+    /// [`CmdError::Io`](crate::CmdError::Io) for a trailing partial frame instead.
+    /// Once the output ends the command's exit status is checked, and a failure is
+    /// yielded as the last item. Dropping the iterator early closes the command's
+    /// stdout and waits for it to exit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// for frame in cmd!("cat", "samples.bin").frames(8)?.strict() {
+    ///     let sample = u64::from_le_bytes(frame?.try_into().unwrap());
+    ///     println!("{}", sample);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn frames(self, size: usize) -> Result<Frames, Error> {
+        self.into_pipeline().frames(size)
+    }
+
+    /// Write the command's stdout to a file, like `cmd > path` in a shell.
+    ///
+    /// The file is created, or truncated if it exists, and the output is streamed into
+    /// it without being buffered in memory. Stderr is inherited. If the file cannot be
+    /// opened, [`CmdError::OutputFile`](crate::CmdError::OutputFile) names the path.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// cmd!("git", "log", "--oneline").output_to_file("history.txt")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn output_to_file(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.into_pipeline().output_to_file(path)
+    }
+
+    /// Append the command's stdout to a file, like `cmd >> path` in a shell.
+    ///
+    /// The file is created if it does not exist. Otherwise this behaves like
+    /// [`output_to_file`](Self::output_to_file).
+    pub fn append_to_file(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.into_pipeline().append_to_file(path)
+    }
+
     /// Stream command's stderr to a Writer.
     /// This is useful for capturing error output separately.
     pub fn write_err_to<W: Write>(self, writer: W) -> Result<(), Error> {
@@ -185,6 +1802,11 @@ impl Cmd {
 
     /// Run the command with both input Reader and output Writer.
     /// This is the most flexible method for streaming I/O.
+    ///
+    /// The reader is fed on a detached thread, so a reader that blocks, like
+    /// `io::stdin()`, cannot keep this method from returning once the command exits.
+    /// See [`run_with_io_scoped`](Self::run_with_io_scoped) for a reader that borrows
+    /// local data.
     pub fn run_with_io<R: Read + Send + 'static, W: Write>(
         self,
         reader: R,
@@ -193,6 +1815,29 @@ impl Cmd {
         self.into_pipeline().run_with_io(reader, writer)
     }
 
+    /// Like [`run_with_io`](Self::run_with_io), but the reader may borrow local data.
+    ///
+    /// The reader is fed on a scoped thread that is joined before this method returns,
+    /// so it must end on its own or stop once the command closes its stdin.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let names = String::from("zebra\napple\n");
+    /// let mut sorted = Vec::new();
+    /// cmd!("sort").run_with_io_scoped(names.as_bytes(), &mut sorted)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn run_with_io_scoped<R: Read + Send, W: Write>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        self.into_pipeline().run_with_io_scoped(reader, writer)
+    }
+
     /// Run the command with input Reader and stderr Writer.
     /// This is useful for processing data while capturing error output.
     pub fn run_with_err_io<R: Read + Send + 'static, W: Write>(
@@ -203,6 +1848,16 @@ impl Cmd {
         self.into_pipeline().run_with_err_io(reader, writer)
     }
 
+    /// Like [`run_with_err_io`](Self::run_with_err_io), but the reader may borrow local
+    /// data, see [`run_with_io_scoped`](Self::run_with_io_scoped).
+    pub fn run_with_err_io_scoped<R: Read + Send, W: Write>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        self.into_pipeline().run_with_err_io_scoped(reader, writer)
+    }
+
     /// Run the command with input Reader and combined stdout+stderr Writer.
     /// This merges both output streams for comprehensive logging.
     pub fn run_with_both_io<R: Read + Send + 'static, W: Write + Send + 'static>(
@@ -213,6 +1868,96 @@ impl Cmd {
         self.into_pipeline().run_with_both_io(reader, writer)
     }
 
+    /// Like [`run_with_both_io`](Self::run_with_both_io), but the reader and the writer
+    /// may borrow local data, see [`run_with_io_scoped`](Self::run_with_io_scoped).
+    pub fn run_with_both_io_scoped<R: Read + Send, W: Write + Send>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        self.into_pipeline().run_with_both_io_scoped(reader, writer)
+    }
+
+    /// Start the command in the background and return without waiting for it.
+    ///
+    /// The command is echoed as usual and inherits the parent's stdout and stderr, so
+    /// a background job such as a development server logs to the terminal. Its stdin
+    /// is closed unless [`input`](Self::input) or friends provide one. Use the
+    /// returned handle to [`wait`](PipelineHandle::wait) for it, to
+    /// [`kill`](PipelineHandle::kill) it, to send it a [`signal`](PipelineHandle::signal)
+    /// on Unix or to get its [`pids`](PipelineHandle::pids).
+    /// A handle that is dropped leaves the process running.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let server = cmd!("python3", "-m", "http.server", "8000").spawn()?;
+    /// cmd!("curl", "--retry", "5", "--retry-connrefused", "http://localhost:8000/").run()?;
+    /// server.kill();
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn spawn(self) -> Result<PipelineHandle, Error> {
+        self.into_pipeline().spawn()
+    }
+
+    /// Feed lines to the command and call `on_line` with every line it prints.
+    ///
+    /// A higher-level take on [`spawn_io_in_out`](Self::spawn_io_in_out) for
+    /// line-oriented tools: the lines are written to stdin, each followed by a newline,
+    /// on a thread of their own while stdout is read line by line in the current one.
+    /// Output lines are passed without their line ending, with invalid UTF-8 replaced.
+    /// The input may be produced lazily and borrow local data; writing stops early if
+    /// the command exits without reading all of it. Stderr goes to the terminal.
+    ///
+    /// Returns once the command has exited, with an error if it failed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let mut results = Vec::new();
+    /// cmd!("bc", "-l").interact(["scale=2", "22/7", "2^10"], |line| results.push(line))?;
+    /// assert_eq!(results, ["3.14", "1024"]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn interact<I, S>(self, lines: I, on_line: impl FnMut(String)) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = S>,
+        I::IntoIter: Send,
+        S: AsRef<str>,
+    {
+        self.into_pipeline().interact(lines, on_line)
+    }
+
+    /// Choose whether dropping the handle of the spawned command terminates it.
+    ///
+    /// Off by default: a [`PipelineHandle`] dropped without [`wait`](PipelineHandle::wait)
+    /// leaves the process running, which is what [`spawn`](Self::spawn) is for. When
+    /// enabled, processes still running when the handle is dropped, e.g. because a panic
+    /// unwinds before `wait()`, are stopped as with [`PipelineHandle::kill`]. This keeps
+    /// long-lived interactive children such as `bc` or a shell from being orphaned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::io::Write;
+    ///
+    /// let (handle, stdin, stdout) = cmd!("bc", "-l").kill_on_drop(true).spawn_io_in_out()?;
+    /// // If anything below panics or returns early, bc is terminated with `handle`
+    /// writeln!(stdin.unwrap(), "2^10")?;
+    /// # drop(stdout);
+    /// handle.wait()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn kill_on_drop(mut self, enabled: bool) -> Self {
+        self.kill_on_drop = enabled;
+        self
+    }
+
     /// Spawn the command with full I/O control.
     pub fn spawn_io_all(self) -> Result<PipelineSpawn, Error> {
         self.into_pipeline().spawn_io_all()
@@ -355,4 +2100,117 @@ impl Cmd {
         // No quoting needed
         arg_str.to_string()
     }
+
+    /// Split a command line into words like a POSIX shell, without any expansion.
+    ///
+    /// Malformed input is split leniently; the problem found, if any, is returned
+    /// alongside the words.
+    pub(crate) fn split_words(s: &str) -> (Vec<String>, Option<&'static str>) {
+        let mut words = Vec::new();
+        let mut word = String::new();
+        let mut problem = None;
+        // Whether a word has started, so that `''` yields an empty argument
+        let mut in_word = false;
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' | '\t' | '\n' => {
+                    if in_word {
+                        words.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                }
+                '\'' => {
+                    in_word = true;
+                    problem = Some("unterminated single quote");
+                    for c in chars.by_ref() {
+                        if c == '\'' {
+                            problem = None;
+                            break;
+                        }
+                        word.push(c);
+                    }
+                }
+                '"' => {
+                    in_word = true;
+                    problem = Some("unterminated double quote");
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => {
+                                problem = None;
+                                break;
+                            }
+                            '\\' => match chars.next() {
+                                Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                                Some('\n') => {}
+                                Some(c) => {
+                                    word.push('\\');
+                                    word.push(c);
+                                }
+                                None => word.push('\\'),
+                            },
+                            c => word.push(c),
+                        }
+                    }
+                }
+                '\\' => match chars.next() {
+                    // A line continuation
+                    Some('\n') => {}
+                    Some(c) => {
+                        in_word = true;
+                        word.push(c);
+                    }
+                    None => {
+                        in_word = true;
+                        word.push('\\');
+                        problem = Some("trailing backslash");
+                    }
+                },
+                c => {
+                    in_word = true;
+                    word.push(c);
+                }
+            }
+        }
+        if in_word {
+            words.push(word);
+        }
+        (words, problem)
+    }
+}
+
+/// Formats the command as [`to_command_string`](Cmd::to_command_string) does.
+impl std::fmt::Display for Cmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_command_string())
+    }
+}
+
+/// Run commands one after another and concatenate their stdout in order.
+///
+/// The commands run sequentially, each to completion before the next one starts,
+/// so the output of one never interleaves with another's. Each command is echoed
+/// as it runs, as with [`Cmd::output`].
+///
+/// Stops at the first command that fails and returns its error; the remaining
+/// commands are not run.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// let report = concat_outputs([
+///     cmd!("git", "log", "--oneline", "-5"),
+///     cmd!("git", "status", "--short"),
+/// ])?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn concat_outputs(cmds: impl IntoIterator<Item = Cmd>) -> Result<String, Error> {
+    let mut output = Vec::new();
+    for cmd in cmds {
+        output.extend(cmd.output_bytes()?);
+    }
+    Ok(String::from_utf8_lossy(&output).to_string())
 }
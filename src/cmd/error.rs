@@ -1,33 +0,0 @@
-//! Error handling for command execution.
-
-/// Command execution error.
-#[derive(Debug)]
-pub struct Error {
-    pub(crate) message: String,
-    pub(crate) source: Option<std::io::Error>,
-}
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)?;
-        if let Some(source) = &self.source {
-            write!(f, ": {}", source)?;
-        }
-        Ok(())
-    }
-}
-
-impl std::error::Error for Error {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        self.source.as_ref().map(|e| e as &dyn std::error::Error)
-    }
-}
-
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error {
-            message: "Command execution failed".to_string(),
-            source: Some(err),
-        }
-    }
-}
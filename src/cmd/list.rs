@@ -0,0 +1,57 @@
+//! Shell-style `&&` and `||` lists of commands.
+
+use super::types::{Cmd, CmdList, ListOp};
+use crate::error::{CmdError, Error};
+
+impl CmdList {
+    /// Run `alternative` if everything before it failed, like `a || b` in a shell.
+    ///
+    /// See [`Cmd::or_cmd`].
+    pub fn or_cmd(mut self, alternative: Cmd) -> Self {
+        self.rest.push((ListOp::Or, alternative));
+        self
+    }
+
+    /// Run `next` if everything before it succeeded, like `a && b` in a shell.
+    ///
+    /// See [`Cmd::chain`].
+    pub fn chain(mut self, next: Cmd) -> Self {
+        self.rest.push((ListOp::And, next));
+        self
+    }
+
+    /// Run the commands, returning the result of the last one that ran.
+    pub fn run(self) -> Result<(), Error> {
+        self.exec(Cmd::run)
+    }
+
+    /// Run the commands and return the text output of the last one that ran.
+    ///
+    /// Only that command's stdout is returned; the output of commands that ran
+    /// before it is discarded.
+    pub fn output(self) -> Result<String, Error> {
+        self.exec(Cmd::output)
+    }
+
+    /// Run the commands and return the binary output of the last one that ran.
+    pub fn output_bytes(self) -> Result<Vec<u8>, Error> {
+        self.exec(Cmd::output_bytes)
+    }
+
+    /// Evaluate the list left to right as a shell does: a command is skipped when its
+    /// operator does not match the outcome so far, which is left unchanged.
+    ///
+    /// A command declined at the confirmation prompt ends the list, since it neither
+    /// succeeded nor failed.
+    fn exec<T>(self, mut f: impl FnMut(Cmd) -> Result<T, Error>) -> Result<T, Error> {
+        let mut result = f(self.first);
+        for (op, cmd) in self.rest {
+            match (op, &result) {
+                (_, Err(CmdError::Skipped { .. })) => break,
+                (ListOp::And, Ok(_)) | (ListOp::Or, Err(_)) => result = f(cmd),
+                _ => {}
+            }
+        }
+        result
+    }
+}
@@ -1,6 +1,24 @@
 //! Macros for convenient command creation.
 
 /// Macro to create a new command.
+///
+/// `cmd!(program, arg1, arg2, ...)` is shorthand for
+/// [`Cmd::new(program)`](crate::Cmd::new)`.arg(arg1).arg(arg2)...`, so each argument
+/// can be anything that converts to an `OsStr`, such as `&str`, `String` or `&Path`.
+/// To add a list of arguments built at run time, follow the macro, or
+/// [`Cmd::new`](crate::Cmd::new), with [`args`](crate::Cmd::args).
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// cmd!("ls", "-la", "/tmp").run()?;
+///
+/// let files: Vec<String> = vec!["a.txt".into(), "b.txt".into()];
+/// cmd!("wc", "-l").args(&files).run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 #[macro_export]
 macro_rules! cmd {
     ($program:expr) => {
@@ -10,3 +28,25 @@ macro_rules! cmd {
         $crate::Cmd::new($program)$(.arg($arg))*
     };
 }
+
+/// Macro to create a new command that is not echoed.
+///
+/// Takes the same arguments as [`cmd!`](crate::cmd!) and is shorthand for
+/// `cmd!(...).no_echo()`. Meant for libraries that run commands internally and
+/// should not write to the stderr of the application using them; unlike the
+/// `NO_ECHO` environment variable, it leaves the application's own commands alone.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// let branch = cmd_quiet!("git", "rev-parse", "--abbrev-ref", "HEAD").output()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[macro_export]
+macro_rules! cmd_quiet {
+    ($($tokens:tt)*) => {
+        $crate::cmd!($($tokens)*).no_echo()
+    };
+}
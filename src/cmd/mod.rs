@@ -1,14 +1,18 @@
 //! Simple command execution and piping functionality.
 
 mod command;
-mod error;
+mod list;
 mod macros;
 mod pipeline;
+mod retry;
 mod types;
 
 // Re-export public API
-pub use error::Error;
-pub use types::{Cmd, Pipeline, PipelineHandle, PipelineSpawn};
+pub use command::concat_outputs;
+pub use types::{
+    Cmd, CmdList, Frames, Output, Pipeline, PipelineHandle, PipelineOutput, PipelineSpawn, Retry,
+    StageOutput,
+};
 
 // Internal items for testing and io_ext
 pub(crate) use types::CmdInput;
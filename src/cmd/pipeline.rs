@@ -1,26 +1,725 @@
 //! Pipeline implementation and execution logic.
 
-use crate::cmd::{error::Error, types::*};
-use crate::style::*;
-use std::io::{BufReader, Read, Write};
-use std::process::{Child, Command as StdCommand, Stdio};
+use crate::cmd::types::*;
+use crate::color::Theme;
+use crate::error::{CmdError, Error};
+use crate::trace::Trace;
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, PipeReader, PipeWriter, Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, OwnedFd};
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+#[cfg(windows)]
+use std::os::windows::io::OwnedHandle;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command as StdCommand, ExitStatus, Stdio};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default capacity of the buffers a closure stage reads and writes through.
+pub(crate) const STAGE_BUFFER_SIZE: usize = 8 * 1024;
+
+/// How often a pipeline with a timeout is polled for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long a timed-out process gets to exit after `SIGTERM` before it is killed.
+#[cfg(unix)]
+const KILL_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+/// How long the I/O threads of a timed-out pipeline get to finish before they are detached.
+const THREAD_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Send `SIGTERM` to the given children, `SIGKILL` any that outlive the grace period,
+/// and reap them all.
+///
+/// A child that leads its own process group, e.g. after [`Cmd::process_group`], is
+/// signaled together with its whole group, so that its own children go too.
+///
+/// Windows has no `SIGTERM`, so there the children are killed with `TerminateProcess`
+/// right away.
+fn terminate(children: &mut [Child], indices: &[usize]) {
+    #[cfg(unix)]
+    let grace_end = {
+        for &i in indices {
+            signal_child(&children[i], libc::SIGTERM);
+        }
+        Instant::now() + KILL_GRACE_PERIOD
+    };
+    #[cfg(not(unix))]
+    let grace_end = Instant::now();
+
+    for &i in indices {
+        let child = &mut children[i];
+        while matches!(child.try_wait(), Ok(None)) && Instant::now() < grace_end {
+            thread::sleep(POLL_INTERVAL);
+        }
+        if matches!(child.try_wait(), Ok(None)) {
+            #[cfg(unix)]
+            signal_child(child, libc::SIGKILL);
+            #[cfg(not(unix))]
+            let _ = child.kill();
+        }
+        let _ = child.wait();
+    }
+}
+
+/// Copy `reader` to a `writer` shared with another stream, chunk by chunk.
+///
+/// The lock is only held while a chunk is written, so the other stream keeps flowing.
+/// Holding it for the whole copy would deadlock a command that fills one pipe while
+/// the other stream waits for the lock.
+fn copy_interleaved(mut reader: impl Read, writer: &Mutex<impl Write>) -> std::io::Result<()> {
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let mut writer = writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        writer.write_all(&buf[..n])?;
+    }
+}
+
+/// Join a thread that feeds or reads a stopped pipeline, or detach it if it is still
+/// running at `grace_end`.
+///
+/// The stopped commands have closed their ends of the pipes, so the thread normally
+/// finishes right away. A surviving grandchild may still hold a pipe open though, and
+/// the thread is not waited for in that case.
+fn join_or_detach<T>(handle: Option<thread::JoinHandle<T>>, grace_end: Instant) {
+    let Some(handle) = handle else {
+        return;
+    };
+    while !handle.is_finished() && Instant::now() < grace_end {
+        thread::sleep(POLL_INTERVAL);
+    }
+    if handle.is_finished() {
+        let _ = handle.join();
+    }
+}
+
+/// Send `signal` to a child, or to its whole process group if it leads one.
+#[cfg(unix)]
+fn signal_child(child: &Child, signal: libc::c_int) {
+    let pid = child.id() as libc::pid_t;
+    // SAFETY: the child has not been reaped yet, so its pid is still valid.
+    unsafe {
+        let target = if libc::getpgid(pid) == pid { -pid } else { pid };
+        libc::kill(target, signal);
+    }
+}
+
+/// Forward the parent's stdin to `child_stdin` unless nothing arrives within `timeout`.
+///
+/// Stops, closing the child's stdin, when no data arrived in time, when the parent's
+/// stdin reaches end of file, or when the child closes its end of the pipe.
+#[cfg(unix)]
+fn forward_stdin(child_stdin: &mut ChildStdin, timeout: Duration) {
+    let give_up = Instant::now() + timeout;
+    let mut received = false;
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let wait_ms = if received {
+            -1
+        } else {
+            let remaining = give_up.saturating_duration_since(Instant::now());
+            remaining.as_millis().min(i32::MAX as u128) as libc::c_int
+        };
+        let mut fds = [
+            libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            // Only errors are polled for: POLLERR is raised once the child's end is closed
+            libc::pollfd {
+                fd: child_stdin.as_raw_fd(),
+                events: 0,
+                revents: 0,
+            },
+        ];
+
+        // SAFETY: `fds` is a valid array of two pollfd structs for the whole call.
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), 2, wait_ms) };
+        if ready < 0 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        if ready == 0 || fds[1].revents != 0 {
+            return;
+        }
+
+        // Read straight from the descriptor: the buffered `Stdin` could hold data
+        // that poll does not see.
+        // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+        let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr().cast(), buf.len()) };
+        if n < 0 {
+            if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        if n == 0 || child_stdin.write_all(&buf[..n as usize]).is_err() {
+            return;
+        }
+        received = true;
+    }
+}
+
+/// Number of bytes moved into a pipe at most by one `splice` call.
+#[cfg(target_os = "linux")]
+const SPLICE_CHUNK: usize = 1 << 16;
+
+/// Total number of bytes moved by `splice`, to check that the fast path is taken.
+#[cfg(all(test, target_os = "linux"))]
+pub(crate) static SPLICED_BYTES: std::sync::atomic::AtomicU64 =
+    std::sync::atomic::AtomicU64::new(0);
+
+/// Copy an input file into the child's stdin.
+///
+/// On Linux the data is moved from the file into the pipe with `splice`, without
+/// copying it through user space. Whatever `splice` does not support, e.g. files on
+/// some special file systems, falls back to `io::copy` from where it stopped. Stops
+/// quietly when the child closes its stdin.
+fn copy_file_to_stdin(mut file: File, child_stdin: &mut ChildStdin) {
+    #[cfg(target_os = "linux")]
+    loop {
+        // SAFETY: both descriptors stay open for the whole call, and null offsets make
+        // splice use and advance the file position.
+        let n = unsafe {
+            libc::splice(
+                file.as_raw_fd(),
+                std::ptr::null_mut(),
+                child_stdin.as_raw_fd(),
+                std::ptr::null_mut(),
+                SPLICE_CHUNK,
+                libc::SPLICE_F_MOVE,
+            )
+        };
+        match n {
+            0 => return,
+            n if n > 0 => {
+                #[cfg(test)]
+                SPLICED_BYTES.fetch_add(n as u64, std::sync::atomic::Ordering::Relaxed);
+            }
+            _ => match std::io::Error::last_os_error().raw_os_error() {
+                Some(libc::EINTR) => {}
+                Some(libc::EINVAL | libc::ENOSYS) => break,
+                _ => return,
+            },
+        }
+    }
+
+    let _ = std::io::copy(&mut file, child_stdin);
+}
+
+/// Forward the parent's stdin to `child_stdin` unless nothing arrives within `timeout`.
+///
+/// Without `poll`, stdin is read on a helper thread that is left blocked in `read` if
+/// nothing arrives in time. A closed child is only noticed on the next write.
+#[cfg(not(unix))]
+fn forward_stdin(child_stdin: &mut ChildStdin, timeout: Duration) {
+    let (sender, receiver) = std::sync::mpsc::channel::<Vec<u8>>();
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        let mut stdin = std::io::stdin();
+        loop {
+            match stdin.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => {
+                    if sender.send(buf[..n].to_vec()).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    let Ok(first) = receiver.recv_timeout(timeout) else {
+        return;
+    };
+    for chunk in std::iter::once(first).chain(receiver) {
+        if child_stdin.write_all(&chunk).is_err() {
+            return;
+        }
+    }
+}
+
+/// Turn one end of a pipe into the matching `ChildStdin`, `ChildStdout` or `ChildStderr`.
+#[cfg(unix)]
+fn child_stream<T: From<OwnedFd>>(end: impl Into<OwnedFd>) -> T {
+    T::from(end.into())
+}
+
+/// Turn one end of a pipe into the matching `ChildStdin`, `ChildStdout` or `ChildStderr`.
+#[cfg(windows)]
+fn child_stream<T: From<OwnedHandle>>(end: impl Into<OwnedHandle>) -> T {
+    T::from(end.into())
+}
+
+/// Signal that terminated a process, if any.
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    status.signal()
+}
+
+/// Signal that terminated a process; there are no signals outside Unix.
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Whether a process was killed by `SIGPIPE`, i.e. cut off by a reader that exited early.
+#[cfg(unix)]
+fn killed_by_sigpipe(status: &ExitStatus) -> bool {
+    exit_signal(status) == Some(libc::SIGPIPE)
+}
+
+/// Whether a process was killed by `SIGPIPE`; there are no signals outside Unix.
+#[cfg(not(unix))]
+fn killed_by_sigpipe(_status: &ExitStatus) -> bool {
+    false
+}
+
+/// Read `reader` to the end, passing each line to `on_line` without its line ending.
+///
+/// Returns everything that was read, so the data can still be attached to an error.
+fn read_lines(reader: impl Read, on_line: &mut dyn FnMut(&str)) -> Vec<u8> {
+    let mut reader = BufReader::new(reader);
+    let mut data = Vec::new();
+    loop {
+        let start = data.len();
+        match reader.read_until(b'\n', &mut data) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = &data[start..];
+                let line = line.strip_suffix(b"\n").unwrap_or(line);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                on_line(&String::from_utf8_lossy(line));
+            }
+        }
+    }
+    data
+}
+
+/// Minimum time between two progress reports.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reader that reports the number of bytes read so far to a progress callback.
+///
+/// Reports at most every [`PROGRESS_INTERVAL`], plus once at end of input.
+struct ProgressReader<R> {
+    inner: R,
+    callback: Option<ProgressFn>,
+    bytes: u64,
+    start: Instant,
+    last_report: Instant,
+}
+
+impl<R: Read> ProgressReader<R> {
+    fn new(inner: R, callback: Option<ProgressFn>) -> Self {
+        let start = Instant::now();
+        Self {
+            inner,
+            callback,
+            bytes: 0,
+            start,
+            last_report: start,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes += n as u64;
+
+        if n == 0 {
+            // Final report; the callback is not called again afterwards
+            if let Some(mut callback) = self.callback.take() {
+                (callback.0)(self.bytes, self.start.elapsed());
+            }
+        } else if let Some(callback) = &mut self.callback {
+            let now = Instant::now();
+            if now.duration_since(self.last_report) >= PROGRESS_INTERVAL {
+                self.last_report = now;
+                (callback.0)(self.bytes, now.duration_since(self.start));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Timestamped log of the data passing through a pipeline's piped streams.
+///
+/// See [`Cmd::record_io`] for the format.
+#[derive(Clone)]
+struct IoRecorder {
+    file: Arc<Mutex<File>>,
+    start: Instant,
+}
+
+impl IoRecorder {
+    fn create(path: &Path) -> Result<Self, Error> {
+        let file = File::create(path).map_err(|source| CmdError::OutputFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            start: Instant::now(),
+        })
+    }
+
+    /// Interpose a recording relay thread on every stream piped to or from the caller.
+    fn attach(&self, spawn: &mut PipelineSpawn) -> Result<(), Error> {
+        if let Some(child_stdin) = spawn.stdin.take() {
+            let (reader, writer) = std::io::pipe().map_err(CmdError::Io)?;
+            // Not joined: it only ends once the caller closes stdin, which need not
+            // happen before the pipeline is waited for
+            let recorder = self.clone();
+            thread::spawn(move || recorder.relay("stdin", reader, child_stdin));
+            spawn.stdin = Some(child_stream(writer));
+        }
+        if let Some(child_stdout) = spawn.stdout.take() {
+            let (reader, writer) = std::io::pipe().map_err(CmdError::Io)?;
+            let recorder = self.clone();
+            let relay = thread::spawn(move || recorder.relay("stdout", child_stdout, writer));
+            spawn.handle.threads.push(relay);
+            spawn.stdout = Some(child_stream(reader));
+        }
+        if let Some(child_stderr) = spawn.stderr.take() {
+            let (reader, writer) = std::io::pipe().map_err(CmdError::Io)?;
+            let recorder = self.clone();
+            let relay = thread::spawn(move || recorder.relay("stderr", child_stderr, writer));
+            spawn.handle.threads.push(relay);
+            spawn.stderr = Some(child_stream(reader));
+        }
+        Ok(())
+    }
+
+    /// Copy `from` to `to`, recording each chunk as it passes.
+    fn relay(self, stream: &str, mut from: impl Read, mut to: impl Write) -> std::io::Result<()> {
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = match from.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.record(stream, &buf[..n]);
+            to.write_all(&buf[..n])?;
+        }
+    }
+
+    fn record(&self, stream: &str, data: &[u8]) {
+        let line = format!(
+            "{:.6} {} {} {}\n",
+            self.start.elapsed().as_secs_f64(),
+            stream,
+            data.len(),
+            data.escape_ascii()
+        );
+        // Recording is best effort and never fails the pipeline
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+}
+
+/// Run `f` with `pipeline` on Tokio's blocking thread pool and wait for it without
+/// blocking the runtime.
+///
+/// If the future is dropped before `f` returns, the pipeline is terminated the same way
+/// as on timeout. A panic in `f` is resumed in the caller.
+#[cfg(feature = "tokio")]
+async fn spawn_blocking<T: Send + 'static>(
+    mut pipeline: Pipeline,
+    f: impl FnOnce(Pipeline) -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+    let cancel = CancelOnDrop(Arc::default());
+    pipeline.cancel = Some(Arc::clone(&cancel.0));
+    match tokio::task::spawn_blocking(move || f(pipeline)).await {
+        Ok(result) => result,
+        Err(e) => match e.try_into_panic() {
+            Ok(payload) => std::panic::resume_unwind(payload),
+            Err(e) => Err(CmdError::Io(std::io::Error::other(e))),
+        },
+    }
+}
+
+/// Raises its flag when dropped, telling the pipeline of a dropped future to stop.
+#[cfg(feature = "tokio")]
+struct CancelOnDrop(Arc<std::sync::atomic::AtomicBool>);
+
+#[cfg(feature = "tokio")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Up to 40 bytes of `output` on each side of where parsing failed, on one line.
+#[cfg(feature = "serde")]
+fn json_snippet(output: &[u8], error: &serde_json::Error) -> String {
+    const CONTEXT: usize = 40;
+    let line_start: usize = output
+        .split(|&b| b == b'\n')
+        .take(error.line().saturating_sub(1))
+        .map(|line| line.len() + 1)
+        .sum();
+    let at = (line_start + error.column().saturating_sub(1)).min(output.len());
+    let start = at.saturating_sub(CONTEXT);
+    let end = (at + CONTEXT).min(output.len());
+
+    let mut snippet = String::from_utf8_lossy(&output[start..end]).replace(['\n', '\r'], " ");
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < output.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+/// Result of running a pipeline with its output captured.
+struct Captured {
+    stdout: Vec<u8>,
+    programs: Vec<OsString>,
+    statuses: Vec<ExitStatus>,
+    stderr: Option<Vec<u8>>,
+    /// Stderr of each command other than the last, if it was piped back.
+    stage_stderr: Vec<Vec<u8>>,
+    pipefail: bool,
+    /// Whether stdout was cut off at the limit given to `run_captured_limited`.
+    truncated: bool,
+}
 
 impl PipelineHandle {
     /// Wait for all processes in the pipeline to complete.
+    ///
+    /// Returns [`CmdError::PipelineStageFailed`] for the first command that did not exit
+    /// successfully, or only for the last command if pipefail is disabled. A single
+    /// command fails with [`CmdError::NonZeroExit`] or [`CmdError::Signaled`].
     pub fn wait(self) -> Result<(), Error> {
-        for mut child in self.children {
-            let status = child.wait().map_err(|e| Error {
-                message: "Failed to wait for child process".to_string(),
-                source: Some(e),
-            })?;
+        self.wait_with_stderr(None)
+    }
+
+    /// Process IDs of the commands in the pipeline, in pipeline order.
+    ///
+    /// Closure stages have no process and are left out.
+    pub fn pids(&self) -> Vec<u32> {
+        self.children.iter().map(Child::id).collect()
+    }
+
+    /// Stop every process in the pipeline that is still running and reap them all.
+    ///
+    /// Processes are terminated the same way as on timeout: on Unix they get `SIGTERM`
+    /// first, then `SIGKILL` if still running after a short grace period. Exit
+    /// statuses are not reported, since the processes are expected to end abnormally.
+    pub fn kill(mut self) {
+        self.terminate_running();
+    }
+
+    /// Send `signal` to every process in the pipeline, such as
+    /// [`signal::SIGHUP`](crate::signal::SIGHUP) to make a server reload its
+    /// configuration. Only available on Unix.
+    ///
+    /// Unlike [`kill`](Self::kill) the handle is kept, so the processes can still be
+    /// waited for. A process that has already exited but was not waited for yet
+    /// ignores the signal. Returns the error of the first process that could not be
+    /// signaled, e.g. for an invalid signal number.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let server = cmd!("nginx", "-g", "daemon off;").spawn()?;
+    /// // ... edit the configuration ...
+    /// server.signal(scripty::signal::SIGHUP)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(unix)]
+    pub fn signal(&self, signal: i32) -> std::io::Result<()> {
+        let mut result = Ok(());
+        for child in &self.children {
+            // SAFETY: the child has not been reaped yet, so its pid is still valid.
+            if unsafe { libc::kill(child.id() as libc::pid_t, signal) } == -1 && result.is_ok() {
+                result = Err(std::io::Error::last_os_error());
+            }
+        }
+        result
+    }
+
+    /// Terminate the children that are still running and reap them all.
+    fn terminate_running(&mut self) {
+        let running: Vec<usize> = (0..self.children.len())
+            .filter(|&i| matches!(self.children[i].try_wait(), Ok(None)))
+            .collect();
+        terminate(&mut self.children, &running);
+        for child in &mut self.children {
+            let _ = child.wait();
+        }
+    }
+
+    /// Wait for every process in the pipeline and collect their exit statuses.
+    ///
+    /// All children are reaped even if some of them exited with a non-zero status.
+    fn wait_statuses(mut self) -> Result<Vec<ExitStatus>, Error> {
+        if self.deadline.is_some() || self.cancel.is_some() {
+            let deadline = self.deadline.take();
+            return self.wait_until(deadline);
+        }
 
-            if !status.success() {
-                return Err(Error {
-                    message: format!("Command failed with exit code: {:?}", status.code()),
-                    source: None,
+        let mut statuses = Vec::with_capacity(self.children.len());
+        for child in &mut self.children {
+            let status = child.wait().map_err(CmdError::Io)?;
+            statuses.push(status);
+        }
+        self.trace.record_statuses(&statuses);
+        Self::join_threads(std::mem::take(&mut self.threads))?;
+        Ok(statuses)
+    }
+
+    /// Wait for the closure stages and report the first one that failed.
+    ///
+    /// A stage that stopped because its downstream exited early is not a failure, just
+    /// like a command killed by `SIGPIPE` in a shell pipeline without pipefail.
+    fn join_threads(threads: Vec<thread::JoinHandle<std::io::Result<()>>>) -> Result<(), Error> {
+        let mut result = Ok(());
+        for handle in threads {
+            let outcome = handle
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("pipeline stage panicked")));
+            if let Err(e) = outcome {
+                if e.kind() != std::io::ErrorKind::BrokenPipe && result.is_ok() {
+                    result = Err(CmdError::Io(e));
+                }
+            }
+        }
+        result
+    }
+
+    /// Poll the children until they all exit, the deadline passes or the pipeline is
+    /// cancelled.
+    ///
+    /// On timeout every child still running is terminated and reaped before
+    /// [`CmdError::Timeout`] is returned for the first of them. Cancellation stops them
+    /// the same way and returns an error of kind
+    /// [`Interrupted`](std::io::ErrorKind::Interrupted).
+    fn wait_until(mut self, deadline: Option<Deadline>) -> Result<Vec<ExitStatus>, Error> {
+        let mut statuses: Vec<Option<ExitStatus>> = vec![None; self.children.len()];
+        let children = &mut self.children;
+        let cancel = self.cancel.take();
+
+        let timed_out = loop {
+            for (child, status) in children.iter_mut().zip(statuses.iter_mut()) {
+                if status.is_none() {
+                    *status = child.try_wait().map_err(CmdError::Io)?;
+                }
+            }
+            if statuses.iter().all(Option::is_some) {
+                let statuses: Vec<ExitStatus> = statuses.into_iter().flatten().collect();
+                self.trace.record_statuses(&statuses);
+                Self::join_threads(std::mem::take(&mut self.threads))?;
+                return Ok(statuses);
+            }
+            if cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            {
+                break None;
+            }
+            if let Some(deadline) = deadline.filter(|deadline| Instant::now() >= deadline.at) {
+                break Some(deadline);
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        let running: Vec<usize> = (0..children.len())
+            .filter(|&i| statuses[i].is_none())
+            .collect();
+        terminate(children, &running);
+        let grace_end = Instant::now() + THREAD_GRACE_PERIOD;
+        for handle in std::mem::take(&mut self.threads) {
+            join_or_detach(Some(handle), grace_end);
+        }
+
+        Err(match timed_out {
+            Some(deadline) => CmdError::Timeout {
+                program: self.programs[running[0]].clone(),
+                timeout: deadline.limit,
+            },
+            None => CmdError::Io(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "pipeline cancelled",
+            )),
+        })
+    }
+
+    /// Wait for all processes and report the first failure.
+    ///
+    /// `stderr` is the captured stderr of the last command, if it was piped. It is
+    /// attached to the error when the last command is the one that failed.
+    fn wait_with_stderr(self, stderr: Option<Vec<u8>>) -> Result<(), Error> {
+        let programs = self.programs.clone();
+        let pipefail = self.pipefail;
+        let statuses = self.wait_statuses()?;
+        Self::check_statuses(programs, statuses, stderr, pipefail)
+    }
+
+    /// Report the first command that did not exit successfully.
+    ///
+    /// Without `pipefail` only the last command's status counts, like a plain shell pipeline.
+    /// A command other than the last one that was killed by `SIGPIPE` only stopped
+    /// because its output was no longer read, as `yes` in `yes | head`, so it is never
+    /// a failure.
+    fn check_statuses(
+        programs: Vec<OsString>,
+        statuses: Vec<ExitStatus>,
+        mut stderr: Option<Vec<u8>>,
+        pipefail: bool,
+    ) -> Result<(), Error> {
+        let last = statuses.len().saturating_sub(1);
+
+        for (i, (status, program)) in statuses.into_iter().zip(programs).enumerate() {
+            if status.success() || (i != last && (!pipefail || killed_by_sigpipe(&status))) {
+                continue;
+            }
+            let stderr = if i == last { stderr.take() } else { None };
+            if last > 0 {
+                return Err(CmdError::PipelineStageFailed {
+                    index: i,
+                    program,
+                    status,
+                    stderr,
                 });
             }
+            return Err(match exit_signal(&status) {
+                Some(signal) => CmdError::Signaled {
+                    program,
+                    signal,
+                    stderr,
+                },
+                None => CmdError::NonZeroExit {
+                    program,
+                    status,
+                    stderr,
+                },
+            });
         }
         Ok(())
     }
@@ -40,46 +739,238 @@ impl PipelineHandle {
                 use std::io::Read;
                 let mut output = Vec::new();
                 let mut reader = BufReader::new(stdout);
-                reader.read_to_end(&mut output).map_err(|e| Error {
-                    message: "Failed to read stdout".to_string(),
-                    source: Some(e),
-                })?;
+                reader.read_to_end(&mut output).map_err(CmdError::Io)?;
 
                 // Wait for the process to complete
-                for mut child in self.children {
-                    child.wait().map_err(|e| Error {
-                        message: "Failed to wait for child process".to_string(),
-                        source: Some(e),
-                    })?;
-                }
+                self.wait_statuses()?;
 
                 return Ok(output);
             }
         }
 
-        Err(Error {
-            message: "No stdout available to read from".to_string(),
-            source: None,
-        })
+        Err(CmdError::Io(std::io::Error::other(
+            "No stdout available to read from",
+        )))
+    }
+}
+
+impl Drop for PipelineHandle {
+    fn drop(&mut self) {
+        if self.kill_on_drop {
+            self.terminate_running();
+        }
+    }
+}
+
+impl Frames {
+    /// Report a trailing partial frame as an error instead of yielding it.
+    ///
+    /// The error is a [`CmdError::Io`] of kind [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof).
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Fill `frame` from stdout, returning how many bytes were read before end of output.
+    fn fill(&mut self, frame: &mut [u8]) -> std::io::Result<usize> {
+        let Some(stdout) = self.stdout.as_mut() else {
+            return Ok(0);
+        };
+        let mut filled = 0;
+        while filled < frame.len() {
+            match stdout.read(&mut frame[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Close stdout and wait for the pipeline to exit.
+    fn finish(&mut self) -> Result<(), Error> {
+        self.stdout = None;
+        if let Some(input) = self.input.take() {
+            let _ = input.join();
+        }
+        match self.handle.take() {
+            Some(handle) => handle.wait(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.handle.as_ref()?;
+
+        let mut frame = vec![0; self.size];
+        let filled = match self.fill(&mut frame) {
+            Ok(filled) => filled,
+            Err(e) => {
+                let _ = self.finish();
+                return Some(Err(CmdError::Io(e)));
+            }
+        };
+        if filled == self.size {
+            return Some(Ok(frame));
+        }
+
+        // End of output: the pipeline's status takes precedence over a partial frame
+        if let Err(e) = self.finish() {
+            return Some(Err(e));
+        }
+        match filled {
+            0 => None,
+            _ if self.strict => Some(Err(CmdError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!(
+                    "output ended with a partial frame of {} of {} bytes",
+                    filled, self.size
+                ),
+            )))),
+            _ => {
+                frame.truncate(filled);
+                Some(Ok(frame))
+            }
+        }
+    }
+}
+
+impl Drop for Frames {
+    fn drop(&mut self) {
+        // Reap the pipeline if iteration stopped early; closing stdout first lets it exit
+        let _ = self.finish();
     }
 }
 
 impl Pipeline {
     /// Add another command to the pipeline, piping stdout.
-    pub fn pipe(mut self, cmd: Cmd) -> Self {
-        self.connections.push((cmd, PipeMode::Stdout));
-        self
+    ///
+    /// Pipelines extend indefinitely: each call appends a stage after the current
+    /// last one, and input set earlier with [`input`](Self::input) and friends
+    /// still feeds the first command.
+    pub fn pipe(self, cmd: Cmd) -> Self {
+        self.push(cmd, PipeMode::Stdout)
     }
 
     /// Add another command to the pipeline, piping stderr.
-    pub fn pipe_err(mut self, cmd: Cmd) -> Self {
-        self.connections.push((cmd, PipeMode::Stderr));
-        self
+    pub fn pipe_err(self, cmd: Cmd) -> Self {
+        self.push(cmd, PipeMode::Stderr)
     }
 
     /// Add another command to the pipeline, piping both stdout and stderr.
-    pub fn pipe_out_err(mut self, cmd: Cmd) -> Self {
-        self.connections.push((cmd, PipeMode::Both));
+    pub fn pipe_out_err(self, cmd: Cmd) -> Self {
+        self.push(cmd, PipeMode::Both)
+    }
+
+    /// Pipe the pipeline's stdout into a Rust closure.
+    ///
+    /// See [`Cmd::pipe_fn`] for details.
+    pub fn pipe_fn<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut dyn BufRead, &mut dyn Write) -> std::io::Result<()> + Send + 'static,
+    {
+        self.push_fn("pipe_fn", Box::new(f))
+    }
+
+    /// Copy the pipeline's stdout into `writer` while passing it on unchanged.
+    ///
+    /// Placed between two stages, this captures the intermediate data of a pipeline
+    /// without running its stages separately. See [`Cmd::tee`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::fs::File;
+    ///
+    /// let count = cmd!("cat", "access.log")
+    ///     .pipe(cmd!("grep", "ERROR"))
+    ///     .tee(File::create("errors.log")?)
+    ///     .pipe(cmd!("wc", "-l"))
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn tee(self, writer: impl Write + Send + 'static) -> Self {
+        let mut writer = writer;
+        self.push_fn(
+            "tee",
+            Box::new(move |input, output| {
+                loop {
+                    let chunk = input.fill_buf()?;
+                    if chunk.is_empty() {
+                        break;
+                    }
+                    writer.write_all(chunk)?;
+                    output.write_all(chunk)?;
+                    let n = chunk.len();
+                    input.consume(n);
+                }
+                writer.flush()
+            }),
+        )
+    }
+
+    /// Gzip-compress the pipeline's stdout in-process at the default level.
+    ///
+    /// See [`Cmd::gzip`] for details.
+    #[cfg(feature = "flate2")]
+    pub fn gzip(self) -> Self {
+        self.gzip_level(6)
+    }
+
+    /// Gzip-compress the pipeline's stdout in-process at the given level.
+    ///
+    /// See [`Cmd::gzip_level`] for details.
+    #[cfg(feature = "flate2")]
+    pub fn gzip_level(self, level: u32) -> Self {
+        let level = flate2::Compression::new(level.min(9));
+        self.push_fn(
+            "gzip",
+            Box::new(move |input, output| {
+                let mut encoder = flate2::write::GzEncoder::new(output, level);
+                std::io::copy(input, &mut encoder)?;
+                encoder.finish()?;
+                Ok(())
+            }),
+        )
+    }
+
+    /// Decompress the pipeline's gzip stdout in-process.
+    ///
+    /// See [`Cmd::gunzip`] for details.
+    #[cfg(feature = "flate2")]
+    pub fn gunzip(self) -> Self {
+        self.push_fn(
+            "gunzip",
+            Box::new(|input, output| {
+                let mut decoder = flate2::bufread::MultiGzDecoder::new(input);
+                std::io::copy(&mut decoder, output)?;
+                Ok(())
+            }),
+        )
+    }
+
+    /// Append a closure stage reading this pipeline's stdout.
+    fn push_fn(mut self, name: &'static str, body: StageBody) -> Self {
+        let stage = StageFn { name, body };
+        self.connections.push((Stage::Fn(stage), PipeMode::Stdout));
+        self
+    }
+
+    /// Append a command, keeping the shortest timeout of all commands.
+    pub(crate) fn push(mut self, cmd: Cmd, mode: PipeMode) -> Self {
+        self.verbose_echo |= cmd.verbose_echo;
+        if self.echo_writer.is_none() {
+            self.echo_writer = cmd.echo_writer.clone();
+        }
+        self.kill_on_drop |= cmd.kill_on_drop;
+        self.timeout = self.timeout.into_iter().chain(cmd.timeout).min();
+        self.connections.push((Stage::Cmd(cmd), mode));
         self
     }
 
@@ -90,6 +981,14 @@ impl Pipeline {
         self
     }
 
+    /// Feed the contents of a file to the pipeline, like `< path` in a shell.
+    ///
+    /// See [`Cmd::input_file`] for details.
+    pub fn input_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.input = Some(CmdInput::File(path.as_ref().to_path_buf()));
+        self
+    }
+
     /// Set text input for the pipeline (deprecated: use spawn_with_io for more control).
     /// This is kept for backward compatibility but users should prefer the spawn_with_* methods.
     pub fn input(mut self, input: impl AsRef<str>) -> Self {
@@ -97,212 +996,579 @@ impl Pipeline {
         self
     }
 
+    /// Feed the items of an iterator to the pipeline as lines.
+    ///
+    /// See [`Cmd::input_iter`] for details.
+    pub fn input_iter<I, S>(mut self, lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        I::IntoIter: Send + 'static,
+        S: AsRef<str>,
+    {
+        let lines = lines.into_iter().map(|line| line.as_ref().to_owned());
+        self.input = Some(CmdInput::Lines(Box::new(lines)));
+        self
+    }
+
+    /// Connect the first command's stdin to `/dev/null`, dropping any input set so far.
+    ///
+    /// See [`Cmd::no_input`] for details.
+    pub fn no_input(mut self) -> Self {
+        self.input = None;
+        if let Some((Stage::Cmd(cmd), _)) = self.connections.first_mut() {
+            cmd.stdin_mode = Some(StdioMode::Null);
+        }
+        self
+    }
+
+    /// Let the first command read the parent's stdin, dropping any input set so far.
+    ///
+    /// See [`Cmd::stdin_inherit`] for details.
+    pub fn stdin_inherit(mut self) -> Self {
+        self.input = None;
+        if let Some((Stage::Cmd(cmd), _)) = self.connections.first_mut() {
+            cmd.stdin_mode = Some(StdioMode::Inherit);
+        }
+        self
+    }
+
+    /// Forward the parent's stdin, closing it if no data arrives within `timeout`.
+    ///
+    /// See [`Cmd::inherit_input_timeout`] for details.
+    pub fn inherit_input_timeout(mut self, timeout: Duration) -> Self {
+        self.input = Some(CmdInput::Inherit(timeout));
+        self
+    }
+
     /// Run without echoing the pipeline.
     pub fn no_echo(mut self) -> Self {
         self.suppress_echo = true;
         self
     }
 
+    /// Echo the pipeline to `writer` instead of stderr.
+    ///
+    /// See [`Cmd::echo_to`] for details.
+    pub fn echo_to(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.echo_writer = Some(crate::output::EchoWriter::new(writer));
+        self
+    }
+
+    /// Echo the working directory the pipeline runs from in front of it.
+    ///
+    /// See [`Cmd::verbose_echo`] for details.
+    pub fn verbose_echo(mut self) -> Self {
+        self.verbose_echo = true;
+        self
+    }
+
+    /// Choose whether dropping the handle of the spawned pipeline terminates it.
+    ///
+    /// See [`Cmd::kill_on_drop`] for details.
+    pub fn kill_on_drop(mut self, enabled: bool) -> Self {
+        self.kill_on_drop = enabled;
+        self
+    }
+
+    /// Choose whether a failing command other than the last one fails the pipeline.
+    ///
+    /// Enabled by default, matching `set -o pipefail`: `run()`, `output()` and the other
+    /// methods that check exit statuses report the first command that failed. When
+    /// disabled, only the last command's exit status counts, like a plain `sh` pipeline,
+    /// which is handy when an upstream command is expected to fail.
+    ///
+    /// Either way, an upstream command killed by `SIGPIPE` because a later one stopped
+    /// reading, like `yes` in `yes | head`, is not a failure.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// // The count is wanted even if some of the files are missing
+    /// let lines = cmd!("cat", "a.log", "b.log")
+    ///     .pipe(cmd!("wc", "-l"))
+    ///     .pipefail(false)
+    ///     .output()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn pipefail(mut self, enabled: bool) -> Self {
+        self.pipefail = enabled;
+        self
+    }
+
+    /// Whether data flows between the stages through kernel pipes only.
+    ///
+    /// Adjacent commands are always connected with `std::io::pipe`, so data goes from
+    /// one process to the next without passing through this one, and there is no
+    /// fallback to buffering in memory. The answer is `false` only when the pipeline
+    /// contains an in-process stage such as [`pipe_fn`](Self::pipe_fn),
+    /// [`tee`](Self::tee) or `gzip()`, whose data is copied through a thread of this
+    /// process. It does not depend on the platform or the input and output methods.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scripty::cmd;
+    ///
+    /// let pipeline = cmd!("ps", "aux").pipe(cmd!("grep", "rust"));
+    /// assert!(pipeline.uses_native_pipes());
+    /// ```
+    pub fn uses_native_pipes(&self) -> bool {
+        self.connections
+            .iter()
+            .all(|(stage, _)| matches!(stage, Stage::Cmd(_)))
+    }
+
+    /// Set the capacity of the buffers that in-process stages read and write through.
+    ///
+    /// Stages such as [`pipe_fn`](Self::pipe_fn), [`tee`](Self::tee) and `gzip()` copy
+    /// their data through this process, reading from and writing to the neighbouring
+    /// stages in chunks of at most this many bytes. The default is 8 KiB, like
+    /// [`BufReader`]. Larger buffers mean fewer system calls and thread wakeups, which
+    /// pays off for multi-gigabyte streams, at the cost of memory for each closure stage
+    /// and data reaching the next stage in bigger, later chunks. Sizes below 1 are
+    /// raised to 1. Pipes between two commands are managed by the operating system and
+    /// are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    /// use std::fs::File;
+    ///
+    /// cmd!("zcat", "dump.sql.gz")
+    ///     .tee(File::create("dump.sql")?)
+    ///     .pipe(cmd!("psql", "mydb"))
+    ///     .buffer_size(1024 * 1024)
+    ///     .run()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size.max(1);
+        self
+    }
+
+    /// Report progress while the pipeline's stdout is being read.
+    ///
+    /// See [`Cmd::with_progress`] for details.
+    pub fn with_progress(mut self, callback: impl FnMut(u64, Duration) + Send + 'static) -> Self {
+        self.progress = Some(ProgressFn(Box::new(callback)));
+        self
+    }
+
+    /// Call `callback` with every line the last command writes to stderr.
+    ///
+    /// See [`Cmd::on_stderr_line`] for details. The stderr of the other commands is
+    /// left alone.
+    pub fn on_stderr_line(mut self, callback: impl FnMut(&str) + Send + 'static) -> Self {
+        self.stderr_line = Some(LineFn(Box::new(callback)));
+        self
+    }
+
+    /// Record the data passing through the pipeline's piped streams to a file.
+    ///
+    /// See [`Cmd::record_io`] for details.
+    pub fn record_io(mut self, path: impl AsRef<Path>) -> Self {
+        self.record = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Terminate the whole pipeline if it is still running after `timeout`.
+    ///
+    /// See [`Cmd::timeout`] for details.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Run the pipeline.
+    ///
+    /// By default this fails if any command in the pipeline exits unsuccessfully, not
+    /// only the last one, like `set -o pipefail` in a shell; see [`pipefail`](Self::pipefail).
+    /// The error names the first failing command; use [`statuses`](Self::statuses) to
+    /// inspect every exit status.
     pub fn run(self) -> Result<(), Error> {
         self.execute_internal(false).map(|_| ())
     }
 
-    /// Run the pipeline and return the output as a string.
-    /// Get binary output from the pipeline.
-    pub fn output_bytes(self) -> Result<Vec<u8>, Error> {
-        self.execute_internal(true)
+    /// Run the pipeline, terminating it if it has not finished by `deadline`.
+    ///
+    /// See [`Cmd::run_until`] for details.
+    pub fn run_until(mut self, deadline: Instant) -> Result<(), Error> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        self.timeout = self.timeout.into_iter().chain([remaining]).min();
+        self.run()
     }
 
-    /// Get text output from the pipeline.
-    pub fn output(self) -> Result<String, Error> {
-        let bytes = self.output_bytes()?;
-        Ok(String::from_utf8_lossy(&bytes).to_string())
+    /// Run the pipeline and return the exit status of the last command.
+    ///
+    /// Unlike [`run`](Self::run), a non-zero exit status is not treated as an error:
+    /// `Err` is only returned when a process cannot be spawned or waited for.
+    /// Output is inherited from the parent just like `run()`.
+    pub fn status(self) -> Result<ExitStatus, Error> {
+        let statuses = self.statuses()?;
+        Ok(statuses.last().copied().unwrap_or_default())
     }
 
-    /// Spawn pipeline with full I/O access.
-    /// User is responsible for managing stdin, stdout, and stderr in separate threads.
-    pub fn spawn_io_all(self) -> Result<PipelineSpawn, Error> {
-        if !self.suppress_echo {
-            self.echo_pipeline();
-        }
+    /// Run the pipeline and return the exit code of the last command.
+    ///
+    /// See [`Cmd::status_code`] for details.
+    pub fn status_code(self) -> Result<i32, Error> {
+        let status = self.status()?;
+        Ok(status
+            .code()
+            .or_else(|| exit_signal(&status).map(|signal| 128 + signal))
+            .unwrap_or(-1))
+    }
 
-        if self.connections.is_empty() {
-            return Ok(PipelineSpawn {
-                handle: PipelineHandle {
-                    children: Vec::new(),
-                },
-                stdin: None,
-                stdout: None,
-                stderr: None,
-            });
+    /// Run the pipeline and return the exit status of every command, in pipeline order.
+    ///
+    /// Like [`status`](Self::status), non-zero exit statuses are not treated as errors,
+    /// so this shows which stage of a failing pipeline failed and with what code. `run()`
+    /// and `output()` fail when any of these statuses is unsuccessful.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let statuses = cmd!("cat", "access.log")
+    ///     .pipe(cmd!("grep", "ERROR"))
+    ///     .pipe(cmd!("wc", "-l"))
+    ///     .statuses()?;
+    /// for (stage, status) in statuses.iter().enumerate() {
+    ///     println!("stage {}: {}", stage, status);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn statuses(mut self) -> Result<Vec<ExitStatus>, Error> {
+        let input = self.take_input()?;
+        let spawn = self.spawn_inherit_stdio()?;
+
+        let input_handle = Self::feed_input(input, spawn.stdin);
+        let statuses = spawn.handle.wait_statuses()?;
+        if let Some(handle) = input_handle {
+            let _ = handle.join();
         }
 
-        // For single command, handle it specially
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Set up I/O - always enable stdin for compatibility
-            std_cmd.stdin(Stdio::piped());
-            std_cmd.stdout(Stdio::piped());
-            std_cmd.stderr(Stdio::piped());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
-
-            let stdin = child.stdin.take();
-            let stdout = child.stdout.take();
-            let stderr = child.stderr.take();
+        Ok(statuses)
+    }
 
-            // For single commands, input handling is done by existing methods for backward compatibility
+    /// Get binary output from the pipeline.
+    pub fn output_bytes(self) -> Result<Vec<u8>, Error> {
+        self.execute_internal(true)
+    }
 
-            return Ok(PipelineSpawn {
-                handle: PipelineHandle {
-                    children: vec![child],
-                },
-                stdin,
-                stdout,
-                stderr,
+    /// Get binary output from the pipeline, failing if it is longer than `max` bytes.
+    ///
+    /// See [`Cmd::output_bytes_limited`] for details.
+    pub fn output_bytes_limited(self, max: usize) -> Result<Vec<u8>, Error> {
+        let captured = self.run_captured_limited(Some(max))?;
+        if captured.truncated {
+            return Err(CmdError::OutputLimit {
+                program: captured.programs.last().cloned().unwrap_or_default(),
+                limit: max,
             });
         }
+        PipelineHandle::check_statuses(
+            captured.programs,
+            captured.statuses,
+            captured.stderr,
+            captured.pipefail,
+        )?;
+        Ok(captured.stdout)
+    }
 
-        // Multi-command pipeline
-        let mut children: Vec<Child> = Vec::new();
-        let mut prev_reader: Option<std::io::PipeReader> = None;
-        let mut first_stdin = None;
-        let mut last_stdout = None;
-        let mut last_stderr = None;
+    /// Get at most `max` bytes of the pipeline's output, and whether any were cut off.
+    ///
+    /// See [`Cmd::output_bytes_truncated`] for details.
+    pub fn output_bytes_truncated(self, max: usize) -> Result<(Vec<u8>, bool), Error> {
+        let captured = self.run_captured_limited(Some(max))?;
+        if !captured.truncated {
+            PipelineHandle::check_statuses(
+                captured.programs,
+                captured.statuses,
+                captured.stderr,
+                captured.pipefail,
+            )?;
+        }
+        Ok((captured.stdout, captured.truncated))
+    }
 
-        // Spawn all commands in the pipeline
-        for (i, (cmd_def, _pipe_mode)) in self.connections.iter().enumerate() {
-            let mut cmd = Self::build_std_command_static(cmd_def);
+    /// Get text output from the pipeline.
+    pub fn output(self) -> Result<String, Error> {
+        let bytes = self.output_bytes()?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
 
-            // Set up stdin
-            if i == 0 {
-                // First command: set up for potential input
-                cmd.stdin(Stdio::piped());
-            } else {
-                // Subsequent commands: use previous command's output
-                if let Some(reader) = prev_reader.take() {
-                    cmd.stdin(Stdio::from(reader));
-                }
-            }
+    /// Get text output from the pipeline together with how long it took to run.
+    ///
+    /// See [`Cmd::measure`] for details.
+    pub fn measure(self) -> Result<(String, Duration), Error> {
+        let start = Instant::now();
+        let output = self.output()?;
+        Ok((output, start.elapsed()))
+    }
 
-            // Set up stdout and stderr
-            let is_last = i == self.connections.len() - 1;
-            if is_last {
-                // Last command: capture both stdout and stderr
-                cmd.stdout(Stdio::piped());
-                cmd.stderr(Stdio::piped());
-            } else {
-                // Intermediate commands: pipe to next command
-                let next_pipe_mode = self.connections[i + 1].1;
-                match next_pipe_mode {
-                    PipeMode::Stdout => {
-                        let (reader, writer) = std::io::pipe().map_err(|e| Error {
-                            message: "Failed to create stdout pipe".to_string(),
-                            source: Some(e),
-                        })?;
-                        cmd.stdout(Stdio::from(writer));
-                        prev_reader = Some(reader);
-                    }
-                    PipeMode::Stderr => {
-                        let (reader, writer) = std::io::pipe().map_err(|e| Error {
-                            message: "Failed to create stderr pipe".to_string(),
-                            source: Some(e),
-                        })?;
-                        cmd.stderr(Stdio::from(writer));
-                        prev_reader = Some(reader);
-                    }
-                    PipeMode::Both => {
-                        let (reader, writer) = std::io::pipe().map_err(|e| Error {
-                            message: "Failed to create combined pipe".to_string(),
-                            source: Some(e),
-                        })?;
-                        let writer_clone = writer.try_clone().map_err(|e| Error {
-                            message: "Failed to clone pipe writer".to_string(),
-                            source: Some(e),
-                        })?;
-                        cmd.stdout(Stdio::from(writer));
-                        cmd.stderr(Stdio::from(writer_clone));
-                        prev_reader = Some(reader);
-                    }
-                }
-            }
+    /// Run the pipeline from async code.
+    ///
+    /// See [`Cmd::run_async`] for details.
+    #[cfg(feature = "tokio")]
+    pub async fn run_async(self) -> Result<(), Error> {
+        spawn_blocking(self, Pipeline::run).await
+    }
 
-            let mut child = cmd.spawn().map_err(|e| Error {
-                message: format!(
-                    "Failed to spawn command: {}",
-                    cmd_def.program.to_string_lossy()
-                ),
-                source: Some(e),
-            })?;
+    /// Get text output from the pipeline from async code.
+    ///
+    /// See [`Cmd::run_async`] for details.
+    #[cfg(feature = "tokio")]
+    pub async fn output_async(self) -> Result<String, Error> {
+        spawn_blocking(self, Pipeline::output).await
+    }
 
-            // Capture I/O handles
-            if i == 0 {
-                first_stdin = child.stdin.take();
-            }
-            if is_last {
-                last_stdout = child.stdout.take();
-                last_stderr = child.stderr.take();
-            }
+    /// Get binary output from the pipeline from async code.
+    ///
+    /// See [`Cmd::run_async`] for details.
+    #[cfg(feature = "tokio")]
+    pub async fn output_bytes_async(self) -> Result<Vec<u8>, Error> {
+        spawn_blocking(self, Pipeline::output_bytes).await
+    }
 
-            children.push(child);
+    /// Get text output from the pipeline, or `None` if one of its programs is not installed.
+    ///
+    /// See [`Cmd::output_opt`] for details.
+    pub fn output_opt(self) -> Result<Option<String>, Error> {
+        match self.output() {
+            Ok(output) => Ok(Some(output)),
+            Err(CmdError::Spawn { source, .. })
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
         }
+    }
 
-        // For pipelines, input handling is now user's responsibility via spawn API
+    /// Get text output from the pipeline as a list of lines.
+    ///
+    /// See [`Cmd::output_lines`] for details.
+    pub fn output_lines(self) -> Result<Vec<String>, Error> {
+        let output = self.output()?;
+        Ok(output.lines().map(String::from).collect())
+    }
 
-        Ok(PipelineSpawn {
-            handle: PipelineHandle { children },
-            stdin: first_stdin,
-            stdout: last_stdout,
-            stderr: last_stderr,
+    /// Get text output from the pipeline with leading and trailing whitespace removed.
+    ///
+    /// See [`Cmd::output_trimmed`] for details.
+    pub fn output_trimmed(self) -> Result<String, Error> {
+        let output = self.output()?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Deserialize the JSON output of the pipeline.
+    ///
+    /// See [`Cmd::output_json`] for details.
+    #[cfg(feature = "serde")]
+    pub fn output_json<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        let program = match self.connections.last() {
+            Some((Stage::Cmd(cmd), _)) => cmd.program.clone(),
+            Some((Stage::Fn(stage_fn), _)) => stage_fn.name.into(),
+            None => std::ffi::OsString::new(),
+        };
+        let output = self.output_bytes()?;
+        serde_json::from_slice(&output).map_err(|source| CmdError::Json {
+            program,
+            snippet: json_snippet(&output, &source),
+            source,
         })
     }
 
-    /// Spawn pipeline with stdin access only.
-    pub fn spawn_io_in(self) -> Result<(PipelineHandle, Option<std::process::ChildStdin>), Error> {
-        if !self.suppress_echo {
-            self.echo_pipeline();
+    /// Get text output from the pipeline with ANSI escape sequences removed.
+    ///
+    /// Useful for tools that force colored output. Escape sequences are only
+    /// stripped, not interpreted.
+    pub fn output_plain(self) -> Result<String, Error> {
+        let output = self.output()?;
+        Ok(crate::style::strip_ansi(&output))
+    }
+
+    /// Run the pipeline and capture its stdout, stderr and exit status.
+    ///
+    /// Unlike [`output`](Self::output), a non-zero exit status is not treated as an error;
+    /// the caller decides what to do with the returned [`Output`]. `stderr` and `status`
+    /// are those of the last command.
+    pub fn capture(self) -> Result<Output, Error> {
+        let captured = self.run_captured()?;
+        Ok(Output {
+            stdout: captured.stdout,
+            stderr: captured.stderr.unwrap_or_default(),
+            status: captured.statuses.last().copied().unwrap_or_default(),
+        })
+    }
+
+    /// Get text output from the pipeline together with the exit status of the last command.
+    ///
+    /// See [`Cmd::output_with_status`] for details. Like [`status`](Self::status), the
+    /// statuses of the other commands are not checked.
+    pub fn output_with_status(self) -> Result<(String, ExitStatus), Error> {
+        let output = self.capture()?;
+        Ok((output.stdout_str(), output.status))
+    }
+
+    /// Run the pipeline and capture its stdout along with the stderr and exit status of
+    /// every command.
+    ///
+    /// Meant for finding out which stage of a misbehaving pipeline complains: unlike
+    /// [`capture`](Self::capture), the stderr of commands in the middle of the pipeline
+    /// is collected too, instead of going to the terminal. Stderr that is piped to the
+    /// next command, or redirected with e.g. [`Cmd::discard_stderr`], is not collected.
+    /// Non-zero exit statuses are not treated as errors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use scripty::cmd;
+    ///
+    /// let output = cmd!("cat", "a.log", "b.log")
+    ///     .pipe(cmd!("grep", "-c", "ERROR"))
+    ///     .capture_stages()?;
+    /// for stage in &output.stages {
+    ///     println!("{}: {}", stage.program.to_string_lossy(), stage.status);
+    /// }
+    /// eprint!("{}", output.stderr_str());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn capture_stages(mut self) -> Result<PipelineOutput, Error> {
+        let last = self.connections.len().saturating_sub(1);
+        for i in 0..last {
+            let next_mode = self.connections[i + 1].1;
+            if let (Stage::Cmd(cmd), PipeMode::Stdout) = (&mut self.connections[i].0, next_mode) {
+                cmd.stderr_mode.get_or_insert(StdioMode::Piped);
+            }
         }
 
-        if self.connections.is_empty() {
-            return Ok((
-                PipelineHandle {
-                    children: Vec::new(),
-                },
-                None,
-            ));
+        let captured = self.run_captured()?;
+        let mut stage_stderr = captured.stage_stderr;
+        if let (Some(last), Some(stderr)) = (stage_stderr.last_mut(), captured.stderr) {
+            *last = stderr;
         }
+        let stages = captured
+            .programs
+            .into_iter()
+            .zip(captured.statuses)
+            .zip(stage_stderr)
+            .map(|((program, status), stderr)| StageOutput {
+                program,
+                stderr,
+                status,
+            })
+            .collect();
 
-        // For single command, handle specially to avoid stdin hanging
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
+        Ok(PipelineOutput {
+            stdout: captured.stdout,
+            stages,
+        })
+    }
 
-            // Only set up stdin as piped - let stdout/stderr inherit
-            std_cmd.stdin(Stdio::piped());
+    /// Feed `input` to the pipeline and return its stdout together with the exit status
+    /// of the last command.
+    ///
+    /// Unlike [`output`](Self::output), a non-zero exit status is not treated as an error:
+    /// `Err` is only returned when a process cannot be spawned or its I/O fails.
+    /// Any input set earlier with [`input`](Self::input) is replaced.
+    pub fn filter(mut self, input: impl AsRef<[u8]>) -> Result<(String, ExitStatus), Error> {
+        self.input = Some(CmdInput::Bytes(input.as_ref().to_vec()));
+        let captured = self.run_captured()?;
+        let status = captured.statuses.last().copied().unwrap_or_default();
+        Ok((
+            String::from_utf8_lossy(&captured.stdout).to_string(),
+            status,
+        ))
+    }
 
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
+    /// Capture the pipeline's stdout for `window`, then stop it.
+    ///
+    /// See [`Cmd::capture_for`] for details.
+    pub fn capture_for(mut self, window: Duration) -> Result<String, Error> {
+        let pipefail = self.pipefail;
+        let input = self.take_input()?;
+        let spawn = self.spawn_with(true, true, false)?;
+        let input_handle = Self::feed_input(input, spawn.stdin);
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let stdout_handle = spawn.stdout.map(|mut stdout| {
+            let buffer = Arc::clone(&buffer);
+            thread::spawn(move || {
+                let mut chunk = [0u8; 8192];
+                loop {
+                    match stdout.read(&mut chunk) {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => buffer.lock().unwrap().extend_from_slice(&chunk[..n]),
+                    }
+                }
+            })
+        });
 
-            let stdin = child.stdin.take();
+        // A timeout shorter than the window still fails the pipeline
+        let mut handle = spawn.handle;
+        let window_end = Instant::now() + window;
+        let timed_out_early = handle
+            .deadline
+            .is_some_and(|deadline| deadline.at < window_end);
+        if !timed_out_early {
+            handle.deadline = Some(Deadline {
+                at: window_end,
+                limit: window,
+            });
+        }
 
-            return Ok((
-                PipelineHandle {
-                    children: vec![child],
-                },
-                stdin,
-            ));
+        let programs = handle.programs.clone();
+        match handle.wait_statuses() {
+            Ok(statuses) => {
+                if let Some(handle) = input_handle {
+                    let _ = handle.join();
+                }
+                if let Some(handle) = stdout_handle {
+                    let _ = handle.join();
+                }
+                PipelineHandle::check_statuses(programs, statuses, None, pipefail)?;
+            }
+            Err(e) => {
+                let grace_end = Instant::now() + THREAD_GRACE_PERIOD;
+                join_or_detach(input_handle, grace_end);
+                join_or_detach(stdout_handle, grace_end);
+                // Stopping at the end of the window is intended, anything else fails
+                if timed_out_early || !matches!(e, CmdError::Timeout { .. }) {
+                    return Err(e);
+                }
+            }
         }
 
-        // For multi-command pipelines, use full spawn_io_all
-        let spawn = self.spawn_io_all()?;
+        let output = std::mem::take(&mut *buffer.lock().unwrap());
+        Ok(String::from_utf8_lossy(&output).to_string())
+    }
+
+    /// Start the pipeline in the background and return without waiting for it.
+    ///
+    /// See [`Cmd::spawn`] for details.
+    pub fn spawn(mut self) -> Result<PipelineHandle, Error> {
+        let input = self.take_input()?;
+        let spawn = self.spawn_with(input.is_some(), false, false)?;
+        // The feeder finishes on its own once the input is written or the command exits
+        drop(Self::feed_input(input, spawn.stdin));
+        Ok(spawn.handle)
+    }
+
+    /// Spawn pipeline with full I/O access.
+    /// User is responsible for managing stdin, stdout, and stderr in separate threads.
+    pub fn spawn_io_all(self) -> Result<PipelineSpawn, Error> {
+        self.spawn_with(true, true, true)
+    }
+
+    /// Spawn pipeline with stdin access only.
+    pub fn spawn_io_in(self) -> Result<(PipelineHandle, Option<std::process::ChildStdin>), Error> {
+        let spawn = self.spawn_io_subset(true, false, false)?;
         Ok((spawn.handle, spawn.stdin))
     }
 
@@ -318,48 +1584,7 @@ impl Pipeline {
         ),
         Error,
     > {
-        if !self.suppress_echo {
-            self.echo_pipeline();
-        }
-
-        if self.connections.is_empty() {
-            return Ok((
-                PipelineHandle {
-                    children: Vec::new(),
-                },
-                None,
-                None,
-            ));
-        }
-
-        // For single command, handle specially to avoid stderr hanging
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Only set up stdin and stdout as piped - let stderr inherit
-            std_cmd.stdin(Stdio::piped());
-            std_cmd.stdout(Stdio::piped());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
-
-            let stdin = child.stdin.take();
-            let stdout = child.stdout.take();
-
-            return Ok((
-                PipelineHandle {
-                    children: vec![child],
-                },
-                stdin,
-                stdout,
-            ));
-        }
-
-        // For multi-command pipelines, use full spawn_io_all
-        let spawn = self.spawn_io_all()?;
+        let spawn = self.spawn_io_subset(true, true, false)?;
         Ok((spawn.handle, spawn.stdin, spawn.stdout))
     }
 
@@ -375,48 +1600,7 @@ impl Pipeline {
         ),
         Error,
     > {
-        if !self.suppress_echo {
-            self.echo_pipeline();
-        }
-
-        if self.connections.is_empty() {
-            return Ok((
-                PipelineHandle {
-                    children: Vec::new(),
-                },
-                None,
-                None,
-            ));
-        }
-
-        // For single command, handle specially to avoid stdout hanging
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Only set up stdin and stderr as piped - let stdout inherit
-            std_cmd.stdin(Stdio::piped());
-            std_cmd.stderr(Stdio::piped());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
-
-            let stdin = child.stdin.take();
-            let stderr = child.stderr.take();
-
-            return Ok((
-                PipelineHandle {
-                    children: vec![child],
-                },
-                stdin,
-                stderr,
-            ));
-        }
-
-        // For multi-command pipelines, use full spawn_io_all
-        let spawn = self.spawn_io_all()?;
+        let spawn = self.spawn_io_subset(true, false, true)?;
         Ok((spawn.handle, spawn.stdin, spawn.stderr))
     }
 
@@ -424,44 +1608,7 @@ impl Pipeline {
     pub fn spawn_io_out(
         self,
     ) -> Result<(PipelineHandle, Option<std::process::ChildStdout>), Error> {
-        if !self.suppress_echo {
-            self.echo_pipeline();
-        }
-
-        if self.connections.is_empty() {
-            return Ok((
-                PipelineHandle {
-                    children: Vec::new(),
-                },
-                None,
-            ));
-        }
-
-        // For single command, handle specially to avoid stdin hanging
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Only set up stdout as piped - let stdin/stderr inherit
-            std_cmd.stdout(Stdio::piped());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
-
-            let stdout = child.stdout.take();
-
-            return Ok((
-                PipelineHandle {
-                    children: vec![child],
-                },
-                stdout,
-            ));
-        }
-
-        // For multi-command pipelines, use full spawn_io_all
-        let spawn = self.spawn_io_all()?;
+        let spawn = self.spawn_io_subset(false, true, false)?;
         Ok((spawn.handle, spawn.stdout))
     }
 
@@ -469,44 +1616,7 @@ impl Pipeline {
     pub fn spawn_io_err(
         self,
     ) -> Result<(PipelineHandle, Option<std::process::ChildStderr>), Error> {
-        if !self.suppress_echo {
-            self.echo_pipeline();
-        }
-
-        if self.connections.is_empty() {
-            return Ok((
-                PipelineHandle {
-                    children: Vec::new(),
-                },
-                None,
-            ));
-        }
-
-        // For single command, handle specially to avoid stdin hanging
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Only set up stderr as piped - let stdin/stdout inherit
-            std_cmd.stderr(Stdio::piped());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
-
-            let stderr = child.stderr.take();
-
-            return Ok((
-                PipelineHandle {
-                    children: vec![child],
-                },
-                stderr,
-            ));
-        }
-
-        // For multi-command pipelines, use full spawn_io_all
-        let spawn = self.spawn_io_all()?;
+        let spawn = self.spawn_io_subset(false, false, true)?;
         Ok((spawn.handle, spawn.stderr))
     }
 
@@ -521,88 +1631,126 @@ impl Pipeline {
         ),
         Error,
     > {
-        if !self.suppress_echo {
-            self.echo_pipeline();
-        }
-
-        if self.connections.is_empty() {
-            return Ok((
-                PipelineHandle {
-                    children: Vec::new(),
-                },
-                None,
-                None,
-            ));
-        }
-
-        // For single command, handle specially to avoid stdin hanging
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Only set up stdout and stderr as piped - let stdin inherit
-            std_cmd.stdout(Stdio::piped());
-            std_cmd.stderr(Stdio::piped());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
-
-            let stdout = child.stdout.take();
-            let stderr = child.stderr.take();
-
-            return Ok((
-                PipelineHandle {
-                    children: vec![child],
-                },
-                stdout,
-                stderr,
-            ));
-        }
-
-        // For multi-command pipelines, use full spawn_io_all
-        let spawn = self.spawn_io_all()?;
+        let spawn = self.spawn_io_subset(false, true, true)?;
         Ok((spawn.handle, spawn.stdout, spawn.stderr))
     }
 
     /// Stream pipeline's stdout to a Writer.
     /// This is more memory-efficient for large outputs.
+    ///
+    /// The writer is flushed once the output ends. Stderr is drained on the side and
+    /// attached to the error if the last command fails, like with `output()`. If
+    /// writing fails, the pipeline is still waited for before the error is returned.
     pub fn write_to<W: Write>(mut self, mut writer: W) -> Result<(), Error> {
         // Extract input before spawning
-        let input = self.input.take();
+        let progress = self.progress.take();
+        let input = self.take_input()?;
         let spawn = self.spawn_io_all()?;
 
         // Handle input in separate thread if provided
-        let input_handle = match input {
-            Some(CmdInput::Bytes(bytes)) => spawn.stdin.map(|mut stdin| {
-                thread::spawn(move || {
-                    use std::io::Write;
-                    let _ = stdin.write_all(&bytes);
-                    drop(stdin);
-                })
-            }),
-            Some(CmdInput::Reader(mut reader)) => spawn.stdin.map(|stdin| {
-                thread::spawn(move || {
-                    use std::io::copy;
-                    let mut stdin = stdin;
-                    let _ = copy(&mut reader, &mut stdin);
-                    drop(stdin);
-                })
-            }),
-            None => None,
+        let input_handle = Self::feed_input(input, spawn.stdin);
+
+        // Drain stderr concurrently so a chatty command cannot block on a full pipe
+        let stderr_handle = spawn.stderr.map(|mut stderr| {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                let _ = stderr.read_to_end(&mut buf);
+                buf
+            })
+        });
+
+        // Handle stdout in current thread; dropping the reader on failure lets the
+        // pipeline see a broken pipe instead of blocking
+        let written = match spawn.stdout {
+            Some(stdout) => {
+                use std::io::copy;
+                let mut reader = ProgressReader::new(BufReader::new(stdout), progress);
+                copy(&mut reader, &mut writer).and_then(|_| writer.flush())
+            }
+            None => Ok(()),
         };
 
-        // Handle stdout in current thread
-        if let Some(stdout) = spawn.stdout {
-            use std::io::copy;
-            copy(&mut BufReader::new(stdout), &mut writer).map_err(|e| Error {
-                message: "Failed to copy pipeline stdout to writer".to_string(),
-                source: Some(e),
+        // Wait for input thread to complete if exists
+        if let Some(handle) = input_handle {
+            let _ = handle.join();
+        }
+        let stderr = stderr_handle.and_then(|handle| handle.join().ok());
+
+        let result = spawn.handle.wait_with_stderr(stderr);
+        written.map_err(CmdError::Io)?;
+        result
+    }
+
+    /// Stream the pipeline's stdout as frames of exactly `size` bytes.
+    ///
+    /// See [`Cmd::frames`] for details.
+    pub fn frames(mut self, size: usize) -> Result<Frames, Error> {
+        assert!(size > 0, "frame size must be non-zero");
+
+        let progress = self.progress.take();
+        let input = self.take_input()?;
+        let spawn = self.spawn_with(true, true, false)?;
+        let input = Self::feed_input(input, spawn.stdin);
+        let stdout = spawn
+            .stdout
+            .map(|stdout| Box::new(ProgressReader::new(stdout, progress)) as Box<dyn Read + Send>);
+
+        Ok(Frames {
+            stdout,
+            handle: Some(spawn.handle),
+            input,
+            size,
+            strict: false,
+        })
+    }
+
+    /// Write the pipeline's stdout to a file, like `> path` in a shell.
+    ///
+    /// See [`Cmd::output_to_file`] for details.
+    pub fn output_to_file(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.redirect_output(path.as_ref(), false)
+    }
+
+    /// Append the pipeline's stdout to a file, like `>> path` in a shell.
+    ///
+    /// See [`Cmd::append_to_file`] for details.
+    pub fn append_to_file(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.redirect_output(path.as_ref(), true)
+    }
+
+    fn redirect_output(mut self, path: &Path, append: bool) -> Result<(), Error> {
+        self.output_file = Some(OutputFile {
+            path: path.to_path_buf(),
+            append,
+        });
+        let input = self.take_input()?;
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .map_err(|source| CmdError::OutputFile {
+                path: path.to_path_buf(),
+                source,
             })?;
+
+        // Stderr stays inherited, as with a shell redirection
+        let progress = self.progress.take();
+        let spawn = self.spawn_with(true, true, false)?;
+        let input_handle = Self::feed_input(input, spawn.stdin);
+
+        if let Some(stdout) = spawn.stdout {
+            let mut reader = ProgressReader::new(stdout, progress);
+            if let Err(e) = std::io::copy(&mut reader, &mut file) {
+                // Nothing reads the output any more; stop and reap the processes. The
+                // input thread is left to end on its own once stdin is closed.
+                spawn.handle.kill();
+                return Err(CmdError::Io(e));
+            }
         }
 
-        // Wait for input thread to complete if exists
         if let Some(handle) = input_handle {
             let _ = handle.join();
         }
@@ -614,36 +1762,16 @@ impl Pipeline {
     /// This is useful for capturing error output separately.
     pub fn write_err_to<W: Write>(mut self, mut writer: W) -> Result<(), Error> {
         // Extract input before spawning
-        let input = self.input.take();
+        let input = self.take_input()?;
         let spawn = self.spawn_io_all()?;
 
         // Handle input in separate thread if provided
-        let input_handle = match input {
-            Some(CmdInput::Bytes(bytes)) => spawn.stdin.map(|mut stdin| {
-                thread::spawn(move || {
-                    use std::io::Write;
-                    let _ = stdin.write_all(&bytes);
-                    drop(stdin);
-                })
-            }),
-            Some(CmdInput::Reader(mut reader)) => spawn.stdin.map(|stdin| {
-                thread::spawn(move || {
-                    use std::io::copy;
-                    let mut stdin = stdin;
-                    let _ = copy(&mut reader, &mut stdin);
-                    drop(stdin);
-                })
-            }),
-            None => None,
-        };
+        let input_handle = Self::feed_input(input, spawn.stdin);
 
         // Handle stderr in current thread
         if let Some(stderr) = spawn.stderr {
             use std::io::copy;
-            copy(&mut BufReader::new(stderr), &mut writer).map_err(|e| Error {
-                message: "Failed to copy pipeline stderr to writer".to_string(),
-                source: Some(e),
-            })?;
+            copy(&mut BufReader::new(stderr), &mut writer).map_err(CmdError::Io)?;
         }
 
         // Wait for input thread to complete if exists
@@ -660,50 +1788,27 @@ impl Pipeline {
         use std::sync::{Arc, Mutex};
 
         // Extract input before spawning
-        let input = self.input.take();
+        let input = self.take_input()?;
         let spawn = self.spawn_io_all()?;
 
         // Wrap writer in Arc<Mutex<>> for safe sharing between threads
         let writer = Arc::new(Mutex::new(writer));
 
         // Handle input in separate thread if provided
-        let input_handle = match input {
-            Some(CmdInput::Bytes(bytes)) => spawn.stdin.map(|mut stdin| {
-                thread::spawn(move || {
-                    use std::io::Write;
-                    let _ = stdin.write_all(&bytes);
-                    drop(stdin);
-                })
-            }),
-            Some(CmdInput::Reader(mut reader)) => spawn.stdin.map(|stdin| {
-                thread::spawn(move || {
-                    use std::io::copy;
-                    let mut stdin = stdin;
-                    let _ = copy(&mut reader, &mut stdin);
-                    drop(stdin);
-                })
-            }),
-            None => None,
-        };
+        let input_handle = Self::feed_input(input, spawn.stdin);
 
         // Handle both stdout and stderr in separate threads
         let stdout_handle = spawn.stdout.map(|stdout| {
             let writer_clone = Arc::clone(&writer);
             thread::spawn(move || {
-                use std::io::copy;
-                if let Ok(mut writer_guard) = writer_clone.lock() {
-                    let _ = copy(&mut BufReader::new(stdout), &mut *writer_guard);
-                }
+                let _ = copy_interleaved(stdout, &writer_clone);
             })
         });
 
         let stderr_handle = spawn.stderr.map(|stderr| {
             let writer_clone = Arc::clone(&writer);
             thread::spawn(move || {
-                use std::io::copy;
-                if let Ok(mut writer_guard) = writer_clone.lock() {
-                    let _ = copy(&mut BufReader::new(stderr), &mut *writer_guard);
-                }
+                let _ = copy_interleaved(stderr, &writer_clone);
             })
         });
 
@@ -725,225 +1830,472 @@ impl Pipeline {
 
     /// Run the pipeline with both input Reader and output Writer.
     /// This is the most flexible method for streaming I/O.
+    ///
+    /// The reader is fed on a detached thread, so a reader that blocks, like
+    /// `io::stdin()`, cannot keep this method from returning once the pipeline exits.
+    /// See [`run_with_io_scoped`](Self::run_with_io_scoped) for a reader that borrows
+    /// local data.
     pub fn run_with_io<R: Read + Send + 'static, W: Write>(
         self,
-        mut reader: R,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        let mut spawn = self.spawn_io_all()?;
+        drop(Self::feed_input(
+            Some(CmdInput::Reader(Box::new(reader))),
+            spawn.stdin.take(),
+        ));
+        Self::copy_output(spawn.handle, spawn.stdout, writer)
+    }
+
+    /// Like [`run_with_io`](Self::run_with_io), but the reader may borrow local data,
+    /// e.g. `&mut reader` or `&data[..]`.
+    ///
+    /// The reader is fed on a scoped thread that is joined before this method returns,
+    /// so it must end on its own or stop once the pipeline closes its stdin.
+    pub fn run_with_io_scoped<R: Read + Send, W: Write>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        let spawn = self.spawn_io_all()?;
+        thread::scope(|scope| {
+            Self::feed_reader(scope, reader, spawn.stdin);
+            Self::copy_output(spawn.handle, spawn.stdout, writer)
+        })
+    }
+
+    /// Run the pipeline with input Reader and stderr Writer.
+    /// This is useful for processing data while capturing error output.
+    ///
+    /// The reader is fed on a detached thread, see [`run_with_io`](Self::run_with_io).
+    pub fn run_with_err_io<R: Read + Send + 'static, W: Write>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        let mut spawn = self.spawn_io_all()?;
+        drop(Self::feed_input(
+            Some(CmdInput::Reader(Box::new(reader))),
+            spawn.stdin.take(),
+        ));
+        Self::copy_output(spawn.handle, spawn.stderr, writer)
+    }
+
+    /// Like [`run_with_err_io`](Self::run_with_err_io), but the reader may borrow local
+    /// data, see [`run_with_io_scoped`](Self::run_with_io_scoped).
+    pub fn run_with_err_io_scoped<R: Read + Send, W: Write>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        let spawn = self.spawn_io_all()?;
+        thread::scope(|scope| {
+            Self::feed_reader(scope, reader, spawn.stdin);
+            Self::copy_output(spawn.handle, spawn.stderr, writer)
+        })
+    }
+
+    /// Run the pipeline with input Reader and combined stdout+stderr Writer.
+    /// This merges both output streams for comprehensive logging.
+    ///
+    /// The reader is fed on a detached thread, see [`run_with_io`](Self::run_with_io).
+    pub fn run_with_both_io<R: Read + Send + 'static, W: Write + Send + 'static>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        let mut spawn = self.spawn_io_all()?;
+        drop(Self::feed_input(
+            Some(CmdInput::Reader(Box::new(reader))),
+            spawn.stdin.take(),
+        ));
+        Self::copy_both_outputs(spawn, writer)
+    }
+
+    /// Like [`run_with_both_io`](Self::run_with_both_io), but the reader and the writer
+    /// may borrow local data, see [`run_with_io_scoped`](Self::run_with_io_scoped).
+    pub fn run_with_both_io_scoped<R: Read + Send, W: Write + Send>(
+        self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), Error> {
+        let mut spawn = self.spawn_io_all()?;
+        let stdin = spawn.stdin.take();
+        thread::scope(|scope| {
+            Self::feed_reader(scope, reader, stdin);
+            Self::copy_both_outputs(spawn, writer)
+        })
+    }
+
+    /// Copy `output` to `writer` on the current thread, then wait for the pipeline.
+    ///
+    /// The pipe is closed before waiting, even if writing fails, so the pipeline cannot
+    /// keep the feeder waiting. A write error is reported once the pipeline is reaped.
+    fn copy_output<W: Write>(
+        handle: PipelineHandle,
+        output: Option<impl Read>,
         mut writer: W,
     ) -> Result<(), Error> {
-        let spawn = self.spawn_io_all()?;
+        let written = match output {
+            Some(output) => std::io::copy(&mut BufReader::new(output), &mut writer),
+            None => Ok(0),
+        };
 
-        // Handle input in separate thread
-        if let Some(mut stdin) = spawn.stdin {
-            thread::spawn(move || {
-                use std::io::copy;
-                let _ = copy(&mut reader, &mut stdin);
-            });
-        }
+        let result = handle.wait();
+        written.map_err(CmdError::Io)?;
+        result
+    }
 
-        // Handle output in current thread
-        if let Some(stdout) = spawn.stdout {
-            use std::io::copy;
-            copy(&mut BufReader::new(stdout), &mut writer).map_err(|e| Error {
-                message: "Failed to copy pipeline output to writer".to_string(),
-                source: Some(e),
-            })?;
-        }
+    /// Copy stdout and stderr to the shared `writer` from a thread each, then wait for
+    /// the pipeline.
+    fn copy_both_outputs<W: Write + Send>(spawn: PipelineSpawn, writer: W) -> Result<(), Error> {
+        use std::sync::Mutex;
+
+        // Share the writer between the stdout and stderr threads
+        let writer = Mutex::new(writer);
+
+        thread::scope(|scope| {
+            for output in [
+                spawn
+                    .stdout
+                    .map(|stdout| Box::new(stdout) as Box<dyn Read + Send>),
+                spawn
+                    .stderr
+                    .map(|stderr| Box::new(stderr) as Box<dyn Read + Send>),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                let writer = &writer;
+                scope.spawn(move || {
+                    let _ = copy_interleaved(output, writer);
+                });
+            }
+        });
 
         spawn.handle.wait()
     }
 
-    /// Run the pipeline with input Reader and stderr Writer.
-    /// This is useful for processing data while capturing error output.
-    pub fn run_with_err_io<R: Read + Send + 'static, W: Write>(
-        self,
-        mut reader: R,
-        mut writer: W,
-    ) -> Result<(), Error> {
-        let spawn = self.spawn_io_all()?;
-
-        // Handle input in separate thread
-        if let Some(mut stdin) = spawn.stdin {
-            thread::spawn(move || {
-                use std::io::copy;
-                let _ = copy(&mut reader, &mut stdin);
-            });
-        }
+    /// Feed lines to the pipeline and call `on_line` with every line it prints.
+    ///
+    /// See [`Cmd::interact`] for details.
+    pub fn interact<I, S>(self, lines: I, mut on_line: impl FnMut(String)) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = S>,
+        I::IntoIter: Send,
+        S: AsRef<str>,
+    {
+        // Stderr stays inherited, it is not piped back like with `spawn_io_in_out()`
+        let spawn = self.spawn_with(true, true, false)?;
+        let (handle, stdin, stdout) = (spawn.handle, spawn.stdin, spawn.stdout);
+        let lines = lines.into_iter();
+
+        thread::scope(|scope| {
+            if let Some(stdin) = stdin {
+                scope.spawn(move || {
+                    // Each line is sent as soon as it is produced; stop quietly at the
+                    // first failed write, e.g. when the command exited early
+                    let mut writer = std::io::LineWriter::new(stdin);
+                    for line in lines {
+                        if writeln!(writer, "{}", line.as_ref()).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
 
-        // Handle stderr output in current thread
-        if let Some(stderr) = spawn.stderr {
-            use std::io::copy;
-            copy(&mut BufReader::new(stderr), &mut writer).map_err(|e| Error {
-                message: "Failed to copy pipeline stderr to writer".to_string(),
-                source: Some(e),
-            })?;
-        }
+            // Read in the current thread so `on_line` does not have to be `Send`
+            let mut read = Ok(());
+            if let Some(stdout) = stdout {
+                let mut reader = BufReader::new(stdout);
+                let mut buf = Vec::new();
+                read = loop {
+                    buf.clear();
+                    match reader.read_until(b'\n', &mut buf) {
+                        Ok(0) => break Ok(()),
+                        Ok(_) => {
+                            let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                            let line = line.strip_suffix(b"\r").unwrap_or(line);
+                            on_line(String::from_utf8_lossy(line).into_owned());
+                        }
+                        Err(e) => break Err(e),
+                    }
+                };
+            }
 
-        spawn.handle.wait()
+            let result = handle.wait();
+            read.map_err(CmdError::Io)?;
+            result
+        })
     }
 
-    /// Run the pipeline with input Reader and combined stdout+stderr Writer.
-    /// This merges both output streams for comprehensive logging.
-    pub fn run_with_both_io<R: Read + Send + 'static, W: Write + Send + 'static>(
-        self,
+    /// Copy `reader` to the first command's stdin on a thread of `scope`.
+    ///
+    /// Stdin is dropped once the reader is exhausted so the command sees EOF. Copying
+    /// stops early if the command closes its stdin.
+    fn feed_reader<'scope, R: Read + Send + 'scope>(
+        scope: &'scope thread::Scope<'scope, '_>,
         mut reader: R,
-        writer: W,
-    ) -> Result<(), Error> {
-        use std::sync::{Arc, Mutex};
+        stdin: Option<ChildStdin>,
+    ) {
+        if let Some(mut stdin) = stdin {
+            scope.spawn(move || {
+                let _ = std::io::copy(&mut reader, &mut stdin);
+            });
+        }
+    }
 
-        let spawn = self.spawn_io_all()?;
+    fn execute_internal(mut self, capture_output: bool) -> Result<Vec<u8>, Error> {
+        if capture_output {
+            let captured = self.run_captured()?;
+            PipelineHandle::check_statuses(
+                captured.programs,
+                captured.statuses,
+                captured.stderr,
+                captured.pipefail,
+            )?;
+            Ok(captured.stdout)
+        } else {
+            // For run() method, don't capture output - let it go to terminal
+            let stderr_line = self.stderr_line.take();
+            let input = self.take_input()?;
+            let spawn = if stderr_line.is_some() {
+                self.spawn_with(true, false, true)?
+            } else {
+                self.spawn_inherit_stdio()?
+            };
 
-        // Handle input in separate thread
-        if let Some(mut stdin) = spawn.stdin {
-            thread::spawn(move || {
-                use std::io::copy;
-                let _ = copy(&mut reader, &mut stdin);
+            // Handle input if provided (for backward compatibility)
+            let input_handle = Self::feed_input(input, spawn.stdin);
+            let stderr_handle = spawn.stderr.zip(stderr_line).map(|(stderr, mut on_line)| {
+                thread::spawn(move || read_lines(stderr, &mut on_line.0))
             });
+            let result = spawn.handle.wait();
+
+            // A failed command has still closed stderr, while after a timeout a surviving
+            // grandchild may hold the pipes open, so the threads only get a grace period
+            if let Err(CmdError::Timeout { .. }) = result {
+                let grace_end = Instant::now() + THREAD_GRACE_PERIOD;
+                join_or_detach(input_handle, grace_end);
+                join_or_detach(stderr_handle, grace_end);
+            } else {
+                if let (Ok(()), Some(handle)) = (&result, input_handle) {
+                    let _ = handle.join();
+                }
+                if let Some(handle) = stderr_handle {
+                    let _ = handle.join();
+                }
+            }
+
+            result.map(|()| Vec::new())
         }
+    }
 
-        // Wrap writer in Arc<Mutex<>> for safe sharing between threads
-        let writer = Arc::new(Mutex::new(writer));
+    /// Run the pipeline capturing stdout and stderr, without judging exit statuses.
+    fn run_captured(self) -> Result<Captured, Error> {
+        self.run_captured_limited(None)
+    }
 
-        // Handle stdout in separate thread
+    /// Like [`run_captured`](Self::run_captured), but stop reading stdout once it grows
+    /// past `limit` bytes.
+    ///
+    /// Stdout is closed at that point so the last command stops writing, and the
+    /// output is cut back to `limit` bytes.
+    fn run_captured_limited(mut self, limit: Option<usize>) -> Result<Captured, Error> {
+        let pipefail = self.pipefail;
+        let progress = self.progress.take();
+        let stderr_line = self.stderr_line.take();
+        let input = self.take_input()?;
+        let mut spawn = self.spawn_io_all()?;
+
+        // Handle input if provided (for backward compatibility)
+        let input_handle = Self::feed_input(input, spawn.stdin);
+
+        // Drain stderr concurrently so a chatty command cannot block on a full pipe;
+        // callers attach it to the error if the last command fails. The stderr of other
+        // commands is only piped back for `capture_stages()`.
+        let drain = |mut stderr: std::process::ChildStderr| {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                let _ = stderr.read_to_end(&mut buf);
+                buf
+            })
+        };
+        let stderr_handle = match (spawn.stderr, stderr_line) {
+            (Some(stderr), Some(mut on_line)) => {
+                Some(thread::spawn(move || read_lines(stderr, &mut on_line.0)))
+            }
+            (stderr, _) => stderr.map(drain),
+        };
+        let stage_stderr_handles: Vec<_> = spawn
+            .handle
+            .children
+            .iter_mut()
+            .map(|child| child.stderr.take().map(drain))
+            .collect();
+
+        // Read stdout on its own thread as well, so the main thread is free to
+        // enforce a timeout while the pipeline is running.
         let stdout_handle = spawn.stdout.map(|stdout| {
-            let writer = Arc::clone(&writer);
             thread::spawn(move || {
-                use std::io::copy;
-                let mut reader = BufReader::new(stdout);
-                if let Ok(mut writer) = writer.lock() {
-                    let _ = copy(&mut reader, &mut *writer);
-                }
+                let mut output = Vec::new();
+                // One byte past the limit tells a long output from one that just fits
+                let max = limit.map_or(u64::MAX, |limit| limit as u64 + 1);
+                ProgressReader::new(BufReader::new(stdout), progress)
+                    .take(max)
+                    .read_to_end(&mut output)
+                    .map(|_| output)
             })
         });
 
-        // Handle stderr in separate thread
-        let stderr_handle = spawn.stderr.map(|stderr| {
-            let writer = Arc::clone(&writer);
-            thread::spawn(move || {
-                use std::io::copy;
-                let mut reader = BufReader::new(stderr);
-                if let Ok(mut writer) = writer.lock() {
-                    let _ = copy(&mut reader, &mut *writer);
+        // The main thread enforces the timeout. After a timeout the I/O threads get a
+        // short grace period to finish and are detached otherwise.
+        let programs = spawn.handle.programs.clone();
+        let statuses = match spawn.handle.wait_statuses() {
+            Ok(statuses) => statuses,
+            Err(e) => {
+                let grace_end = Instant::now() + THREAD_GRACE_PERIOD;
+                join_or_detach(input_handle, grace_end);
+                join_or_detach(stdout_handle, grace_end);
+                join_or_detach(stderr_handle, grace_end);
+                for handle in stage_stderr_handles {
+                    join_or_detach(handle, grace_end);
                 }
-            })
-        });
+                return Err(e);
+            }
+        };
 
-        // Wait for all threads to complete
-        if let Some(handle) = stdout_handle {
+        if let Some(handle) = input_handle {
             let _ = handle.join();
         }
-        if let Some(handle) = stderr_handle {
-            let _ = handle.join();
+        let mut output = match stdout_handle.map(|handle| handle.join()) {
+            Some(Ok(result)) => result.map_err(CmdError::Io)?,
+            Some(Err(_)) => {
+                return Err(CmdError::Io(std::io::Error::other(
+                    "stdout reader panicked",
+                )));
+            }
+            None => Vec::new(),
+        };
+        let truncated = limit.is_some_and(|limit| output.len() > limit);
+        if let Some(limit) = limit {
+            output.truncate(limit);
         }
-
-        spawn.handle.wait()
+        let stderr = stderr_handle.and_then(|handle| handle.join().ok());
+        let stage_stderr = stage_stderr_handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .and_then(|handle| handle.join().ok())
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        Ok(Captured {
+            stdout: output,
+            programs,
+            statuses,
+            stderr,
+            stage_stderr,
+            pipefail,
+            truncated,
+        })
     }
 
-    fn execute_internal(mut self, capture_output: bool) -> Result<Vec<u8>, Error> {
-        // Echo pipeline first if not suppressed
-        let original_suppress = self.suppress_echo;
-        if !original_suppress {
+    /// Echo the pipeline unless suppressed and take its input for feeding.
+    ///
+    /// Echo is suppressed afterwards so spawning does not echo a second time. An input
+    /// file is opened here, before anything is spawned, so a missing file is reported
+    /// without starting the pipeline.
+    fn take_input(&mut self) -> Result<Option<CmdInput>, Error> {
+        if !self.suppress_echo {
             self.echo_pipeline();
-        }
-
-        // Extract input before moving self
-        let input = self.input.take();
-
-        if capture_output {
-            // Call spawn_io_all with echo suppressed to avoid double echo
             self.suppress_echo = true;
-            let spawn = self.spawn_io_all()?;
-
-            // Handle input if provided (for backward compatibility)
-            let input_handle = match input {
-                Some(CmdInput::Bytes(bytes)) => {
-                    spawn.stdin.map(|mut stdin| {
-                        thread::spawn(move || {
-                            use std::io::Write;
-                            let _ = stdin.write_all(&bytes);
-                            drop(stdin); // Close stdin to signal EOF
-                        })
-                    })
-                }
-                Some(CmdInput::Reader(mut reader)) => {
-                    spawn.stdin.map(|stdin| {
-                        thread::spawn(move || {
-                            use std::io::copy;
-                            let mut stdin = stdin;
-                            let _ = copy(&mut reader, &mut stdin);
-                            drop(stdin); // Close stdin to signal EOF
-                        })
-                    })
-                }
-                None => None,
-            };
+        }
 
-            if let Some(stdout) = spawn.stdout {
-                let mut output = Vec::new();
-                let mut reader = BufReader::new(stdout);
-                reader.read_to_end(&mut output).map_err(|e| Error {
-                    message: "Failed to read stdout".to_string(),
-                    source: Some(e),
-                })?;
+        match self.input.take() {
+            Some(CmdInput::File(path)) => match std::fs::File::open(&path) {
+                Ok(file) => Ok(Some(CmdInput::Opened(file))),
+                Err(source) => Err(CmdError::InputFile { path, source }),
+            },
+            input => Ok(input),
+        }
+    }
 
-                // Wait for input thread to complete if exists
-                if let Some(handle) = input_handle {
-                    let _ = handle.join();
+    /// Write the pipeline input to the first command's stdin on a separate thread.
+    ///
+    /// Stdin is dropped once the input is exhausted so the command sees EOF.
+    fn feed_input(
+        input: Option<CmdInput>,
+        stdin: Option<ChildStdin>,
+    ) -> Option<thread::JoinHandle<()>> {
+        let mut stdin = stdin?;
+        match input? {
+            CmdInput::Bytes(bytes) => Some(thread::spawn(move || {
+                let _ = stdin.write_all(&bytes);
+            })),
+            CmdInput::Reader(mut reader) => Some(thread::spawn(move || {
+                let _ = std::io::copy(&mut reader, &mut stdin);
+            })),
+            CmdInput::Lines(lines) => Some(thread::spawn(move || {
+                // Stop quietly at the first failed write, e.g. when the command exited early
+                let mut writer = std::io::BufWriter::new(&mut stdin);
+                for line in lines {
+                    if writeln!(writer, "{}", line).is_err() {
+                        return;
+                    }
                 }
+                let _ = writer.flush();
+            })),
+            CmdInput::File(_) => unreachable!("input files are opened by take_input"),
+            CmdInput::Opened(file) => Some(thread::spawn(move || {
+                copy_file_to_stdin(file, &mut stdin);
+            })),
+            CmdInput::Inherit(timeout) => Some(thread::spawn(move || {
+                forward_stdin(&mut stdin, timeout);
+            })),
+        }
+    }
 
-                spawn.handle.wait()?;
-                Ok(output)
+    /// Spawn a configured command, reporting failures as [`CmdError::Spawn`].
+    ///
+    /// A program that is not found is looked up the way the child would have, so the
+    /// error can tell a missing program from one whose interpreter is missing.
+    fn spawn_child(std_cmd: &mut StdCommand, program: &OsStr) -> Result<Child, Error> {
+        std_cmd.spawn().map_err(|source| {
+            let resolved = if source.kind() == std::io::ErrorKind::NotFound {
+                let path = match std_cmd.get_envs().find(|(key, _)| *key == "PATH") {
+                    Some((_, path)) => path.map(OsStr::to_os_string),
+                    None => std::env::var_os("PATH"),
+                };
+                crate::path::resolve_in(program, path.as_deref(), std_cmd.get_current_dir())
             } else {
-                // Wait for input thread to complete if exists
-                if let Some(handle) = input_handle {
-                    let _ = handle.join();
-                }
-
-                spawn.handle.wait()?;
-                Ok(Vec::new())
-            }
-        } else {
-            // For run() method, don't capture output - let it go to terminal
-            self.suppress_echo = true;
-            let spawn = self.spawn_inherit_stdio()?;
-
-            // Handle input if provided (for backward compatibility)
-            let input_handle = match input {
-                Some(CmdInput::Bytes(bytes)) => {
-                    spawn.stdin.map(|mut stdin| {
-                        thread::spawn(move || {
-                            use std::io::Write;
-                            let _ = stdin.write_all(&bytes);
-                            drop(stdin); // Close stdin to signal EOF
-                        })
-                    })
-                }
-                Some(CmdInput::Reader(mut reader)) => {
-                    spawn.stdin.map(|stdin| {
-                        thread::spawn(move || {
-                            use std::io::copy;
-                            let mut stdin = stdin;
-                            let _ = copy(&mut reader, &mut stdin);
-                            drop(stdin); // Close stdin to signal EOF
-                        })
-                    })
-                }
-                None => None,
+                None
             };
-
-            // Wait for input thread to complete if exists
-            if let Some(handle) = input_handle {
-                let _ = handle.join();
+            CmdError::Spawn {
+                program: program.to_os_string(),
+                source,
+                resolved,
             }
-
-            spawn.handle.wait()?;
-            Ok(Vec::new())
-        }
+        })
     }
 
     fn build_std_command_static(cmd_def: &Cmd) -> StdCommand {
         let mut cmd = StdCommand::new(&cmd_def.program);
         cmd.args(&cmd_def.args);
 
+        if cmd_def.env_clear {
+            cmd.env_clear();
+        }
+        for key in &cmd_def.env_inherit {
+            if let Some(val) = std::env::var_os(key) {
+                cmd.env(key, val);
+            }
+        }
+        for key in &cmd_def.env_removes {
+            cmd.env_remove(key);
+        }
         for (key, val) in &cmd_def.envs {
             cmd.env(key, val);
         }
@@ -952,192 +2304,529 @@ impl Pipeline {
             cmd.current_dir(current_dir);
         }
 
+        #[cfg(unix)]
+        if let Some(mode) = cmd_def.umask {
+            let mode = mode as libc::mode_t;
+            // SAFETY: umask is async-signal-safe and cannot fail.
+            unsafe {
+                cmd.pre_exec(move || {
+                    libc::umask(mode);
+                    Ok(())
+                });
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(gid) = cmd_def.gid {
+            cmd.gid(gid);
+        }
+        #[cfg(unix)]
+        if let Some(uid) = cmd_def.uid {
+            cmd.uid(uid);
+        }
+
+        #[cfg(unix)]
+        if let Some(arg0) = &cmd_def.arg0 {
+            cmd.arg0(arg0);
+        }
+        #[cfg(unix)]
+        if let Some(pgid) = cmd_def.process_group {
+            cmd.process_group(pgid);
+        }
+
+        #[cfg(unix)]
+        for hook in &cmd_def.pre_exec {
+            let hook = hook.clone();
+            // SAFETY: the caller of `Cmd::pre_exec` guarantees the hook is safe to run
+            // between fork and exec.
+            unsafe {
+                cmd.pre_exec(move || (hook.0)());
+            }
+        }
+
+        #[cfg(unix)]
+        if cmd_def.stderr_to_stdout {
+            // SAFETY: dup2 is async-signal-safe. Hooks run after std has set up the
+            // child's stdio, so fd 1 is already the final stdout.
+            unsafe {
+                cmd.pre_exec(|| {
+                    if libc::dup2(libc::STDOUT_FILENO, libc::STDERR_FILENO) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        // Streams that end up piped are overridden by the caller
+        if let Some(mode) = cmd_def.stdin_mode {
+            cmd.stdin(mode.to_stdio());
+        }
+        if let Some(mode) = cmd_def.stdout_mode {
+            cmd.stdout(mode.to_stdio());
+        }
+        if let Some(mode) = cmd_def.stderr_mode {
+            cmd.stderr(mode.to_stdio());
+        }
+
         cmd
     }
 
     /// Spawn pipeline with stdio inherited from parent (for run() method)
     fn spawn_inherit_stdio(self) -> Result<PipelineSpawn, Error> {
+        self.spawn_with(true, false, false)
+    }
+
+    /// Spawn for the `spawn_io_*` methods that hand back only some of the streams.
+    ///
+    /// A single command pipes just the requested streams. A multi-command pipeline is
+    /// spawned like with [`spawn_io_all`](Self::spawn_io_all), so the last command's
+    /// stdout and stderr are piped even if they are not handed back.
+    fn spawn_io_subset(
+        self,
+        pipe_stdin: bool,
+        pipe_stdout: bool,
+        pipe_stderr: bool,
+    ) -> Result<PipelineSpawn, Error> {
+        if self.connections.len() > 1 {
+            return self.spawn_io_all();
+        }
+        self.spawn_with(pipe_stdin, pipe_stdout, pipe_stderr)
+    }
+
+    /// Echo and spawn the pipeline, piping the requested streams back to the caller.
+    ///
+    /// Streams that are not piped are inherited from the parent unless the command
+    /// redirects them itself, e.g. with [`Cmd::discard_stdout`]. The first command's
+    /// stdin is always piped in multi-command pipelines so it sees EOF when unused.
+    fn spawn_with(
+        self,
+        pipe_stdin: bool,
+        pipe_stdout: bool,
+        pipe_stderr: bool,
+    ) -> Result<PipelineSpawn, Error> {
         if !self.suppress_echo {
             self.echo_pipeline();
         }
 
         if self.connections.is_empty() {
             return Ok(PipelineSpawn {
-                handle: PipelineHandle {
-                    children: Vec::new(),
-                },
+                handle: PipelineHandle::default(),
                 stdin: None,
                 stdout: None,
                 stderr: None,
             });
         }
+        if crate::confirm::should_skip(&self.to_command_string()) {
+            let program = self
+                .connections
+                .iter()
+                .find_map(|(stage, _)| match stage {
+                    Stage::Cmd(cmd) => Some(cmd.program.clone()),
+                    Stage::Fn(_) => None,
+                })
+                .unwrap_or_default();
+            return Err(CmdError::Skipped { program });
+        }
 
-        // For single command, inherit stdio from parent
-        if self.connections.len() == 1 {
-            let cmd = self.connections.into_iter().next().unwrap().0;
-            let mut std_cmd = Self::build_std_command_static(&cmd);
-
-            // Set up I/O - inherit stdout/stderr from parent, but allow stdin input
-            std_cmd.stdin(Stdio::piped());
-            std_cmd.stdout(Stdio::inherit());
-            std_cmd.stderr(Stdio::inherit());
-
-            let mut child = std_cmd.spawn().map_err(|e| Error {
-                message: format!("Failed to spawn command: {}", cmd.program.to_string_lossy()),
-                source: Some(e),
-            })?;
+        self.check_current_dirs()?;
+
+        let pipefail = self.pipefail;
+        let kill_on_drop = self.kill_on_drop;
+        let timeout = self.timeout;
+        let cancel = self.cancel.clone();
+        let recorder = self.record.as_deref().map(IoRecorder::create).transpose()?;
+        let trace = Trace::start(
+            self.connections
+                .iter()
+                .filter_map(|(stage, _)| match stage {
+                    Stage::Cmd(cmd) => Some(cmd),
+                    Stage::Fn(_) => None,
+                }),
+        );
+        let mut spawn = if let [(Stage::Cmd(cmd), _)] = self.connections.as_slice() {
+            let mut std_cmd = Self::build_std_command_static(cmd);
+
+            let pipe_stdin = pipe_stdin && cmd.stdin_mode.is_none();
+            Self::pipe_streams(&mut std_cmd, pipe_stdin, pipe_stdout, pipe_stderr);
+
+            let mut child = Self::spawn_child(&mut std_cmd, &cmd.program)?;
+
+            let mut handle = PipelineHandle::default();
+            let (stdin, stdout, stderr) =
+                (child.stdin.take(), child.stdout.take(), child.stderr.take());
+            handle.children.push(child);
+            handle.programs.push(cmd.program.clone());
+            PipelineSpawn {
+                handle,
+                stdin,
+                stdout,
+                stderr,
+            }
+        } else {
+            self.spawn_connected(pipe_stdout, pipe_stderr)?
+        };
 
-            let stdin = child.stdin.take();
+        spawn.handle.pipefail = pipefail;
+        spawn.handle.kill_on_drop = kill_on_drop;
+        spawn.handle.trace = trace;
+        spawn.handle.cancel = cancel;
+        spawn.handle.deadline = timeout.map(|limit| Deadline {
+            at: Instant::now() + limit,
+            limit,
+        });
+        if let Some(recorder) = recorder {
+            recorder.attach(&mut spawn)?;
+        }
+        Ok(spawn)
+    }
 
-            return Ok(PipelineSpawn {
-                handle: PipelineHandle {
-                    children: vec![child],
-                },
-                stdin,
-                stdout: None,
-                stderr: None,
+    /// Check that the working directory of every command exists before spawning.
+    ///
+    /// The OS would only report a bare `ENOENT`, which reads as if the program were
+    /// missing.
+    fn check_current_dirs(&self) -> Result<(), Error> {
+        for (stage, _) in &self.connections {
+            let Stage::Cmd(cmd) = stage else { continue };
+            let Some(path) = &cmd.current_dir else {
+                continue;
+            };
+            let source = match std::fs::metadata(path) {
+                Ok(metadata) if metadata.is_dir() => continue,
+                Ok(_) => std::io::Error::from(std::io::ErrorKind::NotADirectory),
+                Err(source) => source,
+            };
+            return Err(CmdError::CurrentDir {
+                program: cmd.program.clone(),
+                path: path.clone(),
+                source,
             });
         }
+        Ok(())
+    }
+
+    /// Pipe the requested streams back to the caller.
+    ///
+    /// The other streams keep the command's own configuration, which defaults to
+    /// inheriting from the parent.
+    fn pipe_streams(cmd: &mut StdCommand, stdin: bool, stdout: bool, stderr: bool) {
+        if stdin {
+            cmd.stdin(Stdio::piped());
+        }
+        if stdout {
+            cmd.stdout(Stdio::piped());
+        }
+        if stderr {
+            cmd.stderr(Stdio::piped());
+        }
+    }
 
-        // Multi-command pipeline - inherit stdio for the last command
-        let mut children: Vec<Child> = Vec::new();
-        let mut prev_reader: Option<std::io::PipeReader> = None;
+    /// Spawn every stage of a multi-stage pipeline, connecting them with native pipes.
+    ///
+    /// The first stage's stdin is always piped. The last command's stdout and stderr
+    /// are piped back to the caller as requested, otherwise they are inherited from the
+    /// parent so the output goes to the terminal. The same holds for the stderr of
+    /// intermediate commands that is not piped to the next one. Closure stages run on
+    /// their own threads and only have an output stream, whatever the pipe mode.
+    fn spawn_connected(self, pipe_stdout: bool, pipe_stderr: bool) -> Result<PipelineSpawn, Error> {
+        let mut handle = PipelineHandle::default();
+        let mut prev_reader: Option<PipeReader> = None;
         let mut first_stdin = None;
+        let mut last_stdout = None;
+        let mut last_stderr = None;
 
-        // Spawn all commands in the pipeline
-        for (i, (cmd_def, _pipe_mode)) in self.connections.iter().enumerate() {
-            let mut cmd = Self::build_std_command_static(cmd_def);
+        let modes: Vec<PipeMode> = self.connections.iter().map(|(_, mode)| *mode).collect();
+        let last = modes.len() - 1;
+        let buffer_size = self.buffer_size;
+
+        // Spawn all stages in the pipeline
+        for (i, (stage, _pipe_mode)) in self.connections.into_iter().enumerate() {
+            let is_last = i == last;
+            let cmd_def = match stage {
+                Stage::Cmd(cmd_def) => cmd_def,
+                Stage::Fn(stage_fn) => {
+                    let reader = match prev_reader.take() {
+                        Some(reader) => reader,
+                        None => {
+                            let (reader, writer) = Self::create_pipe()?;
+                            first_stdin = Some(child_stream(writer));
+                            reader
+                        }
+                    };
+                    let writer: Box<dyn Write + Send> = if !is_last {
+                        let (reader, writer) = Self::create_pipe()?;
+                        prev_reader = Some(reader);
+                        Box::new(writer)
+                    } else if pipe_stdout {
+                        let (reader, writer) = Self::create_pipe()?;
+                        last_stdout = Some(child_stream(reader));
+                        Box::new(writer)
+                    } else {
+                        Box::new(std::io::stdout())
+                    };
+                    handle
+                        .threads
+                        .push(Self::spawn_stage(stage_fn, reader, writer, buffer_size));
+                    continue;
+                }
+            };
+
+            let mut cmd = Self::build_std_command_static(&cmd_def);
 
             // Set up stdin
             if i == 0 {
                 // First command: set up for potential input
-                cmd.stdin(Stdio::piped());
+                if cmd_def.stdin_mode.is_none() {
+                    cmd.stdin(Stdio::piped());
+                }
             } else {
-                // Subsequent commands: use previous command's output
+                // Subsequent commands: use previous stage's output
                 if let Some(reader) = prev_reader.take() {
                     cmd.stdin(Stdio::from(reader));
                 }
             }
 
             // Set up stdout and stderr
-            let is_last = i == self.connections.len() - 1;
             if is_last {
-                // Last command: inherit stdio to display output to terminal
-                cmd.stdout(Stdio::inherit());
-                cmd.stderr(Stdio::inherit());
+                Self::pipe_streams(&mut cmd, false, pipe_stdout, pipe_stderr);
             } else {
-                // Intermediate commands: pipe to next command
-                let next_pipe_mode = self.connections[i + 1].1;
-                match next_pipe_mode {
+                // Intermediate commands: pipe to next stage
+                let (reader, writer) = Self::create_pipe()?;
+                match modes[i + 1] {
                     PipeMode::Stdout => {
-                        let (reader, writer) = std::io::pipe().map_err(|e| Error {
-                            message: "Failed to create stdout pipe".to_string(),
-                            source: Some(e),
-                        })?;
                         cmd.stdout(Stdio::from(writer));
-                        prev_reader = Some(reader);
                     }
                     PipeMode::Stderr => {
-                        let (reader, writer) = std::io::pipe().map_err(|e| Error {
-                            message: "Failed to create stderr pipe".to_string(),
-                            source: Some(e),
-                        })?;
                         cmd.stderr(Stdio::from(writer));
-                        prev_reader = Some(reader);
                     }
                     PipeMode::Both => {
-                        let (reader, writer) = std::io::pipe().map_err(|e| Error {
-                            message: "Failed to create combined pipe".to_string(),
-                            source: Some(e),
-                        })?;
-                        let writer_clone = writer.try_clone().map_err(|e| Error {
-                            message: "Failed to clone pipe writer".to_string(),
-                            source: Some(e),
-                        })?;
+                        let writer_clone = writer.try_clone().map_err(CmdError::Io)?;
                         cmd.stdout(Stdio::from(writer));
                         cmd.stderr(Stdio::from(writer_clone));
-                        prev_reader = Some(reader);
                     }
                 }
+                prev_reader = Some(reader);
             }
 
-            let mut child = cmd.spawn().map_err(|e| Error {
-                message: format!(
-                    "Failed to spawn command: {}",
-                    cmd_def.program.to_string_lossy()
-                ),
-                source: Some(e),
-            })?;
+            let mut child = Self::spawn_child(&mut cmd, &cmd_def.program)?;
 
-            // Store stdin of first command for potential input
+            // The command still owns the parent's copies of the pipe ends handed to this
+            // stage. Release them right away so that only the child holds them and the
+            // next stage observes EOF as soon as this one exits.
+            drop(cmd);
+
+            // Capture I/O handles
             if i == 0 {
                 first_stdin = child.stdin.take();
             }
+            if is_last {
+                last_stdout = child.stdout.take();
+                last_stderr = child.stderr.take();
+            }
 
-            children.push(child);
+            handle.children.push(child);
+            handle.programs.push(cmd_def.program);
         }
 
         Ok(PipelineSpawn {
-            handle: PipelineHandle { children },
+            handle,
             stdin: first_stdin,
-            stdout: None,
-            stderr: None,
+            stdout: last_stdout,
+            stderr: last_stderr,
+        })
+    }
+
+    /// Run a closure stage on its own thread, flushing its output when it returns.
+    fn spawn_stage(
+        stage_fn: StageFn,
+        reader: PipeReader,
+        writer: Box<dyn Write + Send>,
+        buffer_size: usize,
+    ) -> thread::JoinHandle<std::io::Result<()>> {
+        thread::spawn(move || {
+            let mut reader = BufReader::with_capacity(buffer_size, reader);
+            let mut writer = BufWriter::with_capacity(buffer_size, writer);
+            (stage_fn.body)(&mut reader, &mut writer)?;
+            writer.flush()
         })
     }
 
+    /// Create a native pipe connecting two pipeline stages.
+    ///
+    /// `std::io::pipe` opens both ends with close-on-exec set (and `try_clone` preserves
+    /// it), so an end is only inherited by the child it is explicitly passed to through
+    /// `Stdio`. Without this, grandchildren started by a stage could keep a pipe open and
+    /// the downstream command would never see EOF.
+    fn create_pipe() -> Result<(PipeReader, PipeWriter), Error> {
+        std::io::pipe().map_err(CmdError::Io)
+    }
+
+    /// The command line as it is echoed, without ANSI escape sequences.
+    ///
+    /// See [`Cmd::to_command_string`] for details.
+    pub fn to_command_string(&self) -> String {
+        crate::style::strip_ansi(&self.render(false))
+    }
+
     fn echo_pipeline(&self) {
         if !crate::output::should_echo() {
             return;
         }
 
-        let mut parts = Vec::new();
+        let Theme {
+            prefix,
+            env_label,
+            env,
+            ..
+        } = crate::color::theme();
+
+        // The directory relative paths and `cd:` are resolved against
+        let verbose = self.verbose_echo || crate::output::should_echo_verbose();
+        let cwd = verbose
+            .then(std::env::current_dir)
+            .and_then(Result::ok)
+            .map(|dir| {
+                let quoted_dir = Cmd::quote_argument(dir.as_os_str());
+                format!("{env_label}cwd:{env_label:#} {env}{quoted_dir}{env:#} ")
+            })
+            .unwrap_or_default();
+
+        crate::output::write_echo_to(
+            self.echo_writer.as_ref(),
+            crate::output::EchoKind::Cmd,
+            format_args!(
+                " {prefix}{}:cmd{prefix:#} {}{}",
+                env!("CARGO_PKG_NAME"),
+                cwd,
+                self.render(verbose)
+            ),
+        );
+    }
 
-        // Add cmd prefix
-        parts.push(format!(
-            " {BRIGHT_BLACK}{}:cmd{BRIGHT_BLACK:#}",
-            env!("CARGO_PKG_NAME")
-        ));
+    /// Render the colored command line shown in the echo, with extra details if `verbose`.
+    fn render(&self, verbose: bool) -> String {
+        let Theme {
+            program,
+            args,
+            env_label,
+            env,
+            redirection,
+            ..
+        } = crate::color::theme();
+        let mut parts = Vec::new();
 
-        for (i, (cmd, pipe_mode)) in self.connections.iter().enumerate() {
+        for (i, (stage, pipe_mode)) in self.connections.iter().enumerate() {
             if i > 0 {
                 let pipe_symbol = match pipe_mode {
                     PipeMode::Stdout => "|",
                     PipeMode::Stderr => "|&",
                     PipeMode::Both => "|&&",
                 };
-                parts.push(format!("{MAGENTA}{pipe_symbol}{MAGENTA:#}"));
+                parts.push(format!("{redirection}{pipe_symbol}{redirection:#}"));
             }
 
+            let cmd = match stage {
+                Stage::Cmd(cmd) => cmd,
+                Stage::Fn(stage_fn) => {
+                    parts.push(format!("{program}{}{program:#}", stage_fn.name));
+                    continue;
+                }
+            };
+
             // Add current directory if set
             if let Some(current_dir) = &cmd.current_dir {
                 let quoted_dir = Cmd::quote_argument(current_dir.as_os_str());
-                parts.push(format!("{BRIGHT_BLUE}cd:{BRIGHT_BLUE:#}"));
-                parts.push(format!(
-                    "{UNDERLINE_BRIGHT_BLUE}{quoted_dir}{UNDERLINE_BRIGHT_BLUE:#}"
-                ));
+                parts.push(format!("{env_label}cd:{env_label:#}"));
+                parts.push(format!("{env}{quoted_dir}{env:#}"));
+            }
+
+            if let Some(mode) = cmd.umask {
+                parts.push(format!("{env_label}umask:{env_label:#}"));
+                parts.push(format!("{env}{mode:03o}{env:#}"));
+            }
+
+            #[cfg(unix)]
+            for (label, id) in [("uid", cmd.uid), ("gid", cmd.gid)] {
+                if let Some(id) = id {
+                    parts.push(format!("{env_label}{label}:{env_label:#}"));
+                    parts.push(format!("{env}{id}{env:#}"));
+                }
             }
 
             // Add environment variables
+            if cmd.env_clear {
+                parts.push(format!("{env_label}env:{env_label:#}"));
+                parts.push(format!("{env}-i{env:#}"));
+            }
+            if verbose {
+                for key in &cmd.env_inherit {
+                    let quoted_key = Cmd::quote_argument(key);
+                    parts.push(format!("{env_label}env:{env_label:#}"));
+                    parts.push(format!("{env}+{quoted_key}{env:#}"));
+                }
+            }
+            for key in &cmd.env_removes {
+                let quoted_key = Cmd::quote_argument(key);
+                parts.push(format!("{env_label}env:{env_label:#}"));
+                parts.push(format!("{env}-{quoted_key}{env:#}"));
+            }
             for (key, val) in &cmd.envs {
                 let quoted_key = Cmd::quote_argument(key);
                 let quoted_val = Cmd::quote_argument(val);
-                parts.push(format!("{BRIGHT_BLUE}env:{BRIGHT_BLUE:#}"));
-                parts.push(format!(
-                    "{UNDERLINE_BRIGHT_BLUE}{quoted_key}={quoted_val}{UNDERLINE_BRIGHT_BLUE:#}"
-                ));
+                parts.push(format!("{env_label}env:{env_label:#}"));
+                parts.push(format!("{env}{quoted_key}={quoted_val}{env:#}"));
             }
 
             // Add program
             let quoted_program = Cmd::quote_argument(&cmd.program);
-            parts.push(format!("{BOLD_CYAN}{quoted_program}{BOLD_CYAN:#}"));
+            parts.push(format!("{program}{quoted_program}{program:#}"));
 
             // Add arguments
             for arg in &cmd.args {
                 let quoted_arg = Cmd::quote_argument(arg);
-                parts.push(format!("{BOLD_UNDERLINE}{quoted_arg}{BOLD_UNDERLINE:#}"));
+                parts.push(format!("{args}{quoted_arg}{args:#}"));
+            }
+
+            // Add input redirection after the first command
+            if i == 0 {
+                let path = match (cmd.stdin_mode, &self.input) {
+                    (Some(StdioMode::Null), _) => Some(Path::new("/dev/null")),
+                    (None, Some(CmdInput::File(path))) => Some(path.as_path()),
+                    _ => None,
+                };
+                if let Some(path) = path {
+                    let quoted_path = Cmd::quote_argument(path.as_os_str());
+                    parts.push(format!("{redirection}<{redirection:#}"));
+                    parts.push(format!("{args}{quoted_path}{args:#}"));
+                }
+            }
+
+            // `2>&1` follows any output file, which stderr then goes to as well
+            let is_last = i + 1 == self.connections.len();
+            if cmd.stderr_to_stdout && !(is_last && self.output_file.is_some()) {
+                parts.push(format!("{redirection}2>&1{redirection:#}"));
+            }
+        }
+
+        // Add output redirection after the last command
+        if let Some(output) = &self.output_file {
+            let quoted_path = Cmd::quote_argument(output.path.as_os_str());
+            let symbol = if output.append { ">>" } else { ">" };
+            parts.push(format!("{redirection}{symbol}{redirection:#}"));
+            parts.push(format!("{args}{quoted_path}{args:#}"));
+            if matches!(self.connections.last(), Some((Stage::Cmd(cmd), _)) if cmd.stderr_to_stdout)
+            {
+                parts.push(format!("{redirection}2>&1{redirection:#}"));
             }
         }
 
-        eprintln!("{}", parts.join(" "));
+        parts.join(" ")
+    }
+}
+
+/// Formats the pipeline as [`to_command_string`](Pipeline::to_command_string) does.
+impl std::fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_command_string())
     }
 }
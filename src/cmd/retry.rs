@@ -0,0 +1,70 @@
+//! Running flaky commands again until they succeed.
+
+use super::types::{Cmd, Retry};
+use crate::error::{CmdError, Error};
+
+impl Retry {
+    /// Run the command, retrying it as configured.
+    pub fn run(self) -> Result<(), Error> {
+        self.exec(Cmd::run)
+    }
+
+    /// Run the command, retrying it as configured, and return the text output of the
+    /// attempt that succeeded.
+    pub fn output(self) -> Result<String, Error> {
+        self.exec(Cmd::output)
+    }
+
+    /// Run the command, retrying it as configured, and return the binary output of the
+    /// attempt that succeeded.
+    pub fn output_bytes(self) -> Result<Vec<u8>, Error> {
+        self.exec(Cmd::output_bytes)
+    }
+
+    /// Whether a failed attempt is worth another one.
+    fn should_retry(&self, error: &CmdError) -> bool {
+        match &self.predicate {
+            Some(predicate) => predicate(error),
+            None => matches!(
+                error,
+                CmdError::NonZeroExit { .. } | CmdError::Signaled { .. }
+            ),
+        }
+    }
+
+    /// Run attempts until one succeeds, the error is not retried, or none are left.
+    ///
+    /// The command line is echoed by the first attempt; later ones only echo their number.
+    fn exec<T>(self, mut f: impl FnMut(Cmd) -> Result<T, Error>) -> Result<T, Error> {
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+        loop {
+            let cmd = if attempt == 1 {
+                self.cmd.clone()
+            } else {
+                if !self.cmd.suppress_echo {
+                    let prefix = crate::color::theme().prefix;
+                    crate::output::conditional_eprintln_to(
+                        self.cmd.echo_writer.as_ref(),
+                        crate::output::EchoKind::Cmd,
+                        format_args!(
+                            " {prefix}{}:retry attempt {attempt}/{}{prefix:#}",
+                            env!("CARGO_PKG_NAME"),
+                            self.attempts
+                        ),
+                    );
+                }
+                self.cmd.clone().no_echo()
+            };
+
+            match f(cmd) {
+                Err(e) if attempt < self.attempts && self.should_retry(&e) => {}
+                result => return result,
+            }
+
+            std::thread::sleep(delay);
+            delay = delay.saturating_mul(2);
+            attempt += 1;
+        }
+    }
+}
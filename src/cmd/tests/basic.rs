@@ -95,6 +95,53 @@ fn test_args_method() {
     );
 }
 
+/// Tests building the argument list at run time from a `Vec` or a slice
+#[test]
+fn test_args_from_vec() {
+    let args: Vec<OsString> = vec!["-n".into(), "a b".into()];
+    let cmd = Cmd::new("echo").args(&args);
+    assert_eq!(cmd.args, args);
+
+    let cmd = cmd!("echo", "-n").args(&args[1..]);
+    assert_eq!(cmd.args, args);
+
+    let output = Cmd::new("echo").args(args).no_echo().output().unwrap();
+    assert_eq!(output, "a b");
+}
+
+/// Tests passing a custom `argv[0]` to the program
+#[cfg(target_os = "linux")]
+#[test]
+fn test_arg0() {
+    let output = Cmd::new("cat")
+        .arg0("custom-name")
+        .arg("/proc/self/cmdline")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "custom-name\0/proc/self/cmdline\0");
+
+    // The echo shows the program that is executed
+    assert_eq!(
+        Cmd::new("cat").arg0("custom-name").to_command_string(),
+        "cat"
+    );
+}
+
+/// Tests that `arg_if()` and `args_if()` only add arguments when the condition holds
+#[test]
+fn test_arg_if() {
+    let cmd = Cmd::new("rsync")
+        .arg_if(true, "-v")
+        .arg_if(false, "--dry-run")
+        .args_if(true, ["-a", "-z"])
+        .args_if(false, ["--delete"])
+        .arg("src/");
+    assert_eq!(cmd.args, ["-v", "-a", "-z", "src/"].map(OsString::from));
+
+    assert!(Cmd::new("ls").arg_if(false, "-l").args.is_empty());
+}
+
 /// Tests that all builder methods work correctly in combination
 #[test]
 fn test_builder_pattern_completeness() {
@@ -130,3 +177,462 @@ fn test_builder_pattern_completeness() {
     assert_eq!(cmd.current_dir, Some(temp_dir));
     assert!(cmd.suppress_echo);
 }
+
+/// Tests that `output_plain()` strips ANSI escape sequences
+#[test]
+fn test_output_plain_strips_ansi() {
+    let output = cmd!(
+        "printf",
+        "\\033[1;31merror\\033[0m: \\033[4mfile\\033[0m\\n"
+    )
+    .no_echo()
+    .output_plain()
+    .unwrap();
+    assert_eq!(output, "error: file\n");
+}
+
+/// Tests that `filter()` returns both the transformed output and the exit status
+#[test]
+fn test_filter_returns_output_and_status() {
+    let (output, status) = cmd!("tr", "a-z", "A-Z")
+        .no_echo()
+        .filter("hello world\n")
+        .unwrap();
+    assert_eq!(output, "HELLO WORLD\n");
+    assert!(status.success());
+
+    // A non-zero exit is reported through the status, not as an error
+    let (output, status) = cmd!("grep", "missing").no_echo().filter("a\nb\n").unwrap();
+    assert_eq!(output, "");
+    assert_eq!(status.code(), Some(1));
+
+    // Pipelines filter too; the status is that of the last command
+    let (output, status) = cmd!("tr", "a-z", "A-Z")
+        .pipe(cmd!("sh", "-c", "cat; exit 3"))
+        .no_echo()
+        .filter("abc")
+        .unwrap();
+    assert_eq!(output, "ABC");
+    assert_eq!(status.code(), Some(3));
+}
+
+/// Tests that `capture()` returns stdout, stderr and status without failing on non-zero exit
+#[test]
+fn test_capture_output_struct() {
+    let out = cmd!("sh", "-c", "echo out; echo err >&2; exit 4")
+        .no_echo()
+        .capture()
+        .unwrap();
+    assert_eq!(out.stdout, b"out\n");
+    assert_eq!(out.stderr_str(), "err\n");
+    assert_eq!(out.stdout_str(), "out\n");
+    assert_eq!(out.status.code(), Some(4));
+
+    let out = cmd!("echo", "hello")
+        .pipe(cmd!("tr", "a-z", "A-Z"))
+        .no_echo()
+        .capture()
+        .unwrap();
+    assert_eq!(out.stdout_str(), "HELLO\n");
+    assert!(out.stderr.is_empty());
+    assert!(out.status.success());
+}
+
+/// Tests that `output_with_status()` returns stdout and the status of a failing command
+#[test]
+fn test_output_with_status() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let dir = temp.path();
+    std::fs::write(dir.join("a"), "same\n").unwrap();
+    std::fs::write(dir.join("b"), "other\n").unwrap();
+
+    // diff exits with 1 when the files differ, which is not an error here
+    let (output, status) = cmd!("diff", dir.join("a"), dir.join("b"))
+        .no_echo()
+        .output_with_status()
+        .unwrap();
+    assert_eq!(status.code(), Some(1));
+    assert!(output.contains("< same"));
+    assert!(output.contains("> other"));
+
+    let (output, status) = cmd!("diff", dir.join("a"), dir.join("a"))
+        .no_echo()
+        .output_with_status()
+        .unwrap();
+    assert!(status.success());
+    assert!(output.is_empty());
+
+    // Only spawn failures are errors
+    assert!(matches!(
+        cmd!("scripty-no-such-program")
+            .no_echo()
+            .output_with_status(),
+        Err(crate::CmdError::Spawn { .. })
+    ));
+}
+
+/// Tests timing a command with `measure()`
+#[test]
+fn test_measure() {
+    use std::time::Duration;
+
+    let (output, took) = cmd!("sh", "-c", "sleep 0.2; echo done")
+        .no_echo()
+        .measure()
+        .unwrap();
+    assert_eq!(output, "done\n");
+    assert!(took >= Duration::from_millis(200), "{took:?}");
+    assert!(took < Duration::from_secs(5), "{took:?}");
+
+    let (output, _) = cmd!("echo", "a")
+        .pipe(cmd!("tr", "a", "b"))
+        .no_echo()
+        .measure()
+        .unwrap();
+    assert_eq!(output, "b\n");
+
+    assert!(matches!(
+        cmd!("false").no_echo().measure(),
+        Err(crate::CmdError::NonZeroExit { .. })
+    ));
+}
+
+/// Tests feeding a file to stdin with `input_file()`
+#[test]
+fn test_input_file() {
+    use crate::error::CmdError;
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("input");
+    std::fs::write(&path, "banana\napple\ncherry\n").unwrap();
+
+    let output = cmd!("sort").input_file(&path).no_echo().output().unwrap();
+    assert_eq!(output, "apple\nbanana\ncherry\n");
+
+    let output = cmd!("cat")
+        .pipe(cmd!("wc", "-l"))
+        .input_file(&path)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "3");
+
+    std::fs::remove_file(&path).unwrap();
+
+    // A missing file is reported with its path before anything runs
+    let error = cmd!("cat").input_file(&path).no_echo().run().unwrap_err();
+    match &error {
+        CmdError::InputFile { path: p, source } => {
+            assert_eq!(p, &path);
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        other => panic!("expected an input file error, got {:?}", other),
+    }
+    assert!(error.to_string().contains(&*path.to_string_lossy()));
+}
+
+/// Tests concatenating the outputs of several commands in order
+#[test]
+fn test_concat_outputs() {
+    use crate::concat_outputs;
+
+    let output = concat_outputs([
+        cmd!("echo", "first").no_echo(),
+        cmd!("sh", "-c", "sleep 0.1; echo second").no_echo(),
+        cmd!("echo", "third").no_echo(),
+    ])
+    .unwrap();
+    assert_eq!(output, "first\nsecond\nthird\n");
+
+    assert_eq!(concat_outputs(Vec::new()).unwrap(), "");
+
+    // Stops at the first failure
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let marker = temp.path().join("marker");
+    let result = concat_outputs([
+        cmd!("echo", "first").no_echo(),
+        cmd!("false").no_echo(),
+        cmd!("touch").arg(&marker).no_echo(),
+    ]);
+    assert!(result.is_err());
+    assert!(!marker.exists());
+}
+
+/// Tests splitting the output into lines
+#[test]
+fn test_output_lines() {
+    let lines = cmd!("printf", "a\\nb b\\r\\nc\\n")
+        .no_echo()
+        .output_lines()
+        .unwrap();
+    assert_eq!(lines, vec!["a", "b b", "c"]);
+
+    // No trailing newline, blank lines in between are kept
+    let lines = cmd!("printf", "a\\n\\nb").no_echo().output_lines().unwrap();
+    assert_eq!(lines, vec!["a", "", "b"]);
+
+    assert!(cmd!("true").no_echo().output_lines().unwrap().is_empty());
+
+    let lines = cmd!("printf", "b\\na\\n")
+        .pipe(cmd!("sort"))
+        .no_echo()
+        .output_lines()
+        .unwrap();
+    assert_eq!(lines, vec!["a", "b"]);
+}
+
+/// Tests that output_trimmed() strips surrounding whitespace only
+#[test]
+fn test_output_trimmed() {
+    let output = cmd!("printf", "  a b \n\n")
+        .no_echo()
+        .output_trimmed()
+        .unwrap();
+    assert_eq!(output, "a b");
+
+    let output = cmd!("echo", "x")
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .output_trimmed()
+        .unwrap();
+    assert_eq!(output, "x");
+
+    assert!(cmd!("false").no_echo().output_trimmed().is_err());
+}
+
+/// Tests deserializing JSON output
+#[cfg(feature = "serde")]
+#[test]
+fn test_output_json() {
+    use crate::CmdError;
+    use std::collections::HashMap;
+
+    let value: HashMap<String, Vec<u32>> = cmd!("echo", r#"{"a": [1, 2], "b": []}"#)
+        .no_echo()
+        .output_json()
+        .unwrap();
+    assert_eq!(value["a"], [1, 2]);
+    assert!(value["b"].is_empty());
+
+    let value: serde_json::Value = cmd!("echo", r#"{"name": "scripty"}"#)
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .output_json()
+        .unwrap();
+    assert_eq!(value["name"], "scripty");
+
+    // The error shows where parsing failed
+    let output = format!("{{\"items\": [\n{}\n  oops\n]}}", "  1,\n".repeat(20));
+    let error = cmd!("printf", "%s", output)
+        .no_echo()
+        .output_json::<serde_json::Value>()
+        .unwrap_err();
+    let CmdError::Json {
+        program, snippet, ..
+    } = &error
+    else {
+        panic!("unexpected error: {error}");
+    };
+    assert_eq!(program, "printf");
+    assert!(snippet.contains("oops"), "{snippet}");
+    assert!(snippet.starts_with("..."), "{snippet}");
+    assert!(error.to_string().contains("oops"));
+
+    // Command failures are reported as usual
+    assert!(matches!(
+        cmd!("false").no_echo().output_json::<serde_json::Value>(),
+        Err(CmdError::NonZeroExit { .. })
+    ));
+}
+
+/// Tests that a large input file reaches the command intact through `splice`
+#[test]
+fn test_input_file_splice() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("input");
+    let data: Vec<u8> = (0..8 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+
+    #[cfg(target_os = "linux")]
+    let spliced = || crate::cmd::pipeline::SPLICED_BYTES.load(std::sync::atomic::Ordering::Relaxed);
+    #[cfg(target_os = "linux")]
+    let before = spliced();
+
+    let output = cmd!("wc", "-c")
+        .input_file(&path)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), data.len().to_string());
+
+    let output = cmd!("cat")
+        .input_file(&path)
+        .no_echo()
+        .output_bytes()
+        .unwrap();
+    assert!(output == data);
+
+    // Other tests may splice at the same time, so only a lower bound can be checked
+    #[cfg(target_os = "linux")]
+    assert!(spliced() - before >= 2 * data.len() as u64);
+}
+
+/// Tests feeding lines from an iterator with `input_iter()`
+#[test]
+fn test_input_iter() {
+    let names = vec![String::from("banana"), String::from("apple")];
+    let output = cmd!("sort").input_iter(names).no_echo().output().unwrap();
+    assert_eq!(output, "apple\nbanana\n");
+
+    let output = cmd!("cat")
+        .pipe(cmd!("wc", "-l"))
+        .input_iter(["a", "b", "c"])
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "3");
+
+    // An endless iterator stops quietly once the command exits
+    let output = cmd!("head", "-3")
+        .input_iter((1..).map(|n: u64| n.to_string()))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "1\n2\n3\n");
+}
+
+/// Tests capping captured output with output_bytes_limited() and output_bytes_truncated()
+#[test]
+fn test_output_bytes_limited() {
+    // Output that fits is returned as is, even when it hits the limit exactly
+    let output = cmd!("printf", "12345")
+        .no_echo()
+        .output_bytes_limited(5)
+        .unwrap();
+    assert_eq!(output, b"12345");
+    let (output, truncated) = cmd!("printf", "12345")
+        .no_echo()
+        .output_bytes_truncated(5)
+        .unwrap();
+    assert_eq!(output, b"12345");
+    assert!(!truncated);
+
+    // Endless output stops at the limit
+    let error = cmd!("yes")
+        .no_echo()
+        .output_bytes_limited(1000)
+        .unwrap_err();
+    assert!(
+        matches!(error, crate::CmdError::OutputLimit { limit: 1000, ref program } if program == "yes")
+    );
+    assert_eq!(
+        error.to_string(),
+        "Output of 'yes' exceeded the limit of 1000 bytes"
+    );
+
+    let (output, truncated) = cmd!("yes")
+        .pipe(cmd!("tr", "y", "n"))
+        .no_echo()
+        .output_bytes_truncated(6)
+        .unwrap();
+    assert_eq!(output, b"n\nn\nn\n");
+    assert!(truncated);
+
+    // A failing command that stays within the limit is still an error
+    assert!(matches!(
+        cmd!("sh", "-c", "echo hi; exit 1")
+            .no_echo()
+            .output_bytes_truncated(100)
+            .unwrap_err(),
+        crate::CmdError::NonZeroExit { .. }
+    ));
+}
+
+/// Tests running a command in a scratch directory with run_in_temp()
+#[test]
+fn test_run_in_temp() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let marker = temp.path().join("marker");
+
+    cmd!("sh", "-c", "pwd > \"$1\"; touch file", "sh", &marker)
+        .no_echo()
+        .run_in_temp()
+        .unwrap();
+    let dir = std::fs::read_to_string(&marker).unwrap();
+    let dir = std::path::Path::new(dir.trim());
+    let name = dir.file_name().unwrap().to_string_lossy();
+    assert!(name.starts_with("scripty-"), "{name}");
+    assert!(!dir.exists());
+
+    // The directory is removed when the command fails as well
+    let error = cmd!("sh", "-c", "pwd > \"$1\"; exit 1", "sh", &marker)
+        .no_echo()
+        .run_in_temp()
+        .unwrap_err();
+    assert!(matches!(error, crate::CmdError::NonZeroExit { .. }));
+    let dir = std::fs::read_to_string(&marker).unwrap();
+    assert!(!std::path::Path::new(dir.trim()).exists());
+}
+
+/// Tests running commands from async code with the `tokio` feature
+#[cfg(feature = "tokio")]
+#[test]
+fn test_async_execution() {
+    use std::time::{Duration, Instant};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let output = cmd!("echo", "hello")
+            .pipe(cmd!("tr", "a-z", "A-Z"))
+            .no_echo()
+            .output_async()
+            .await
+            .unwrap();
+        assert_eq!(output, "HELLO\n");
+
+        let bytes = cmd!("printf", "\\001\\002")
+            .no_echo()
+            .output_bytes_async()
+            .await
+            .unwrap();
+        assert_eq!(bytes, [1, 2]);
+
+        let error = cmd!("false").no_echo().run_async().await.unwrap_err();
+        assert!(matches!(error, crate::CmdError::NonZeroExit { .. }));
+
+        // Commands awaited on a single-threaded runtime still run side by side
+        let start = Instant::now();
+        let first = tokio::spawn(cmd!("sleep", "0.5").no_echo().run_async());
+        let second = tokio::spawn(cmd!("sleep", "0.5").no_echo().run_async());
+        first.await.unwrap().unwrap();
+        second.await.unwrap().unwrap();
+        assert!(start.elapsed() < Duration::from_millis(900));
+    });
+}
+
+/// Tests that dropping the future of an async run terminates the command
+#[cfg(feature = "tokio")]
+#[test]
+fn test_async_cancel() {
+    use std::time::{Duration, Instant};
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let start = Instant::now();
+    runtime.block_on(async {
+        let task = tokio::spawn(cmd!("sleep", "10").no_echo().run_async());
+        // Give the command time to start without blocking the runtime
+        tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_millis(200)))
+            .await
+            .unwrap();
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+    });
+    // Shutting the runtime down waits for the blocking thread, which only returns once
+    // the command has been stopped
+    drop(runtime);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -132,3 +132,308 @@ fn test_empty_environment_variables() {
     // printenv should fail for unset variables
     assert!(result.is_err());
 }
+
+/// Tests removing inherited and previously set environment variables
+#[test]
+fn test_env_remove() {
+    let output = cmd!("env")
+        .env("SCRIPTY_REMOVED", "1")
+        .env("SCRIPTY_KEPT", "2")
+        .env_remove("SCRIPTY_REMOVED")
+        .env_remove("PATH")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert!(output.contains("SCRIPTY_KEPT=2"));
+    assert!(!output.contains("SCRIPTY_REMOVED"));
+    assert!(!output.lines().any(|line| line.starts_with("PATH=")));
+
+    // Setting a variable again after removing it wins
+    let output = cmd!("printenv", "SCRIPTY_READDED")
+        .env_remove("SCRIPTY_READDED")
+        .env("SCRIPTY_READDED", "back")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "back");
+}
+
+/// Tests that `env_clear()` followed by `env()` yields exactly the given variables
+#[test]
+fn test_env_clear_is_deterministic() {
+    let output = cmd!("/usr/bin/env")
+        .env("DROPPED", "x")
+        .env_clear()
+        .env("B", "2")
+        .env("A", "1")
+        .no_echo()
+        .output()
+        .unwrap();
+    let mut vars: Vec<&str> = output.lines().collect();
+    vars.sort();
+    assert_eq!(vars, ["A=1", "B=2"]);
+}
+
+/// Tests setting multiple environment variables at once with `envs()`
+#[test]
+fn test_envs_from_collections() {
+    use std::collections::HashMap;
+
+    let output = cmd!("sh", "-c", "echo $ENVS_A-$ENVS_B")
+        .envs([("ENVS_B", "2"), ("ENVS_A", "1")])
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "1-2");
+
+    let vars: HashMap<String, String> = [("ENVS_X", "x"), ("ENVS_Y", "y"), ("ENVS_Z", "z")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let cmd = cmd!("printenv").envs(&vars);
+    let keys: Vec<_> = cmd.envs.iter().map(|(k, _)| k.clone()).collect();
+    assert_eq!(keys, ["ENVS_X", "ENVS_Y", "ENVS_Z"]);
+
+    let output = cmd.no_echo().output().unwrap();
+    assert!(output.contains("ENVS_X=x"));
+    assert!(output.contains("ENVS_Z=z"));
+}
+
+/// Tests setting and removing variables together with `env_overrides()`
+#[test]
+fn test_env_overrides() {
+    use std::collections::HashMap;
+
+    let cmd = cmd!("env")
+        .env("SCRIPTY_OVERRIDE_GONE", "1")
+        .env_overrides([
+            ("SCRIPTY_OVERRIDE_SET", Some("2")),
+            ("SCRIPTY_OVERRIDE_GONE", None),
+            ("PATH", None),
+        ]);
+    assert_eq!(
+        cmd.to_command_string(),
+        "env: -PATH env: -SCRIPTY_OVERRIDE_GONE env: SCRIPTY_OVERRIDE_SET=2 env"
+    );
+
+    let output = cmd.no_echo().output().unwrap();
+    assert!(output.contains("SCRIPTY_OVERRIDE_SET=2"));
+    assert!(!output.contains("SCRIPTY_OVERRIDE_GONE"));
+    assert!(!output.lines().any(|line| line.starts_with("PATH=")));
+
+    let overrides: HashMap<&str, Option<String>> =
+        HashMap::from([("SCRIPTY_OVERRIDE_MAP", Some("m".to_string()))]);
+    let output = cmd!("printenv", "SCRIPTY_OVERRIDE_MAP")
+        .env_overrides(overrides)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "m\n");
+}
+
+/// Tests passing only an allowlist of the parent's variables with `env_inherit_only()`
+#[test]
+fn test_env_inherit_only() {
+    let cmd = cmd!("env")
+        .env_inherit_only(["PATH", "SCRIPTY_INHERIT_UNSET"])
+        .env("SCRIPTY_INHERIT_SET", "1");
+    assert_eq!(
+        cmd.to_command_string(),
+        "env: -i env: SCRIPTY_INHERIT_SET=1 env"
+    );
+
+    let (output, echo) = crate::capture_echo(|| cmd.verbose_echo().output());
+    let mut names: Vec<_> = output
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.split_once('=').map(|(name, _)| name.to_owned()))
+        .collect();
+    names.sort();
+    assert_eq!(names, ["PATH", "SCRIPTY_INHERIT_SET"]);
+    if crate::output::should_echo() {
+        assert!(echo.contains(
+            "env: -i env: +PATH env: +SCRIPTY_INHERIT_UNSET env: SCRIPTY_INHERIT_SET=1 env"
+        ));
+    }
+
+    // Removing a variable takes it off the list
+    let output = cmd!("env")
+        .env_inherit_only(["PATH"])
+        .env_remove("PATH")
+        .no_echo()
+        .output();
+    assert!(!output.unwrap_or_default().contains("PATH="));
+}
+
+/// Tests choosing the program from an environment variable
+#[test]
+fn test_cmd_from_env() {
+    use super::Cmd;
+
+    // SAFETY: The variables are unique to this test and removed afterwards
+    unsafe {
+        env::set_var("SCRIPTY_TEST_FROM_ENV_TOOL", "echo");
+        env::set_var("SCRIPTY_TEST_FROM_ENV_WORDS", "printf  %s-%s ");
+        env::set_var("SCRIPTY_TEST_FROM_ENV_BLANK", "  ");
+    }
+
+    let output = Cmd::from_env("SCRIPTY_TEST_FROM_ENV_TOOL", "false")
+        .arg("chosen")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "chosen\n");
+
+    // Extra words become arguments
+    let output = Cmd::from_env("SCRIPTY_TEST_FROM_ENV_WORDS", "false")
+        .args(["a", "b"])
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "a-b");
+
+    // Unset or blank variables fall back to the default
+    let output = Cmd::from_env("SCRIPTY_TEST_FROM_ENV_UNSET", "echo")
+        .arg("default")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "default\n");
+    let output = Cmd::from_env("SCRIPTY_TEST_FROM_ENV_BLANK", "echo")
+        .arg("default")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "default\n");
+
+    // SAFETY: Cleaning up the variables set above
+    unsafe {
+        env::remove_var("SCRIPTY_TEST_FROM_ENV_TOOL");
+        env::remove_var("SCRIPTY_TEST_FROM_ENV_WORDS");
+        env::remove_var("SCRIPTY_TEST_FROM_ENV_BLANK");
+    }
+}
+
+/// Tests that umask applies to files created by the command only
+#[cfg(unix)]
+#[test]
+fn test_umask() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let dir = temp.path();
+
+    cmd!("touch", "private")
+        .current_dir(dir)
+        .umask(0o077)
+        .no_echo()
+        .run()
+        .unwrap();
+    let mode = std::fs::metadata(dir.join("private"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let output = cmd!("sh", "-c", "umask")
+        .umask(0o027)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "0027");
+}
+
+/// Tests switching the user and group of a command
+#[cfg(unix)]
+#[test]
+fn test_uid_gid() {
+    // Switching to the current IDs is always allowed
+    // SAFETY: getuid and getgid cannot fail.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let output = cmd!("id", "-u")
+        .uid(uid)
+        .gid(gid)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), uid.to_string());
+
+    let cmd = cmd!("id").uid(1000).gid(100);
+    assert_eq!(cmd.to_command_string(), "uid: 1000 gid: 100 id");
+
+    // Dropping privileges needs root
+    if uid == 0 {
+        let output = cmd!("sh", "-c", "id -u; id -g")
+            .uid(65534)
+            .gid(65534)
+            .no_echo()
+            .output()
+            .unwrap();
+        assert_eq!(output, "65534\n65534\n");
+    }
+}
+
+/// Tests running setup hooks in the child before exec
+#[cfg(unix)]
+#[test]
+fn test_pre_exec() {
+    use crate::CmdError;
+
+    let limit_open_files = || {
+        let limit = libc::rlimit {
+            rlim_cur: 64,
+            rlim_max: 64,
+        };
+        // SAFETY: setrlimit is async-signal-safe.
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    // SAFETY: the hook only calls async-signal-safe functions.
+    let cmd = unsafe { cmd!("sh", "-c", "ulimit -n").pre_exec(limit_open_files) };
+    // Clones share the hook
+    assert_eq!(cmd.clone().no_echo().output().unwrap().trim(), "64");
+    assert_eq!(cmd.no_echo().output().unwrap().trim(), "64");
+
+    // A failing hook prevents the program from running
+    // SAFETY: the hook does nothing but return an error.
+    let error =
+        unsafe { cmd!("true").pre_exec(|| Err(std::io::Error::from_raw_os_error(libc::EPERM))) }
+            .no_echo()
+            .run()
+            .unwrap_err();
+    assert!(matches!(
+        error,
+        CmdError::Spawn { source, .. } if source.raw_os_error() == Some(libc::EPERM)
+    ));
+}
+
+/// Tests that a command in its own process group is stopped together with its children
+#[cfg(unix)]
+#[test]
+fn test_process_group() {
+    use std::time::Duration;
+
+    let job = cmd!("sleep", "10")
+        .process_group(0)
+        .no_echo()
+        .spawn()
+        .unwrap();
+    let pid = job.pids()[0] as libc::pid_t;
+    // SAFETY: the child has not been reaped yet.
+    assert_eq!(unsafe { libc::getpgid(pid) }, pid);
+    job.kill();
+
+    // The background subshell would create the marker if it outlived the timeout
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let marker = temp.path().join("marker");
+    let result = cmd!("sh", "-c", "(sleep 0.3; touch \"$0\") & wait", &marker)
+        .process_group(0)
+        .timeout(Duration::from_millis(100))
+        .no_echo()
+        .run();
+    assert!(matches!(result, Err(crate::CmdError::Timeout { .. })));
+    std::thread::sleep(Duration::from_millis(600));
+    assert!(!marker.exists());
+}
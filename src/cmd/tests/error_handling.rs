@@ -5,6 +5,7 @@
 
 use super::Cmd;
 use crate::cmd;
+use crate::error::CmdError;
 use std::ffi::OsString;
 
 /// Tests comprehensive command not found error handling
@@ -14,10 +15,24 @@ fn test_command_not_found_error() {
     let result = cmd!("nonexistent_command_12345").no_echo().run();
     assert!(result.is_err());
 
-    // Test that error message is informative
+    // Test that the error identifies the missing program
     let error = result.unwrap_err();
-    assert!(error.message.contains("Failed to spawn command"));
-    assert!(error.message.contains("nonexistent_command_12345"));
+    match &error {
+        CmdError::Spawn {
+            program,
+            source,
+            resolved,
+        } => {
+            assert_eq!(program, "nonexistent_command_12345");
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            assert!(resolved.is_none());
+        }
+        other => panic!("expected a spawn error, got {:?}", other),
+    }
+    assert_eq!(
+        error.to_string(),
+        "Command not found: 'nonexistent_command_12345' (is it installed and on PATH?)"
+    );
 
     // Test with different non-existent command
     let result = cmd!("this_command_definitely_does_not_exist")
@@ -25,16 +40,76 @@ fn test_command_not_found_error() {
         .run();
     assert!(result.is_err());
     let error = result.unwrap_err();
-    assert!(error.message.contains("Failed to spawn command"));
+    assert!(matches!(error, CmdError::Spawn { .. }));
+    assert!(error.to_string().contains("Command not found"));
     assert!(
         error
-            .message
+            .to_string()
             .contains("this_command_definitely_does_not_exist")
     );
 
     // Test with output() method
     let result = cmd!("missing_command").no_echo().output();
     assert!(result.is_err());
+
+    // A missing program given as a path is not blamed on PATH
+    let error = cmd!("./missing_script.sh").no_echo().run().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Command not found: './missing_script.sh' (program or its interpreter not found)"
+    );
+
+    // A script whose interpreter is missing is found, and the message says so
+    #[cfg(unix)]
+    crate::fs::with_temp_dir(|dir| {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = dir.join("script.sh");
+        std::fs::write(&script, "#!/nonexistent/interpreter\n")?;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+        let error = cmd!("./script.sh")
+            .current_dir(dir)
+            .no_echo()
+            .run()
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            CmdError::Spawn { resolved: Some(path), .. } if *path == script
+        ));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Failed to run './script.sh': found at {}, but its interpreter or a library \
+                 it needs is missing",
+                script.display()
+            )
+        );
+
+        let error = cmd!("script.sh")
+            .env("PATH", dir)
+            .no_echo()
+            .run()
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            CmdError::Spawn { resolved: Some(path), .. } if *path == script
+        ));
+        std::io::Result::Ok(())
+    })
+    .unwrap();
+
+    // Other spawn failures keep the OS error
+    #[cfg(unix)]
+    {
+        let error = cmd!("/").no_echo().run().unwrap_err();
+        assert!(matches!(error, CmdError::Spawn { .. }));
+        assert!(
+            error
+                .to_string()
+                .starts_with("Failed to spawn command: /: ")
+        );
+    }
 }
 
 /// Tests command that exits with non-zero status
@@ -95,3 +170,470 @@ fn test_pipeline_error_propagation() {
     let result = cmd!("sh", "-c", "exit 1").pipe(cmd!("cat")).no_echo().run();
     assert!(result.is_err());
 }
+
+/// Tests that `status()` reports non-zero exit codes instead of failing
+#[test]
+fn test_status_returns_exit_code() {
+    for exit_code in [0, 1, 3, 255] {
+        let status = cmd!("sh", "-c", &format!("exit {}", exit_code))
+            .no_echo()
+            .status()
+            .unwrap();
+        assert_eq!(status.code(), Some(exit_code));
+    }
+
+    // Pipelines report the status of the last command
+    let status = cmd!("echo", "test")
+        .pipe(cmd!("sh", "-c", "cat > /dev/null; exit 4"))
+        .no_echo()
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(4));
+
+    // Spawn failures are still errors
+    let result = cmd!("nonexistent_command_12345").no_echo().status();
+    assert!(result.is_err());
+}
+
+/// Tests that `status_code()` returns exit codes and shell-style codes for signals
+#[test]
+fn test_status_code() {
+    for exit_code in [0, 1, 42] {
+        let code = cmd!("sh", "-c", &format!("exit {}", exit_code))
+            .no_echo()
+            .status_code()
+            .unwrap();
+        assert_eq!(code, exit_code);
+    }
+
+    let code = cmd!("echo", "test")
+        .pipe(cmd!("grep", "-q", "missing"))
+        .no_echo()
+        .status_code()
+        .unwrap();
+    assert_eq!(code, 1);
+
+    #[cfg(unix)]
+    {
+        let code = cmd!("sh", "-c", "kill -9 $$")
+            .no_echo()
+            .status_code()
+            .unwrap();
+        assert_eq!(code, 128 + 9);
+    }
+
+    let result = cmd!("nonexistent_command_12345").no_echo().status_code();
+    assert!(result.is_err());
+}
+
+/// Tests that a non-zero exit is reported with its status and captured stderr
+#[test]
+fn test_non_zero_exit_error() {
+    let error = cmd!("sh", "-c", "echo 'something broke' >&2; exit 3")
+        .no_echo()
+        .output()
+        .unwrap_err();
+
+    match &error {
+        CmdError::NonZeroExit {
+            program,
+            status,
+            stderr,
+        } => {
+            assert_eq!(program, "sh");
+            assert_eq!(status.code(), Some(3));
+            assert_eq!(stderr.as_deref(), Some(&b"something broke\n"[..]));
+        }
+        other => panic!("expected a non-zero exit error, got {:?}", other),
+    }
+    assert!(error.to_string().contains("'sh'"));
+
+    // With run() stderr is inherited, so nothing is captured
+    let error = cmd!("sh", "-c", "exit 2").no_echo().run().unwrap_err();
+    match error {
+        CmdError::NonZeroExit { status, stderr, .. } => {
+            assert_eq!(status.code(), Some(2));
+            assert!(stderr.is_none());
+        }
+        other => panic!("expected a non-zero exit error, got {:?}", other),
+    }
+
+    // The failing stage of a pipeline is reported
+    let error = cmd!("false").pipe(cmd!("cat")).no_echo().run().unwrap_err();
+    match error {
+        CmdError::PipelineStageFailed { index, program, .. } => {
+            assert_eq!(index, 0);
+            assert_eq!(program, "false");
+        }
+        other => panic!("expected a pipeline stage error, got {:?}", other),
+    }
+}
+
+/// Tests that the error converts into the boxed `Result` alias
+#[test]
+fn test_error_boxing() {
+    fn run() -> crate::Result<()> {
+        cmd!("sh", "-c", "exit 1").no_echo().run()?;
+        Ok(())
+    }
+
+    let error = run().unwrap_err();
+    let error = error.downcast_ref::<CmdError>().unwrap();
+    assert!(matches!(error, CmdError::NonZeroExit { .. }));
+}
+
+/// Tests that a command exceeding its timeout is terminated and reported
+#[test]
+fn test_timeout_kills_command() {
+    use std::time::{Duration, Instant};
+
+    let timeout = Duration::from_millis(200);
+
+    let start = Instant::now();
+    let error = cmd!("sleep", "5")
+        .timeout(timeout)
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(3));
+    match &error {
+        CmdError::Timeout {
+            program,
+            timeout: limit,
+        } => {
+            assert_eq!(program, "sleep");
+            assert_eq!(*limit, timeout);
+        }
+        other => panic!("expected a timeout error, got {:?}", other),
+    }
+    assert!(error.to_string().contains("timed out"));
+
+    // Capturing methods honor the timeout as well
+    let start = Instant::now();
+    let result = cmd!("sleep", "5").timeout(timeout).no_echo().output();
+    assert!(matches!(result, Err(CmdError::Timeout { .. })));
+    let result = cmd!("sh", "-c", "echo partial; sleep 5")
+        .timeout(timeout)
+        .no_echo()
+        .output_bytes();
+    assert!(matches!(result, Err(CmdError::Timeout { .. })));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // The whole pipeline is terminated; the still-running stage is reported
+    let error = cmd!("sleep", "5")
+        .pipe(cmd!("cat"))
+        .timeout(timeout)
+        .no_echo()
+        .output()
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Timeout { ref program, .. } if program == "sleep"));
+
+    // A command that finishes in time is unaffected
+    let output = cmd!("echo", "fast")
+        .timeout(Duration::from_secs(5))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "fast");
+}
+
+/// Tests that `run_until()` terminates a slow command at the deadline
+#[test]
+fn test_run_until_deadline() {
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(300);
+    let error = cmd!("sleep", "5")
+        .no_echo()
+        .run_until(deadline)
+        .unwrap_err();
+    let elapsed = start.elapsed();
+    assert!(matches!(error, CmdError::Timeout { ref program, .. } if program == "sleep"));
+    assert!(elapsed >= Duration::from_millis(300));
+    assert!(elapsed < Duration::from_secs(3));
+
+    // A deadline that leaves enough time does not interfere
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert!(cmd!("true").no_echo().run_until(deadline).is_ok());
+}
+
+/// Tests that each kind of failure maps to its own error variant
+#[test]
+fn test_error_variants_are_distinct() {
+    let classify = |result: Result<String, CmdError>| match result {
+        Err(CmdError::Spawn { .. }) => "spawn",
+        Err(CmdError::NonZeroExit { status, .. }) => {
+            assert!(status.code().is_some());
+            "exited"
+        }
+        Err(CmdError::Signaled { signal, stderr, .. }) => {
+            assert_eq!(signal, 9);
+            assert_eq!(stderr.as_deref(), Some(&b"dying\n"[..]));
+            "signaled"
+        }
+        Err(other) => panic!("unexpected error: {:?}", other),
+        Ok(_) => "ok",
+    };
+
+    assert_eq!(
+        classify(cmd!("nonexistent_command_12345").no_echo().output()),
+        "spawn"
+    );
+    assert_eq!(
+        classify(cmd!("sh", "-c", "exit 3").no_echo().output()),
+        "exited"
+    );
+    assert_eq!(
+        classify(
+            cmd!("sh", "-c", "echo dying >&2; kill -9 $$")
+                .no_echo()
+                .output()
+        ),
+        "signaled"
+    );
+
+    let error = cmd!("sh", "-c", "kill -9 $$").no_echo().run().unwrap_err();
+    assert_eq!(error.to_string(), "Command 'sh' was terminated by signal 9");
+}
+
+/// Tests polling a command until it succeeds or the deadline passes
+#[test]
+fn test_poll_until_success() {
+    use std::time::{Duration, Instant};
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let counter = temp.path().join("counter");
+
+    // Succeeds on the third attempt
+    let script = r#"echo x >> "$1"; [ "$(wc -l < "$1")" -ge 3 ]"#;
+    cmd!("sh", "-c", script, "sh")
+        .arg(&counter)
+        .no_echo()
+        .poll_until_success(
+            Duration::from_millis(10),
+            Instant::now() + Duration::from_secs(10),
+        )
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&counter).unwrap().lines().count(),
+        3
+    );
+
+    let start = Instant::now();
+    let error = cmd!("false")
+        .no_echo()
+        .poll_until_success(
+            Duration::from_millis(10),
+            Instant::now() + Duration::from_millis(200),
+        )
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Timeout { .. }));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // Errors other than a failed attempt are not retried
+    let error = cmd!("nonexistent_command_12345")
+        .no_echo()
+        .poll_until_success(
+            Duration::from_millis(10),
+            Instant::now() + Duration::from_secs(10),
+        )
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Spawn { .. }));
+}
+
+/// Tests telling empty output apart from missing and failing commands
+#[test]
+fn test_output_empty_vs_missing() {
+    // Empty output from a successful command
+    assert_eq!(cmd!("true").no_echo().output().unwrap(), "");
+    assert_eq!(
+        cmd!("true").no_echo().output_opt().unwrap(),
+        Some(String::new())
+    );
+
+    // A missing command is an error, or None with output_opt
+    let error = cmd!("nonexistent_command_12345")
+        .no_echo()
+        .output()
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Spawn { .. }));
+    assert_eq!(
+        cmd!("nonexistent_command_12345")
+            .no_echo()
+            .output_opt()
+            .unwrap(),
+        None
+    );
+
+    // A failing command is an error either way
+    assert!(matches!(
+        cmd!("false").no_echo().output().unwrap_err(),
+        CmdError::NonZeroExit { .. }
+    ));
+    assert!(matches!(
+        cmd!("false").no_echo().output_opt().unwrap_err(),
+        CmdError::NonZeroExit { .. }
+    ));
+
+    assert_eq!(
+        cmd!("echo", "hi")
+            .pipe(cmd!("nonexistent_command_12345"))
+            .no_echo()
+            .output_opt()
+            .unwrap(),
+        None
+    );
+}
+
+/// Tests falling back to an alternative command with `||` and `&&` lists
+#[test]
+fn test_or_cmd_fallback() {
+    // A non-zero exit falls back, and the fallback's output is returned
+    let output = cmd!("sh", "-c", "echo first; exit 1")
+        .no_echo()
+        .or_cmd(cmd!("echo", "fallback").no_echo())
+        .output()
+        .unwrap();
+    assert_eq!(output, "fallback\n");
+
+    // So does a spawn error
+    let output = cmd!("nonexistent_command_12345")
+        .no_echo()
+        .or_cmd(cmd!("echo", "fallback").no_echo())
+        .output()
+        .unwrap();
+    assert_eq!(output, "fallback\n");
+
+    // The alternative is not run when the first command succeeds
+    let output = cmd!("echo", "first")
+        .no_echo()
+        .or_cmd(cmd!("nonexistent_command_12345").no_echo())
+        .output()
+        .unwrap();
+    assert_eq!(output, "first\n");
+
+    // The last error is reported when everything fails
+    let error = cmd!("false")
+        .no_echo()
+        .or_cmd(cmd!("nonexistent_command_12345").no_echo())
+        .run()
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Spawn { .. }));
+
+    // Evaluated left to right like `false && a || b`
+    let output = cmd!("false")
+        .no_echo()
+        .chain(cmd!("echo", "skipped").no_echo())
+        .or_cmd(cmd!("echo", "recovered").no_echo())
+        .output()
+        .unwrap();
+    assert_eq!(output, "recovered\n");
+    let output = cmd!("true")
+        .no_echo()
+        .chain(cmd!("echo", "second").no_echo())
+        .or_cmd(cmd!("echo", "skipped").no_echo())
+        .output()
+        .unwrap();
+    assert_eq!(output, "second\n");
+}
+
+/// Tests that a missing working directory is reported before spawning
+#[test]
+fn test_missing_current_dir() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let missing = temp.path().join("missing");
+    let error = cmd!("true")
+        .current_dir(&missing)
+        .no_echo()
+        .run()
+        .unwrap_err();
+    match &error {
+        CmdError::CurrentDir {
+            program,
+            path,
+            source,
+        } => {
+            assert_eq!(program, "true");
+            assert_eq!(path, &missing);
+            assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+        }
+        other => panic!("expected a working directory error, got {:?}", other),
+    }
+    assert_eq!(
+        error.to_string(),
+        format!("Working directory does not exist: {}", missing.display())
+    );
+
+    // Nothing runs when a later stage has a bad directory
+    let marker = temp.path().join("marker");
+    let result = cmd!("touch")
+        .arg(&marker)
+        .pipe(cmd!("cat").current_dir(&missing))
+        .no_echo()
+        .run();
+    assert!(matches!(result, Err(CmdError::CurrentDir { .. })));
+    assert!(!marker.exists());
+
+    // A file is not a directory
+    let file = std::env::current_exe().unwrap();
+    let error = cmd!("true").current_dir(&file).no_echo().run().unwrap_err();
+    assert!(matches!(&error, CmdError::CurrentDir { source, .. }
+        if source.kind() == std::io::ErrorKind::NotADirectory));
+}
+
+/// Tests retrying a failing command with retry() and retry_if()
+#[test]
+fn test_retry() {
+    use std::time::Duration;
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let counter = temp.path().join("counter");
+    // Fails until it has been run three times
+    let flaky = || {
+        cmd!(
+            "sh",
+            "-c",
+            "n=$(cat \"$0\" 2>/dev/null || echo 0); n=$((n + 1)); echo $n > \"$0\"; echo run $n; [ $n -ge 3 ]",
+            &counter
+        )
+    };
+
+    let (output, echo) = crate::capture_echo(|| {
+        flaky()
+            .retry(3, Duration::from_millis(10))
+            .output()
+            .unwrap()
+    });
+    assert_eq!(output, "run 3\n");
+    if crate::output::should_echo() {
+        assert_eq!(echo.matches(":cmd ").count(), 1);
+        assert!(echo.contains("retry attempt 2/3"), "{}", echo);
+        assert!(echo.contains("retry attempt 3/3"), "{}", echo);
+    }
+
+    // The error of the last attempt is returned
+    std::fs::remove_file(&counter).unwrap();
+    let result = flaky().no_echo().retry(2, Duration::from_millis(10)).run();
+    assert!(matches!(result, Err(CmdError::NonZeroExit { .. })));
+
+    // Errors the predicate rejects are not retried
+    std::fs::remove_file(&counter).unwrap();
+    let result = flaky()
+        .no_echo()
+        .retry_if(
+            5,
+            Duration::from_millis(10),
+            |err| matches!(err, CmdError::NonZeroExit { status, .. } if status.code() == Some(7)),
+        )
+        .run();
+    assert!(result.is_err());
+    assert_eq!(std::fs::read_to_string(&counter).unwrap(), "1\n");
+
+    // A program that cannot be found is not retried by default
+    let result = cmd!("scripty_no_such_program")
+        .no_echo()
+        .retry(3, Duration::from_secs(60))
+        .run();
+    assert!(matches!(result, Err(CmdError::Spawn { .. })));
+}
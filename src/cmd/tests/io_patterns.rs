@@ -576,3 +576,520 @@ fn test_pattern_performance() {
         assert_eq!(count, 10000);
     }
 }
+
+/// Tests streaming stdout as fixed-size binary frames
+#[test]
+fn test_frames() {
+    // Output length is a multiple of the frame size
+    let frames: Vec<Vec<u8>> = cmd!("printf", "abcdef")
+        .no_echo()
+        .frames(3)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(frames, vec![b"abc".to_vec(), b"def".to_vec()]);
+
+    // A trailing partial frame is yielded as is by default
+    let frames: Vec<Vec<u8>> = cmd!("printf", "abcdefg")
+        .no_echo()
+        .frames(3)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        frames,
+        vec![b"abc".to_vec(), b"def".to_vec(), b"g".to_vec()]
+    );
+
+    // ...and reported as an error in strict mode
+    let mut frames = cmd!("printf", "abcdefg")
+        .no_echo()
+        .frames(3)
+        .unwrap()
+        .strict();
+    assert_eq!(frames.next().unwrap().unwrap(), b"abc");
+    assert_eq!(frames.next().unwrap().unwrap(), b"def");
+    match frames.next() {
+        Some(Err(crate::CmdError::Io(e))) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof)
+        }
+        other => panic!("expected a partial frame error, got {:?}", other),
+    }
+    assert!(frames.next().is_none());
+
+    // A failing command is reported after its output
+    let results: Vec<_> = cmd!("sh", "-c", "printf abc; exit 2")
+        .no_echo()
+        .frames(3)
+        .unwrap()
+        .collect();
+    assert_eq!(results.len(), 2);
+    assert!(matches!(
+        results[1],
+        Err(crate::CmdError::NonZeroExit { .. })
+    ));
+
+    // Stopping early does not hang on a command with endless output
+    let first = cmd!("yes")
+        .no_echo()
+        .frames(4)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(first, b"y\ny\n");
+}
+
+/// Tests that no_input connects stdin to /dev/null
+#[test]
+fn test_no_input() {
+    let output = cmd!("sh", "-c", "read line || echo eof")
+        .no_input()
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "eof\n");
+
+    // Overrides input given earlier
+    let output = cmd!("cat")
+        .input("ignored")
+        .no_input()
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "");
+
+    let output = cmd!("cat")
+        .no_input()
+        .pipe(cmd!("wc", "-c"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "0");
+
+    assert!(cmd!("cat").no_input().no_echo().run().is_ok());
+}
+
+/// Tests that stdout_inherit and stderr_inherit give way to capturing
+#[test]
+fn test_stdout_stderr_inherit() {
+    // Capturing takes precedence, and inheriting undoes discarding
+    let output = cmd!("sh", "-c", "echo out; echo err >&2")
+        .discard_stdout()
+        .stdout_inherit()
+        .stderr_inherit()
+        .no_echo()
+        .capture()
+        .unwrap();
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+
+    // The stderr of a stage in the middle stays on the terminal
+    let output = cmd!("sh", "-c", "echo a; echo warn >&2")
+        .stderr_inherit()
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .capture_stages()
+        .unwrap();
+    assert_eq!(output.stdout, b"a\n");
+    assert!(output.stages[0].stderr.is_empty());
+}
+
+/// Tests recording the data passing through stdin, stdout and stderr
+#[test]
+fn test_record_io() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("record");
+
+    let output = cmd!("sh", "-c", "cat; echo oops >&2")
+        .record_io(&path)
+        .input("2+2\n")
+        .no_echo()
+        .capture()
+        .unwrap();
+    assert_eq!(output.stdout_str(), "2+2\n");
+    assert_eq!(output.stderr_str(), "oops\n");
+
+    let record = std::fs::read_to_string(&path).unwrap();
+    let chunks: Vec<(&str, &str)> = record
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.splitn(4, ' ').collect();
+            assert!(fields[0].parse::<f64>().is_ok(), "bad timestamp: {}", line);
+            (fields[1], fields[3])
+        })
+        .collect();
+    assert!(chunks.contains(&("stdin", "2+2\\n")));
+    assert!(chunks.contains(&("stdout", "2+2\\n")));
+    assert!(chunks.contains(&("stderr", "oops\\n")));
+
+    // Interactive use through spawn_io_in_out is recorded in both directions
+    let (handle, stdin, stdout) = cmd!("cat")
+        .record_io(&path)
+        .no_echo()
+        .spawn_io_in_out()
+        .unwrap();
+    let mut stdin = stdin.unwrap();
+    stdin.write_all(b"ping\n").unwrap();
+    drop(stdin);
+    let mut reply = String::new();
+    stdout.unwrap().read_to_string(&mut reply).unwrap();
+    handle.wait().unwrap();
+    assert_eq!(reply, "ping\n");
+
+    let record = std::fs::read_to_string(&path).unwrap();
+    assert!(record.contains(" stdin 5 ping\\n\n"));
+    assert!(record.contains(" stdout 5 ping\\n\n"));
+}
+
+/// Tests sampling the output of a never-ending command for a time window
+#[test]
+fn test_capture_for() {
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    let output = cmd!("sh", "-c", "while :; do echo sample; sleep 0.01; done")
+        .no_echo()
+        .capture_for(Duration::from_millis(200))
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!output.is_empty());
+    assert!(output.lines().all(|line| line == "sample"), "{output}");
+
+    let output = cmd!("sh", "-c", "echo start; sleep 10")
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .capture_for(Duration::from_millis(300))
+        .unwrap();
+    assert_eq!(output, "start\n");
+
+    // A command that finishes early is returned right away
+    let start = Instant::now();
+    let output = cmd!("echo", "done")
+        .no_echo()
+        .capture_for(Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(output, "done\n");
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    assert!(matches!(
+        cmd!("false").no_echo().capture_for(Duration::from_secs(10)),
+        Err(crate::CmdError::NonZeroExit { .. })
+    ));
+}
+
+/// Tests starting a background job, then waiting for it or killing it
+#[test]
+fn test_spawn_background() {
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    let server = cmd!("sleep", "10").no_echo().spawn().unwrap();
+    let pids = server.pids();
+    assert_eq!(pids.len(), 1);
+    server.kill();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    // The process has been reaped, so its pid is gone
+    assert!(
+        cmd!("kill", "-0", pids[0].to_string())
+            .no_echo()
+            .discard_stderr()
+            .run()
+            .is_err()
+    );
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("output");
+    let job = cmd!("sh", "-c", "cat > \"$0\"", &path)
+        .input("from the parent\n")
+        .no_echo()
+        .spawn()
+        .unwrap();
+    job.wait().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "from the parent\n");
+
+    let job = cmd!("sh", "-c", "exit 2")
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .spawn()
+        .unwrap();
+    assert_eq!(job.pids().len(), 2);
+    assert!(job.wait().is_err());
+}
+
+/// Tests sending signals to a background job without giving up its handle
+#[cfg(unix)]
+#[test]
+fn test_spawn_signal() {
+    let job = cmd!("sleep", "10").no_echo().spawn().unwrap();
+    assert!(job.signal(-1).is_err());
+    job.signal(crate::signal::SIGTERM).unwrap();
+    assert!(matches!(
+        job.wait(),
+        Err(crate::CmdError::Signaled {
+            signal: crate::signal::SIGTERM,
+            ..
+        })
+    ));
+
+    // A job that already exited can still be signaled until it is waited for
+    let job = cmd!("true").no_echo().spawn().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    job.signal(crate::signal::SIGUSR1).unwrap();
+    job.wait().unwrap();
+}
+
+/// Tests feeding readers that borrow local data to the `run_with_*_io_scoped()` methods
+#[test]
+fn test_run_with_io_scoped_borrowed_reader() {
+    let data = String::from("zebra\napple\n");
+    let mut output = Vec::new();
+    cmd!("sort")
+        .no_echo()
+        .run_with_io_scoped(data.as_bytes(), &mut output)
+        .unwrap();
+    assert_eq!(output, b"apple\nzebra\n");
+
+    // A reader borrowed mutably can be used again afterwards
+    let mut reader = Cursor::new(data.as_bytes());
+    let mut errors = Vec::new();
+    cmd!("sh", "-c", "head -1 >&2")
+        .no_echo()
+        .run_with_err_io_scoped(&mut reader, &mut errors)
+        .unwrap();
+    assert_eq!(errors, b"zebra\n");
+    assert!(reader.position() > 0);
+
+    // The combined writer may borrow too
+    let mut combined = Vec::new();
+    cmd!("sh", "-c", "cat; echo done >&2")
+        .pipe_out_err(cmd!("sort"))
+        .no_echo()
+        .run_with_both_io_scoped(&data.as_bytes()[..6], &mut combined)
+        .unwrap();
+    assert_eq!(combined, b"done\nzebra\n");
+
+    // A failing writer is reported once the pipeline has been reaped
+    struct Broken;
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let endless = std::io::repeat(b'y');
+    let error = cmd!("cat")
+        .no_echo()
+        .run_with_io_scoped(endless, Broken)
+        .unwrap_err();
+    assert!(matches!(error, crate::CmdError::Io(_)));
+}
+
+/// Tests that a command filling the stderr pipe before writing stdout cannot deadlock
+/// the combined writer
+#[test]
+fn test_run_with_both_io_large_stderr() {
+    let script = "head -c 200000 /dev/zero >&2; echo out";
+    let mut combined = Vec::new();
+    cmd!("sh", "-c", script)
+        .no_echo()
+        .run_with_both_io_scoped(&b""[..], &mut combined)
+        .unwrap();
+    assert_eq!(combined.len(), 200_004);
+    assert!(combined.windows(4).any(|chunk| chunk == b"out\n"));
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("combined");
+    cmd!("sh", "-c", script)
+        .no_echo()
+        .write_both_to(std::fs::File::create(&path).unwrap())
+        .unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 200_004);
+}
+
+/// Tests that run_with_io() returns once the command exits while its reader still blocks
+#[test]
+fn test_run_with_io_blocking_reader() {
+    let (reader, writer) = std::io::pipe().unwrap();
+    let mut output = Vec::new();
+    cmd!("echo", "done")
+        .no_echo()
+        .run_with_io(reader, &mut output)
+        .unwrap();
+    assert_eq!(output, b"done\n");
+    drop(writer);
+}
+
+/// Tests feeding lines and collecting output lines with interact()
+#[test]
+fn test_interact() {
+    let mut results = Vec::new();
+    cmd!("sh", "-c", "while read -r x; do echo \"got $x\"; done")
+        .no_echo()
+        .interact(["a", "b c"], |line| results.push(line))
+        .unwrap();
+    assert_eq!(results, ["got a", "got b c"]);
+
+    // Input borrowed from the caller, in a pipeline
+    let words = [String::from("b"), String::from("a")];
+    let mut results = Vec::new();
+    cmd!("sort")
+        .pipe(cmd!("tr", "a-z", "A-Z"))
+        .no_echo()
+        .interact(&words, |line| results.push(line))
+        .unwrap();
+    assert_eq!(results, ["A", "B"]);
+
+    // A command that exits early stops the input and its failure is reported
+    let mut count = 0;
+    let result = cmd!("sh", "-c", "read -r x; echo $x; exit 3")
+        .no_echo()
+        .interact((0..).map(|i| i.to_string()), |_| count += 1);
+    assert!(result.is_err());
+    assert_eq!(count, 1);
+}
+
+/// Tests that interact() leaves the stderr of a pipeline's last command on the terminal
+#[test]
+fn test_interact_pipeline_stderr() {
+    let mut results = Vec::new();
+    cmd!("sh", "-c", "cat")
+        .pipe(cmd!("sh", "-c", "cat; echo x >&2"))
+        .no_echo()
+        .interact(["a"], |line| results.push(line))
+        .unwrap();
+    assert_eq!(results, ["a"]);
+}
+
+/// Tests that kill_on_drop(true) stops a spawned command whose handle is dropped
+#[test]
+fn test_kill_on_drop() {
+    use std::time::Duration;
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let killed = temp.path().join("killed");
+    let kept = temp.path().join("kept");
+    let script = "sleep 1; touch \"$0\"";
+
+    let (handle, stdin) = cmd!("sh", "-c", script, &killed)
+        .kill_on_drop(true)
+        .no_echo()
+        .spawn_io_in()
+        .unwrap();
+    drop(stdin);
+    drop(handle);
+
+    // Off by default: the command keeps running
+    let (handle, _stdout) = cmd!("true")
+        .pipe(cmd!("sh", "-c", script, &kept))
+        .no_echo()
+        .spawn_io_out()
+        .unwrap();
+    drop(handle);
+
+    std::thread::sleep(Duration::from_secs(2));
+    assert!(!killed.exists());
+    assert!(kept.exists());
+
+    // A handle that was waited for is not affected
+    let handle = cmd!("echo", "done")
+        .kill_on_drop(true)
+        .no_echo()
+        .spawn()
+        .unwrap();
+    handle.wait().unwrap();
+}
+
+/// Tests merging stderr into stdout with combine_stderr_into_stdout(), like `2>&1`
+#[test]
+fn test_combine_stderr_into_stdout() {
+    let script = "echo out1; echo err1 >&2; echo out2; echo err2 >&2";
+
+    // Both streams are captured in the order they were written
+    let output = cmd!("sh", "-c", script)
+        .combine_stderr_into_stdout()
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "out1\nerr1\nout2\nerr2\n");
+
+    // Stderr follows stdout into the next command of a pipeline
+    let output = cmd!("sh", "-c", script)
+        .combine_stderr_into_stdout()
+        .discard_stderr()
+        .pipe(cmd!("grep", "err"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "err1\nerr2\n");
+
+    // ... and into an output file
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("output");
+    let (result, echo) = crate::capture_echo(|| {
+        cmd!("sh", "-c", script)
+            .combine_stderr_into_stdout()
+            .output_to_file(&path)
+    });
+    result.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "out1\nerr1\nout2\nerr2\n"
+    );
+    if crate::output::should_echo() {
+        assert!(echo.ends_with(&format!("> {} 2>&1\n", path.display())));
+    }
+
+    let command = cmd!("make")
+        .combine_stderr_into_stdout()
+        .pipe(cmd!("tee", "log"))
+        .to_command_string();
+    assert_eq!(command, "make 2>&1 | tee log");
+}
+
+/// Tests reacting to stderr lines as they arrive with on_stderr_line()
+#[test]
+fn test_on_stderr_line() {
+    use std::sync::{Arc, Mutex};
+
+    let script =
+        "echo out; echo 'progress 1' >&2; printf 'progress 2\\r\\n' >&2; printf 'done' >&2";
+
+    // output() still captures stdout
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&lines);
+    let output = cmd!("sh", "-c", script)
+        .on_stderr_line(move |line| sink.lock().unwrap().push(line.to_owned()))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "out\n");
+    assert_eq!(*lines.lock().unwrap(), ["progress 1", "progress 2", "done"]);
+
+    // run() reports every line before returning, even when the command fails
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&lines);
+    let error = cmd!("sh", "-c", "echo a >&2; echo b >&2; exit 3")
+        .on_stderr_line(move |line| sink.lock().unwrap().push(line.to_owned()))
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(matches!(error, crate::CmdError::NonZeroExit { .. }));
+    assert_eq!(*lines.lock().unwrap(), ["a", "b"]);
+
+    // The stderr handed to the callback is still attached to the error
+    let error = cmd!("sh", "-c", "echo oops >&2; exit 1")
+        .on_stderr_line(|_| {})
+        .no_echo()
+        .output()
+        .unwrap_err();
+    match error {
+        crate::CmdError::NonZeroExit { stderr, .. } => {
+            assert_eq!(stderr.as_deref(), Some(&b"oops\n"[..]))
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
@@ -17,3 +17,7 @@ mod pipeline;
 mod quoting;
 mod run_output_verification;
 mod write_methods;
+
+// The modules above drive Unix tools such as `sh` and `sort`; this one runs on Windows
+#[cfg(windows)]
+mod windows;
@@ -157,3 +157,96 @@ fn test_no_echo_mode_builder_propagation() {
     let pipeline = cmd.pipe(cmd!("cat"));
     assert!(pipeline.suppress_echo);
 }
+
+/// Tests that `cmd_quiet!` builds the same command as `cmd!` without echoing it
+#[test]
+fn test_cmd_quiet_macro() {
+    use crate::cmd_quiet;
+
+    let quiet = cmd_quiet!("echo", "a", "b");
+    let loud = cmd!("echo", "a", "b");
+    assert!(quiet.suppress_echo);
+    assert!(!loud.suppress_echo);
+    assert_eq!(quiet.program, loud.program);
+    assert_eq!(quiet.args, loud.args);
+    assert!(cmd_quiet!("true").suppress_echo);
+
+    let (output, echo) = crate::capture_echo(|| cmd_quiet!("echo", "hi").output().unwrap());
+    assert_eq!(output, "hi\n");
+    assert!(echo.is_empty());
+}
+
+/// Tests that verbose_echo() shows the working directory in front of the command
+#[test]
+fn test_verbose_echo() {
+    let cwd = std::env::current_dir().unwrap();
+
+    let (_, echo) = crate::capture_echo(|| {
+        cmd!("echo", "hi")
+            .verbose_echo()
+            .current_dir("/")
+            .output()
+            .unwrap()
+    });
+    if crate::output::should_echo() {
+        assert_eq!(
+            echo,
+            format!(" scripty:cmd cwd: {} cd: / echo hi\n", cwd.display())
+        );
+    }
+
+    // Carried over when piped into another command
+    let (_, echo) = crate::capture_echo(|| {
+        cmd!("echo", "hi")
+            .pipe(cmd!("cat").verbose_echo())
+            .output()
+            .unwrap()
+    });
+    if crate::output::should_echo() {
+        assert!(echo.contains(&format!("cwd: {} echo hi | cat", cwd.display())));
+    }
+
+    // Not part of the command string
+    assert_eq!(cmd!("true").verbose_echo().to_command_string(), "true");
+}
+
+/// Tests that echo_to() sends one command's echo to its own writer
+#[test]
+fn test_echo_to() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let own = SharedBuffer::default();
+    let (_, echo) = crate::capture_echo(|| {
+        cmd!("echo", "noisy").echo_to(own.clone()).output().unwrap();
+        cmd!("echo", "a")
+            .pipe(cmd!("cat").echo_to(own.clone()))
+            .output()
+            .unwrap();
+        cmd!("echo", "quiet")
+            .echo_to(own.clone())
+            .no_echo()
+            .output()
+            .unwrap();
+        cmd!("echo", "main").output().unwrap();
+    });
+    let own = crate::style::strip_ansi(&String::from_utf8_lossy(&own.0.lock().unwrap()));
+    if crate::output::should_echo() {
+        assert_eq!(own, " scripty:cmd echo noisy\n scripty:cmd echo a | cat\n");
+        assert_eq!(echo, " scripty:cmd echo main\n");
+    } else {
+        assert!(own.is_empty());
+    }
+}
@@ -141,6 +141,22 @@ fn test_complex_mixed_pipeline() {
     assert!(!output.contains("error output")); // Original should be transformed
 }
 
+/// Tests a five-stage pipeline mixing stdout and stderr pipes, fed from the head
+#[test]
+fn test_five_stage_mixed_pipeline() {
+    let pipeline = cmd!("sort")
+        .input("banana\napple\n")
+        .pipe(cmd!("sh", "-c", "cat >&2"))
+        .pipe_err(cmd!("tr", "[:lower:]", "[:upper:]"))
+        .pipe(cmd!("sh", "-c", "cat; echo done >&2"))
+        .pipe_out_err(cmd!("sort"))
+        .no_echo();
+    assert_eq!(pipeline.connections.len(), 5);
+
+    let output = pipeline.output().unwrap();
+    assert_eq!(output, "APPLE\nBANANA\ndone\n");
+}
+
 /// Tests that different pipe modes can be used in the same pipeline
 #[test]
 fn test_mixed_pipe_modes() {
@@ -293,7 +309,18 @@ fn test_empty_pipeline() {
     let pipeline = Pipeline {
         connections: vec![],
         input: None,
+        output_file: None,
         suppress_echo: true,
+        verbose_echo: false,
+        echo_writer: None,
+        kill_on_drop: false,
+        timeout: None,
+        pipefail: true,
+        progress: None,
+        stderr_line: None,
+        record: None,
+        cancel: None,
+        buffer_size: crate::cmd::pipeline::STAGE_BUFFER_SIZE,
     };
     let result = pipeline.output().unwrap();
     assert!(result.is_empty());
@@ -392,3 +419,395 @@ fn test_pipe_out_err_mixed_output() {
     assert_eq!(lines[0], "ERR:message2");
     assert_eq!(lines[1], "OUT:message1");
 }
+
+/// Tests that internal pipe ends are not leaked into grandchildren
+///
+/// The middle stage starts a background `sleep` whose standard streams are detached.
+/// If any pipe end held by scripty were inherited by it (i.e. not close-on-exec),
+/// the downstream `cat` would not see EOF until the `sleep` exits.
+#[test]
+fn test_pipe_fds_not_leaked_into_grandchildren() {
+    use std::time::{Duration, Instant};
+
+    let start = Instant::now();
+    let output = cmd!("echo", "hello")
+        .pipe(cmd!("sh", "-c", "sleep 5 </dev/null >/dev/null 2>&1 & cat"))
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .output()
+        .unwrap();
+
+    assert_eq!(output.trim(), "hello");
+    assert!(
+        start.elapsed() < Duration::from_secs(3),
+        "pipeline should finish without waiting for the background grandchild"
+    );
+}
+
+/// Tests that `statuses()` reports the exit status of every stage
+#[test]
+fn test_pipeline_statuses() {
+    let statuses = cmd!("sh", "-c", "echo data; exit 2")
+        .pipe(cmd!("grep", "nomatch"))
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .statuses()
+        .unwrap();
+    let codes: Vec<_> = statuses.iter().map(|status| status.code()).collect();
+    assert_eq!(codes, [Some(2), Some(1), Some(0)]);
+
+    // run() fails on the first failing stage, even though the last one succeeded
+    let error = cmd!("sh", "-c", "exit 2")
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        crate::CmdError::PipelineStageFailed { index: 0, ref program, .. } if program == "sh"
+    ));
+
+    // The message names the failing stage, counting from 1
+    let error = cmd!("echo", "data")
+        .pipe(cmd!("sh", "-c", "cat >/dev/null; exit 2"))
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        crate::CmdError::PipelineStageFailed { index: 1, status, .. } if status.code() == Some(2)
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Pipeline stage 2 'sh' exited with code 2"
+    );
+
+    // A single command keeps its own error
+    assert!(matches!(
+        cmd!("sh", "-c", "exit 2").no_echo().run().unwrap_err(),
+        crate::CmdError::NonZeroExit { .. }
+    ));
+}
+
+/// Tests that disabling pipefail only checks the last command
+#[test]
+fn test_pipefail_toggle() {
+    // Enabled by default: an upstream failure fails the pipeline
+    let result = cmd!("sh", "-c", "echo data; exit 1")
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .output();
+    assert!(result.is_err());
+
+    let output = cmd!("sh", "-c", "echo data; exit 1")
+        .pipe(cmd!("cat"))
+        .pipefail(false)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "data\n");
+    assert!(
+        cmd!("false")
+            .pipe(cmd!("true"))
+            .pipefail(false)
+            .no_echo()
+            .run()
+            .is_ok()
+    );
+
+    // The last command still counts
+    let error = cmd!("true")
+        .pipe(cmd!("sh", "-c", "exit 4"))
+        .pipefail(false)
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        crate::CmdError::PipelineStageFailed { index: 1, status, .. } if status.code() == Some(4)
+    ));
+}
+
+/// Tests piping command output through a Rust closure
+#[test]
+fn test_pipe_fn() {
+    use std::io::{BufRead, Write};
+
+    let upper = |input: &mut dyn BufRead, output: &mut dyn Write| {
+        for line in input.lines() {
+            writeln!(output, "{}", line?.to_uppercase())?;
+        }
+        Ok(())
+    };
+
+    let output = cmd!("printf", "a\\nb\\n")
+        .pipe_fn(upper)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "A\nB\n");
+
+    // A closure in the middle of a pipeline feeds the next command
+    let output = cmd!("printf", "one\\ntwo\\nthree\\n")
+        .pipe_fn(|input, output| {
+            for line in input.lines() {
+                let line = line?;
+                if line.contains('o') {
+                    writeln!(output, "{}", line)?;
+                }
+            }
+            Ok(())
+        })
+        .pipe(cmd!("wc", "-l"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "2");
+
+    assert!(cmd!("echo", "hello").pipe_fn(upper).no_echo().run().is_ok());
+
+    // An error returned by the closure fails the pipeline
+    let error = cmd!("echo", "hello")
+        .pipe_fn(|_, _| Err(std::io::Error::other("bad input")))
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(matches!(error, crate::CmdError::Io(_)));
+}
+
+/// Tests that tee copies the output to a writer and passes it on
+#[test]
+fn test_tee() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let copy = SharedBuffer::default();
+    let output = cmd!("printf", "b\\na\\n")
+        .tee(copy.clone())
+        .pipe(cmd!("sort"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "a\nb\n");
+    assert_eq!(*copy.0.lock().unwrap(), b"b\na\n");
+
+    let copy = SharedBuffer::default();
+    let output = cmd!("echo", "hello")
+        .tee(copy.clone())
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "hello\n");
+    assert_eq!(*copy.0.lock().unwrap(), b"hello\n");
+
+    // A tap between two stages of a longer pipeline
+    let copy = SharedBuffer::default();
+    let output = cmd!("printf", "c\nb\na\n")
+        .pipe(cmd!("sort"))
+        .tee(copy.clone())
+        .pipe(cmd!("head", "-1"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "a\n");
+    assert_eq!(*copy.0.lock().unwrap(), b"a\nb\nc\n");
+}
+
+/// Tests that closure stages read and write in chunks of `buffer_size()`
+#[test]
+fn test_buffer_size() {
+    let data = "x".repeat(100_000);
+    let largest = |size: Option<usize>| {
+        let mut pipeline = cmd!("cat")
+            .input(data.clone())
+            .no_echo()
+            .pipe_fn(|input, output| {
+                let mut largest = 0;
+                loop {
+                    let chunk = input.fill_buf()?;
+                    if chunk.is_empty() {
+                        break;
+                    }
+                    largest = largest.max(chunk.len());
+                    let n = chunk.len();
+                    input.consume(n);
+                }
+                writeln!(output, "{}", largest)
+            });
+        if let Some(size) = size {
+            pipeline = pipeline.buffer_size(size);
+        }
+        pipeline.output().unwrap().trim().parse::<usize>().unwrap()
+    };
+
+    assert!(largest(None) <= 8 * 1024);
+    assert!(largest(Some(100)) <= 100);
+    assert_eq!(largest(Some(0)), 1);
+
+    let output = cmd!("echo", "hello")
+        .pipe_fn(|input, output| std::io::copy(input, output).map(drop))
+        .buffer_size(1)
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "hello\n");
+}
+
+/// Tests compressing and decompressing a stream in-process
+#[cfg(feature = "flate2")]
+#[test]
+fn test_gzip_round_trip() {
+    let data: Vec<u8> = (0..100_000u32)
+        .flat_map(|i| (i % 251).to_le_bytes())
+        .collect();
+
+    let compressed = cmd!("cat")
+        .input_bytes(&data)
+        .gzip()
+        .no_echo()
+        .output_bytes()
+        .unwrap();
+    assert_eq!(&compressed[..2], b"\x1f\x8b");
+    assert!(compressed.len() < data.len());
+
+    let output = cmd!("cat")
+        .input_bytes(&data)
+        .gzip_level(1)
+        .gunzip()
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .output_bytes()
+        .unwrap();
+    assert_eq!(output, data);
+
+    // Invalid data fails the pipeline
+    assert!(
+        cmd!("echo", "not gzip")
+            .gunzip()
+            .no_echo()
+            .output_bytes()
+            .is_err()
+    );
+}
+
+/// Tests detecting in-process stages between the commands
+#[test]
+fn test_uses_native_pipes() {
+    assert!(cmd!("echo", "a").pipe(cmd!("cat")).uses_native_pipes());
+    assert!(
+        cmd!("echo", "a")
+            .pipe_err(cmd!("cat"))
+            .pipe_out_err(cmd!("cat"))
+            .input("x")
+            .uses_native_pipes()
+    );
+
+    let pipeline = cmd!("echo", "a")
+        .pipe(cmd!("cat"))
+        .pipe_fn(|input, output| {
+            std::io::copy(input, output)?;
+            Ok(())
+        });
+    assert!(!pipeline.uses_native_pipes());
+    assert!(!cmd!("echo", "a").tee(std::io::sink()).uses_native_pipes());
+}
+
+/// Tests that `yes | head` succeeds although `yes` is cut off by a broken pipe
+#[test]
+fn test_broken_pipe_is_not_a_failure() {
+    let output = cmd!("yes")
+        .pipe(cmd!("head", "-5"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "y\n".repeat(5));
+
+    let output = cmd!("yes")
+        .pipe(cmd!("head", "-5"))
+        .pipe(cmd!("wc", "-l"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "5");
+
+    // Input the command does not read is dropped quietly
+    let output = cmd!("head", "-1")
+        .input("line\n".repeat(100_000))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output, "line\n");
+
+    // Other upstream failures still count
+    assert!(
+        cmd!("sh", "-c", "echo x; exit 3")
+            .pipe(cmd!("head", "-1"))
+            .no_echo()
+            .output()
+            .is_err()
+    );
+}
+
+/// Tests collecting the stderr and status of every stage with capture_stages()
+#[test]
+fn test_capture_stages() {
+    let output = cmd!("sh", "-c", "echo a; echo warn1 >&2")
+        .pipe(cmd!("sh", "-c", "cat; echo warn2 >&2; exit 2"))
+        .no_echo()
+        .capture_stages()
+        .unwrap();
+    assert_eq!(output.stdout_str(), "a\n");
+    assert_eq!(output.stages.len(), 2);
+    assert_eq!(output.stages[0].stderr, b"warn1\n");
+    assert!(output.stages[0].status.success());
+    assert_eq!(output.stages[1].stderr, b"warn2\n");
+    assert_eq!(output.stages[1].status.code(), Some(2));
+    assert_eq!(output.stderr_str(), "[sh] warn1\n[sh] warn2\n");
+
+    // Stderr piped to the next command is not collected
+    let output = cmd!("sh", "-c", "echo err >&2")
+        .pipe_err(cmd!("tr", "a-z", "A-Z"))
+        .no_echo()
+        .capture_stages()
+        .unwrap();
+    assert_eq!(output.stdout_str(), "ERR\n");
+    assert!(output.stages[0].stderr.is_empty());
+}
+
+/// Tests redirecting a whole pipeline from and to files, like `cat < in | grep x > out`
+#[test]
+fn test_pipeline_file_redirections() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let input = temp.path().join("in");
+    let output = temp.path().join("out");
+    std::fs::write(&input, "x1\ny\nx2\n").unwrap();
+
+    let (result, echo) = crate::capture_echo(|| {
+        cmd!("cat")
+            .pipe(cmd!("grep", "x"))
+            .input_file(&input)
+            .output_to_file(&output)
+    });
+    result.unwrap();
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "x1\nx2\n");
+    if crate::output::should_echo() {
+        assert!(echo.contains(&format!(
+            "cat < {} | grep x > {}",
+            input.display(),
+            output.display()
+        )));
+    }
+}
@@ -14,6 +14,71 @@ fn test_quote_argument_simple() {
     assert_eq!(quoted, "simple");
 }
 
+/// Tests splitting a command line string into arguments with `args_from_str()`
+#[test]
+fn test_args_from_str() {
+    let split = |s: &str| Cmd::split_words(s).0;
+
+    assert_eq!(
+        split("-n  --color=auto\tfoo\n"),
+        ["-n", "--color=auto", "foo"]
+    );
+    assert_eq!(
+        split("'hello world' \"a \\\"b\\\" c\""),
+        ["hello world", "a \"b\" c"]
+    );
+    assert_eq!(split("'' \"\" x"), ["", "", "x"]);
+    assert_eq!(split("a'b c'd \"e\"f"), ["ab cd", "ef"]);
+    assert_eq!(split(r"a\ b \'c\' d\\"), ["a b", "'c'", "d\\"]);
+    assert_eq!(split("'\\n' \"\\n \\$\""), ["\\n", "\\n $"]);
+    assert_eq!(split("one \\\ntwo"), ["one", "two"]);
+    // No expansion, only tokenization
+    assert_eq!(split("*.rs $HOME ~ | >"), ["*.rs", "$HOME", "~", "|", ">"]);
+    // An unclosed quote runs to the end
+    assert_eq!(split("a 'b c"), ["a", "b c"]);
+    assert!(split(" \t ").is_empty());
+
+    let cmd = Cmd::new("grep")
+        .args_from_str("-n 'hello world'")
+        .arg("file.txt");
+    assert_eq!(cmd.to_command_string(), "grep -n 'hello world' file.txt");
+}
+
+/// Tests that `try_args_from_str()` rejects unterminated quotes and trailing backslashes
+#[test]
+fn test_try_args_from_str() {
+    use crate::CmdError;
+
+    let cmd = Cmd::new("grep")
+        .try_args_from_str(r#"-n 'a b' "c \"d\"" e\ f"#)
+        .unwrap();
+    assert_eq!(cmd.args, ["-n", "a b", "c \"d\"", "e f"]);
+
+    for (args, expected) in [
+        ("a 'b c", "unterminated single quote"),
+        ("a \"b c", "unterminated double quote"),
+        ("a \"b \\\"", "unterminated double quote"),
+        ("a b\\", "trailing backslash"),
+    ] {
+        match Cmd::new("grep").try_args_from_str(args) {
+            Err(CmdError::InvalidArgs {
+                args: input,
+                reason,
+            }) => {
+                assert_eq!(input, args);
+                assert_eq!(reason, expected, "{args}");
+            }
+            other => panic!("expected InvalidArgs for {args:?}, got {other:?}"),
+        }
+    }
+
+    let err = Cmd::new("grep").try_args_from_str("'x").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid arguments `'x`: unterminated single quote"
+    );
+}
+
 /// Tests quoting of arguments containing spaces
 #[test]
 fn test_quote_argument_with_spaces() {
@@ -168,3 +233,53 @@ fn test_quote_argument_mixed_everything() {
         "\"can't handle\\tthis\\ncomplex 'string' with\\0null\""
     );
 }
+
+/// Tests rendering the command line as it is echoed, without colors
+#[test]
+fn test_to_command_string() {
+    let cmd = crate::cmd!("grep", "-r", "it's here", "src")
+        .current_dir("/tmp/my dir")
+        .env("LANG", "C")
+        .env_remove("PAGER");
+    assert_eq!(
+        cmd.to_command_string(),
+        "cd: '/tmp/my dir' env: -PAGER env: LANG=C grep -r \"it's here\" src"
+    );
+
+    let pipeline = crate::cmd!("cat")
+        .pipe_err(crate::cmd!("sort"))
+        .pipe(crate::cmd!("wc", "-l"))
+        .input_file("in.txt");
+    assert_eq!(pipeline.to_command_string(), "cat < in.txt |& sort | wc -l");
+
+    // Consistent with the echo
+    let cmd = crate::cmd!("echo", "a b").env("X", "1");
+    let expected = cmd.to_command_string();
+    let (_, echo) = crate::capture_echo(|| cmd.output().unwrap());
+    assert_eq!(
+        crate::style::strip_ansi(&echo).trim(),
+        format!("scripty:cmd {}", expected)
+    );
+}
+
+/// Tests formatting commands and pipelines with Display and Debug
+#[test]
+fn test_display_and_debug() {
+    let cmd = crate::cmd!("grep", "-n", "foo");
+    assert_eq!(cmd.to_string(), "grep -n foo");
+    assert_eq!(format!("running `{}`", cmd), "running `grep -n foo`");
+
+    let pipeline = crate::cmd!("cat", "a b").pipe(cmd);
+    assert_eq!(pipeline.to_string(), pipeline.to_command_string());
+    assert_eq!(pipeline.to_string(), "cat 'a b' | grep -n foo");
+
+    let debug = format!(
+        "{:?}",
+        crate::cmd!("make", "all")
+            .env("CC", "clang")
+            .current_dir("/src")
+    );
+    for part in ["\"make\"", "\"all\"", "\"CC\"", "\"clang\"", "\"/src\""] {
+        assert!(debug.contains(part), "{} not in {}", part, debug);
+    }
+}
@@ -385,3 +385,296 @@ fn test_run_with_large_output() {
         "Should contain last line"
     );
 }
+
+#[test]
+fn test_run_discards_stdout_and_stderr() {
+    if std::env::var("TEST_SUBPROCESS").is_ok() {
+        cmd!("sh", "-c", "echo 'noisy stdout'; echo 'noisy stderr' >&2")
+            .discard_stdout()
+            .discard_stderr()
+            .no_echo()
+            .run()
+            .unwrap();
+        cmd!("sh", "-c", "echo 'kept stdout'; echo 'dropped stderr' >&2")
+            .discard_stderr()
+            .no_echo()
+            .run()
+            .unwrap();
+        // Errors still surface through the exit status
+        let error = cmd!("sh", "-c", "echo 'failing'; exit 3")
+            .discard_stdout()
+            .no_echo()
+            .run();
+        assert!(error.is_err());
+        // Capturing takes precedence over discarding
+        let output = cmd!("echo", "captured")
+            .discard_stdout()
+            .no_echo()
+            .output()
+            .unwrap();
+        assert_eq!(output.trim(), "captured");
+        return;
+    }
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .arg("--exact")
+        .arg("cmd::tests::run_output_verification::test_run_discards_stdout_and_stderr")
+        .arg("--nocapture")
+        .env("TEST_SUBPROCESS", "1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !stdout.contains("noisy stdout"),
+        "stdout should be discarded"
+    );
+    assert!(!stdout.contains("failing"), "stdout should be discarded");
+    assert!(
+        !stderr.contains("noisy stderr"),
+        "stderr should be discarded"
+    );
+    assert!(
+        !stderr.contains("dropped stderr"),
+        "stderr should be discarded"
+    );
+    assert!(
+        stdout.contains("kept stdout"),
+        "stdout should still be inherited"
+    );
+}
+
+#[test]
+fn test_inherit_input_timeout() {
+    use std::time::{Duration, Instant};
+
+    const TEST_NAME: &str = "cmd::tests::run_output_verification::test_inherit_input_timeout";
+
+    if let Ok(mode) = std::env::var("TEST_SUBPROCESS") {
+        let start = Instant::now();
+        let output = cmd!("sh", "-c", "cat; echo eof")
+            .inherit_input_timeout(Duration::from_millis(200))
+            .no_echo()
+            .output()
+            .unwrap();
+        if mode == "forward" {
+            assert_eq!(output, "hello\neof\n");
+        } else {
+            // Nothing arrives on stdin, so the command sees EOF after the timeout
+            assert_eq!(output, "eof\n");
+            assert!(start.elapsed() >= Duration::from_millis(200));
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+        return;
+    }
+
+    // Keep the subprocess's stdin open without ever writing to it
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", TEST_NAME, "--nocapture"])
+        .env("TEST_SUBPROCESS", "silent")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdin = child.stdin.take();
+    let output = child.wait_with_output().unwrap();
+    drop(stdin);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Data that arrives in time is forwarded. It comes from a file so that it is
+    // available right away, however late this process gets scheduled.
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let input = temp.path().join("input");
+    std::fs::write(&input, "hello\n").unwrap();
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", TEST_NAME, "--nocapture"])
+        .env("TEST_SUBPROCESS", "forward")
+        .stdin(std::fs::File::open(&input).unwrap())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_stdin_inherit() {
+    const TEST_NAME: &str = "cmd::tests::run_output_verification::test_stdin_inherit";
+
+    if std::env::var("TEST_SUBPROCESS").is_ok() {
+        // By default the command does not see the parent's stdin
+        assert_eq!(cmd!("cat").no_echo().output().unwrap(), "");
+        let output = cmd!("cat")
+            .stdin_inherit()
+            .input("ignored\n")
+            .pipe(cmd!("tr", "a-z", "A-Z"))
+            .no_echo()
+            .output()
+            .unwrap();
+        assert_eq!(output, "HELLO\n");
+        return;
+    }
+
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let input = temp.path().join("input");
+    std::fs::write(&input, "hello\n").unwrap();
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", TEST_NAME, "--nocapture"])
+        .env("TEST_SUBPROCESS", "1")
+        .stdin(std::fs::File::open(&input).unwrap())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+// With the `log` feature the echo goes to the logger instead of stderr
+#[cfg(not(feature = "log"))]
+#[test]
+fn test_echo_without_colors() {
+    const TEST_NAME: &str = "cmd::tests::run_output_verification::test_echo_without_colors";
+
+    if let Ok(mode) = std::env::var("TEST_SUBPROCESS") {
+        if mode == "set_enabled" {
+            crate::color::set_enabled(false);
+        }
+        cmd!("echo", "plain").run().unwrap();
+        return;
+    }
+
+    for (mode, no_color) in [("set_enabled", ""), ("env", "1")] {
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", TEST_NAME, "--nocapture"])
+            .env("TEST_SUBPROCESS", mode)
+            .env("NO_COLOR", no_color)
+            .env_remove("NO_ECHO")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("echo plain"), "missing echo: {}", stderr);
+        assert!(!stderr.contains('\x1b'), "colored echo: {:?}", stderr);
+    }
+}
+
+// With the `log` feature the echo goes to the logger instead of stderr
+#[cfg(not(feature = "log"))]
+#[test]
+fn test_echo_theme() {
+    use crate::color::{self, Style, Theme};
+    const TEST_NAME: &str = "cmd::tests::run_output_verification::test_echo_theme";
+
+    if std::env::var("TEST_SUBPROCESS").is_ok() {
+        color::set_enabled(true);
+        color::set_theme(Theme {
+            program: Style::new().fg_color(color::GREEN),
+            ..Theme::plain()
+        });
+        cmd!("echo", "themed").run().unwrap();
+        return;
+    }
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", TEST_NAME, "--nocapture"])
+        .env("TEST_SUBPROCESS", "1")
+        .env_remove("NO_ECHO")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("scripty:cmd \x1b[32mecho\x1b[0m themed\n"),
+        "unexpected echo: {:?}",
+        stderr
+    );
+}
+
+#[test]
+fn test_confirm_each() {
+    const TEST_NAME: &str = "cmd::tests::run_output_verification::test_confirm_each";
+
+    if std::env::var("TEST_SUBPROCESS").is_ok() {
+        crate::set_confirm_each(true);
+        cmd!("echo", "first").run().unwrap();
+        assert!(matches!(
+            cmd!("echo", "second").status_code(),
+            Err(crate::CmdError::Skipped { .. })
+        ));
+        // A skipped command is an error, never an empty output or a success
+        let error = cmd!("echo", "third").output().unwrap_err();
+        assert!(matches!(&error, crate::CmdError::Skipped { program } if program == "echo"));
+        cmd!("echo", "fourth").pipe(cmd!("cat")).run().unwrap();
+        // After "always" nothing is asked anymore
+        cmd!("echo", "fifth").run().unwrap();
+        cmd!("echo", "sixth").run().unwrap();
+        return;
+    }
+
+    let run = |answers: &str| {
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", TEST_NAME, "--nocapture"])
+            .env("TEST_SUBPROCESS", "1")
+            .env("NO_ECHO", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        std::io::Write::write_all(&mut stdin, answers.as_bytes()).unwrap();
+        drop(stdin);
+        child.wait_with_output().unwrap()
+    };
+
+    // "maybe" is not an answer, so the question is repeated
+    let output = run("y\nn\nmaybe\nno\nYES\na\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    for word in ["first", "fourth", "fifth", "sixth"] {
+        assert!(stdout.contains(word), "{} should run: {}", word, stdout);
+    }
+    for word in ["second", "third"] {
+        assert!(
+            !stdout.contains(word),
+            "{} should be skipped: {}",
+            word,
+            stdout
+        );
+    }
+    assert_eq!(stderr.matches("confirm run").count(), 6, "{}", stderr);
+    assert!(stderr.contains("confirm run `echo fourth | cat`?"));
+
+    // Quitting, or running out of answers, aborts the program
+    for answers in ["y\nq\n", "y\n"] {
+        let output = run(answers);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(1));
+        assert!(stdout.contains("first") && !stdout.contains("second"));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("confirm aborted"));
+    }
+}
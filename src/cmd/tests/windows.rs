@@ -0,0 +1,63 @@
+//! Windows tests.
+//!
+//! Tests for the portable core - running, piping, capturing and timeouts -
+//! using the tools that ship with Windows.
+
+use crate::cmd;
+use crate::error::CmdError;
+use std::time::Duration;
+
+/// Tests capturing the output of a command
+#[test]
+fn test_output() {
+    let output = cmd!("cmd", "/C", "echo", "hello")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "hello");
+}
+
+/// Tests piping one command into another and feeding input
+#[test]
+fn test_pipe_and_input() {
+    let output = cmd!("cmd", "/C", "echo", "hello")
+        .pipe(cmd!("findstr", "hello"))
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "hello");
+
+    let output = cmd!("findstr", "b")
+        .input("a\r\nb\r\n")
+        .no_echo()
+        .output()
+        .unwrap();
+    assert_eq!(output.trim(), "b");
+}
+
+/// Tests that a non-zero exit and a missing program are reported
+#[test]
+fn test_errors() {
+    let error = cmd!("cmd", "/C", "exit", "3").no_echo().run().unwrap_err();
+    match error {
+        CmdError::NonZeroExit { status, .. } => assert_eq!(status.code(), Some(3)),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    assert!(matches!(
+        cmd!("nonexistent_command_12345").no_echo().run(),
+        Err(CmdError::Spawn { .. })
+    ));
+    assert!(crate::require_commands(&["cmd"]).is_ok());
+}
+
+/// Tests that a timed-out command is terminated
+#[test]
+fn test_timeout() {
+    let error = cmd!("ping", "-n", "30", "127.0.0.1")
+        .timeout(Duration::from_millis(200))
+        .no_echo()
+        .run()
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Timeout { .. }));
+}
@@ -180,3 +180,155 @@ fn test_write_with_cursor() {
     // Note: This test verifies that write_to executes with a cursor without error
     // For actual output verification, we use the buffer-based tests above
 }
+
+#[test]
+fn test_output_to_file_and_append() {
+    let temp = crate::fs::create_temp_dir().unwrap();
+    let path = temp.path().join("output");
+
+    cmd!("echo", "first")
+        .no_echo()
+        .output_to_file(&path)
+        .unwrap();
+    cmd!("echo", "second")
+        .no_echo()
+        .append_to_file(&path)
+        .unwrap();
+    cmd!("echo", "hello")
+        .pipe(cmd!("tr", "a-z", "A-Z"))
+        .no_echo()
+        .append_to_file(&path)
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "first\nsecond\nHELLO\n"
+    );
+
+    // Truncates an existing file
+    cmd!("echo", "replaced")
+        .no_echo()
+        .output_to_file(&path)
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "replaced\n");
+
+    let (_, echo) = crate::capture_echo(|| {
+        crate::cmd!("sort")
+            .input_file(&path)
+            .append_to_file("/nonexistent/dir/out.txt")
+    });
+    if crate::output::should_echo() {
+        assert!(echo.contains("< "));
+        assert!(echo.ends_with(">> /nonexistent/dir/out.txt\n"));
+    }
+
+    // The file creation error names the path
+    let error = cmd!("echo", "x")
+        .no_echo()
+        .output_to_file("/nonexistent/dir/out.txt")
+        .unwrap_err();
+    assert!(matches!(error, crate::CmdError::OutputFile { .. }));
+    assert!(error.to_string().contains("/nonexistent/dir/out.txt"));
+}
+
+/// Tests that the processes are stopped when the output file cannot be written
+#[cfg(target_os = "linux")]
+#[test]
+fn test_output_to_file_write_error_stops_pipeline() {
+    use std::time::{Duration, Instant};
+
+    let dir = crate::fs::create_temp_dir().unwrap();
+    let marker = dir.path().join("marker");
+    let start = Instant::now();
+    let error = cmd!("sh", "-c", "echo x; sleep 1; touch \"$0\"", &marker)
+        .no_echo()
+        .output_to_file("/dev/full")
+        .unwrap_err();
+    assert!(matches!(error, crate::CmdError::Io(_)));
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // The command was terminated instead of being left to finish in the background
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(!marker.exists());
+}
+
+#[test]
+fn test_with_progress_reports_total_bytes() {
+    use std::sync::{Arc, Mutex};
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reports);
+    let output = cmd!("head", "-c", "100000", "/dev/zero")
+        .with_progress(move |bytes, _elapsed| sink.lock().unwrap().push(bytes))
+        .no_echo()
+        .output_bytes()
+        .unwrap();
+    assert_eq!(output.len(), 100_000);
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.last(), Some(&100_000));
+    assert!(reports.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    // Streaming to a writer reports as well
+    let total = Arc::new(Mutex::new(0));
+    let sink = Arc::clone(&total);
+    let mut buffer = Vec::new();
+    cmd!("printf", "%05000d", "0")
+        .with_progress(move |bytes, _elapsed| *sink.lock().unwrap() = bytes)
+        .no_echo()
+        .write_to(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer.len(), 5000);
+    assert_eq!(*total.lock().unwrap(), 5000);
+}
+
+#[test]
+fn test_write_to_pipeline_flushes_and_reports_errors() {
+    use crate::CmdError;
+    use std::io::{BufWriter, Write};
+
+    // A buffered writer is flushed before write_to() returns
+    let mut sink = Vec::new();
+    {
+        let mut writer = BufWriter::with_capacity(1 << 20, &mut sink);
+        cmd!("seq", "1", "1000")
+            .pipe(cmd!("grep", "7"))
+            .pipe(cmd!("wc", "-l"))
+            .no_echo()
+            .write_to(&mut writer)
+            .unwrap();
+        assert!(writer.buffer().is_empty());
+    }
+    assert_eq!(String::from_utf8(sink).unwrap().trim(), "271");
+
+    // A failing stage is reported with its stderr
+    let mut buffer = Vec::new();
+    let error = cmd!("echo", "data")
+        .pipe(cmd!("sh", "-c", "cat; echo oops >&2; exit 3"))
+        .no_echo()
+        .write_to(&mut buffer)
+        .unwrap_err();
+    assert_eq!(buffer, b"data\n");
+    match error {
+        CmdError::PipelineStageFailed { stderr, .. } => {
+            assert_eq!(stderr.as_deref(), Some(&b"oops\n"[..]))
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+
+    // A failing writer is reported after the pipeline has been reaped
+    struct Broken;
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let error = cmd!("yes")
+        .pipe(cmd!("cat"))
+        .no_echo()
+        .write_to(Broken)
+        .unwrap_err();
+    assert!(matches!(error, CmdError::Io(e) if e.to_string() == "disk full"));
+}
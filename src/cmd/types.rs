@@ -1,16 +1,31 @@
 //! Type definitions for command execution and piping.
 
+use crate::error::CmdError;
+use crate::output::EchoWriter;
+use crate::trace::Trace;
 use std::ffi::OsString;
-use std::io::Read;
+use std::io::{BufRead, Read, Write};
 use std::path::PathBuf;
-use std::process::Child;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-/// Input source for commands - either bytes in memory or a streaming reader.
+/// Input source for commands - bytes in memory, a streaming reader, a file or the parent's stdin.
 pub(crate) enum CmdInput {
     /// Pre-loaded bytes in memory
     Bytes(Vec<u8>),
     /// Streaming reader (boxed for object safety)
     Reader(Box<dyn Read + Send>),
+    /// Lines written one by one, each followed by a newline
+    Lines(Box<dyn Iterator<Item = String> + Send>),
+    /// File opened when the pipeline is executed
+    File(PathBuf),
+    /// Input file once opened, copied with `splice` where possible
+    Opened(std::fs::File),
+    /// The parent's stdin, closed if no data arrives within the timeout
+    Inherit(Duration),
 }
 
 impl std::fmt::Debug for CmdInput {
@@ -21,18 +36,121 @@ impl std::fmt::Debug for CmdInput {
                 .field(&format!("{} bytes", bytes.len()))
                 .finish(),
             CmdInput::Reader(_) => f.debug_tuple("Reader").field(&"<reader>").finish(),
+            CmdInput::Lines(_) => f.debug_tuple("Lines").field(&"<iterator>").finish(),
+            CmdInput::File(path) => f.debug_tuple("File").field(path).finish(),
+            CmdInput::Opened(file) => f.debug_tuple("Opened").field(file).finish(),
+            CmdInput::Inherit(timeout) => f.debug_tuple("Inherit").field(timeout).finish(),
+        }
+    }
+}
+
+/// Redirection of a standard stream that would otherwise be inherited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StdioMode {
+    /// Connect the stream to `/dev/null`.
+    Null,
+    /// Share the parent's stream, e.g. the terminal.
+    Inherit,
+    /// Pipe the stream back to the parent, even for a command in the middle of a pipeline.
+    Piped,
+}
+
+impl StdioMode {
+    pub(crate) fn to_stdio(self) -> Stdio {
+        match self {
+            StdioMode::Null => Stdio::null(),
+            StdioMode::Inherit => Stdio::inherit(),
+            StdioMode::Piped => Stdio::piped(),
         }
     }
 }
 
 /// A simple command builder.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Cmd {
     pub(crate) program: OsString,
     pub(crate) args: Vec<OsString>,
     pub(crate) envs: Vec<(OsString, OsString)>,
+    /// Inherited variables to unset; never overlaps with `envs`.
+    pub(crate) env_removes: Vec<OsString>,
+    /// Start from an empty environment instead of inheriting the parent's.
+    pub(crate) env_clear: bool,
+    /// Variables copied from the parent's environment after clearing it.
+    pub(crate) env_inherit: Vec<OsString>,
     pub(crate) current_dir: Option<PathBuf>,
+    /// File mode creation mask set in the child before exec.
+    pub(crate) umask: Option<u32>,
+    /// User ID the child switches to before exec.
+    #[cfg(unix)]
+    pub(crate) uid: Option<u32>,
+    /// Group ID the child switches to before exec.
+    #[cfg(unix)]
+    pub(crate) gid: Option<u32>,
+    /// Name passed to the program as `argv[0]` instead of `program`.
+    #[cfg(unix)]
+    pub(crate) arg0: Option<OsString>,
+    /// Process group the child joins, `0` for a new group led by the child.
+    #[cfg(unix)]
+    pub(crate) process_group: Option<i32>,
+    /// Hooks run in the child before exec, in the order they were added.
+    #[cfg(unix)]
+    pub(crate) pre_exec: Vec<PreExecHook>,
+    /// Where stdin comes from instead of a pipe fed by the parent.
+    pub(crate) stdin_mode: Option<StdioMode>,
+    /// Where stdout goes when it is neither piped to another command nor captured.
+    pub(crate) stdout_mode: Option<StdioMode>,
+    /// Where stderr goes when it is neither piped to another command nor captured.
+    pub(crate) stderr_mode: Option<StdioMode>,
+    /// Make stderr a copy of stdout in the child, after every other redirection.
+    pub(crate) stderr_to_stdout: bool,
     pub(crate) suppress_echo: bool,
+    /// Echo the working directory along with the command.
+    pub(crate) verbose_echo: bool,
+    /// Where the command is echoed to instead of the usual destination.
+    pub(crate) echo_writer: Option<EchoWriter>,
+    /// Terminate the processes when the handle of a spawned pipeline is dropped.
+    pub(crate) kill_on_drop: bool,
+    pub(crate) timeout: Option<Duration>,
+}
+
+/// Closure run in the child process between fork and exec.
+#[cfg(unix)]
+#[derive(Clone)]
+pub(crate) struct PreExecHook(
+    pub(crate) std::sync::Arc<dyn Fn() -> std::io::Result<()> + Send + Sync>,
+);
+
+#[cfg(unix)]
+impl std::fmt::Debug for PreExecHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PreExecHook").field(&"<closure>").finish()
+    }
+}
+
+/// A stage of a pipeline: an external command or a Rust closure.
+// Pipelines hold a handful of stages, mostly commands, so boxing them would not pay off
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub(crate) enum Stage {
+    Cmd(Cmd),
+    Fn(StageFn),
+}
+
+/// Body of a closure stage, reading the upstream output and writing the stage's output.
+pub(crate) type StageBody =
+    Box<dyn FnOnce(&mut dyn BufRead, &mut dyn Write) -> std::io::Result<()> + Send>;
+
+/// A pipeline stage implemented in Rust, run on its own thread.
+pub(crate) struct StageFn {
+    /// Name shown in the echoed pipeline.
+    pub(crate) name: &'static str,
+    pub(crate) body: StageBody,
+}
+
+impl std::fmt::Debug for StageFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StageFn").field("name", &self.name).finish()
+    }
 }
 
 /// Specifies which output streams should be piped between commands.
@@ -95,6 +213,43 @@ pub(crate) enum PipeMode {
 /// Handle to a spawned pipeline for waiting and collecting results.
 pub struct PipelineHandle {
     pub(crate) children: Vec<Child>,
+    /// Program names of `children`, used for error reporting.
+    pub(crate) programs: Vec<OsString>,
+    /// Threads running the Rust closure stages of the pipeline.
+    pub(crate) threads: Vec<JoinHandle<std::io::Result<()>>>,
+    /// When set, the children are terminated if they are still running at this point.
+    pub(crate) deadline: Option<Deadline>,
+    /// Whether a failing command other than the last one is an error.
+    pub(crate) pipefail: bool,
+    /// Tracing spans of the commands, with the `tracing` feature.
+    pub(crate) trace: Trace,
+    /// Terminate the children that are still running when the handle is dropped.
+    pub(crate) kill_on_drop: bool,
+    /// Raised from another thread to terminate the children while they are waited for.
+    pub(crate) cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for PipelineHandle {
+    fn default() -> Self {
+        Self {
+            children: Vec::new(),
+            programs: Vec::new(),
+            threads: Vec::new(),
+            deadline: None,
+            pipefail: true,
+            trace: Trace::default(),
+            kill_on_drop: false,
+            cancel: None,
+        }
+    }
+}
+
+/// Point in time after which a spawned pipeline is terminated.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    pub(crate) at: Instant,
+    /// How long the pipeline was allowed to run, reported in timeout errors.
+    pub(crate) limit: Duration,
 }
 
 /// Complete I/O access to a spawned pipeline.
@@ -105,10 +260,180 @@ pub struct PipelineSpawn {
     pub stderr: Option<std::process::ChildStderr>,
 }
 
+/// Iterator over fixed-size frames of a running pipeline's stdout.
+///
+/// Returned by [`Cmd::frames`] and [`Pipeline::frames`].
+pub struct Frames {
+    pub(crate) stdout: Option<Box<dyn Read + Send>>,
+    pub(crate) handle: Option<PipelineHandle>,
+    pub(crate) input: Option<JoinHandle<()>>,
+    pub(crate) size: usize,
+    pub(crate) strict: bool,
+}
+
+/// Captured stdout, stderr and exit status of a finished command.
+///
+/// Returned by [`Cmd::capture`] and [`Pipeline::capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    /// The bytes written to stdout.
+    pub stdout: Vec<u8>,
+    /// The bytes written to stderr.
+    pub stderr: Vec<u8>,
+    /// The exit status of the command.
+    pub status: ExitStatus,
+}
+
+impl Output {
+    /// Stdout as text, with invalid UTF-8 replaced.
+    pub fn stdout_str(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// Stderr as text, with invalid UTF-8 replaced.
+    pub fn stderr_str(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+}
+
+/// Stdout of a pipeline together with the stderr and exit status of every command.
+///
+/// Returned by [`Pipeline::capture_stages`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineOutput {
+    /// The bytes the last stage wrote to stdout.
+    pub stdout: Vec<u8>,
+    /// One entry per command in pipeline order; closure stages are left out.
+    pub stages: Vec<StageOutput>,
+}
+
+/// Stderr and exit status of one command of a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageOutput {
+    /// The program of the command.
+    pub program: OsString,
+    /// The bytes written to stderr; empty if stderr was piped to the next command or
+    /// redirected.
+    pub stderr: Vec<u8>,
+    /// The exit status of the command.
+    pub status: ExitStatus,
+}
+
+impl PipelineOutput {
+    /// Stdout as text, with invalid UTF-8 replaced.
+    pub fn stdout_str(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// The stderr of every command as text, each line tagged with the program that
+    /// wrote it, e.g. `[grep] foo.txt: No such file or directory`.
+    pub fn stderr_str(&self) -> String {
+        let mut text = String::new();
+        for stage in &self.stages {
+            let program = stage.program.to_string_lossy();
+            for line in String::from_utf8_lossy(&stage.stderr).lines() {
+                text.push_str(&format!("[{}] {}\n", program, line));
+            }
+        }
+        text
+    }
+}
+
+/// Target of a `> path` or `>> path` redirection.
+#[derive(Debug)]
+pub(crate) struct OutputFile {
+    pub(crate) path: PathBuf,
+    pub(crate) append: bool,
+}
+
 /// A pipeline of commands.
 #[derive(Debug)]
 pub struct Pipeline {
-    pub(crate) connections: Vec<(Cmd, PipeMode)>,
+    pub(crate) connections: Vec<(Stage, PipeMode)>,
     pub(crate) input: Option<CmdInput>,
+    /// File the output is redirected to, shown in the echo.
+    pub(crate) output_file: Option<OutputFile>,
     pub(crate) suppress_echo: bool,
+    /// Echo the working directory along with the pipeline.
+    pub(crate) verbose_echo: bool,
+    /// Where the pipeline is echoed to instead of the usual destination.
+    pub(crate) echo_writer: Option<EchoWriter>,
+    /// Terminate the processes when the handle of the spawned pipeline is dropped.
+    pub(crate) kill_on_drop: bool,
+    pub(crate) timeout: Option<Duration>,
+    /// Whether a failing command other than the last one fails the pipeline.
+    pub(crate) pipefail: bool,
+    /// Called with the bytes read from stdout so far and the elapsed time.
+    pub(crate) progress: Option<ProgressFn>,
+    /// Called with every line the last command writes to stderr.
+    pub(crate) stderr_line: Option<LineFn>,
+    /// File recording the data passing through the piped streams.
+    pub(crate) record: Option<PathBuf>,
+    /// Capacity of the buffers closure stages read and write through.
+    pub(crate) buffer_size: usize,
+    /// Raised when the future of an async run is dropped, to stop the pipeline.
+    pub(crate) cancel: Option<Arc<AtomicBool>>,
+}
+
+/// Commands run one after another, each depending on how the previous ones went.
+///
+/// Built with [`Cmd::or_cmd`] (`a || b`) and [`Cmd::chain`] (`a && b`).
+#[derive(Debug)]
+pub struct CmdList {
+    pub(crate) first: Cmd,
+    pub(crate) rest: Vec<(ListOp, Cmd)>,
+}
+
+/// A command that is run again when it fails.
+///
+/// Built with [`Cmd::retry`] and [`Cmd::retry_if`].
+pub struct Retry {
+    pub(crate) cmd: Cmd,
+    /// Total number of runs allowed, at least 1.
+    pub(crate) attempts: usize,
+    /// Delay before the second attempt, doubled for every later one.
+    pub(crate) backoff: Duration,
+    /// Decides whether an error is retried; by default only failed commands are.
+    pub(crate) predicate: Option<RetryPredicate>,
+}
+
+/// Predicate given to [`Cmd::retry_if`].
+pub(crate) type RetryPredicate = Box<dyn Fn(&CmdError) -> bool>;
+
+impl std::fmt::Debug for Retry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Retry")
+            .field("cmd", &self.cmd)
+            .field("attempts", &self.attempts)
+            .field("backoff", &self.backoff)
+            .field("predicate", &self.predicate.as_ref().map(|_| "<closure>"))
+            .finish()
+    }
+}
+
+/// Condition under which a command of a [`CmdList`] runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListOp {
+    /// Run if the previous command succeeded (`&&`).
+    And,
+    /// Run if the previous command failed (`||`).
+    Or,
+}
+
+/// Progress callback set with [`Pipeline::with_progress`].
+pub(crate) struct ProgressFn(pub(crate) Box<dyn FnMut(u64, Duration) + Send>);
+
+impl std::fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressFn").field(&"<callback>").finish()
+    }
+}
+
+/// Line callback set with [`Pipeline::on_stderr_line`].
+pub(crate) struct LineFn(pub(crate) Box<dyn FnMut(&str) + Send>);
+
+impl std::fmt::Debug for LineFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LineFn").field(&"<callback>").finish()
+    }
 }
@@ -1,4 +1,140 @@
+//! Colors used by scripty's echo output, and the switch to turn them off.
+
+use crate::style;
 use anstyle::{AnsiColor, Color};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, RwLock};
+
+pub use anstyle::Style;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static INIT: Once = Once::new();
+static THEME: RwLock<Theme> = RwLock::new(Theme::DEFAULT);
+
+/// Apply the `NO_COLOR` convention unless colors were set explicitly first.
+fn init() {
+    INIT.call_once(|| {
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            ENABLED.store(false, Ordering::Relaxed);
+        }
+    });
+}
+
+/// Enable or disable colors in the echoed commands and file system operations.
+///
+/// When disabled, the echo is written as plain text without any ANSI escape
+/// sequences, e.g. for logs that go to a file. Colors are enabled by default unless
+/// the [`NO_COLOR`](https://no-color.org) environment variable is set to a non-empty
+/// value when scripty first echoes something; calling this function overrides it.
+///
+/// # Examples
+///
+/// ```
+/// use scripty::color;
+///
+/// color::set_enabled(false);
+/// assert!(!color::is_enabled());
+/// ```
+pub fn set_enabled(enabled: bool) {
+    INIT.call_once(|| {});
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the echo output is colored; see [`set_enabled`].
+pub fn is_enabled() -> bool {
+    init();
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Styles of the parts of the echoed commands and file system operations.
+///
+/// Start from [`Theme::default`], which is scripty's usual look, and override the
+/// parts to change. A theme only decides how the echo looks when colors are enabled;
+/// [`set_enabled`] still turns all styling off.
+///
+/// # Examples
+///
+/// ```
+/// use scripty::color::{self, Style, Theme};
+///
+/// color::set_theme(Theme {
+///     program: Style::new().fg_color(color::GREEN).bold(),
+///     args: Style::new(),
+///     ..Theme::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// The `scripty:cmd` and `scripty:fs` tags in front of each line.
+    pub prefix: Style,
+    /// The program of a command, and the name of a closure stage.
+    pub program: Style,
+    /// The arguments of a command, and the paths it is redirected from or to.
+    pub args: Style,
+    /// The labels of the settings shown in front of a command, such as `cd:` and `env:`.
+    pub env_label: Style,
+    /// The values of those settings, such as the directory or `KEY=value`.
+    pub env: Style,
+    /// Pipe symbols and the `<`, `>` and `>>` redirection operators.
+    pub redirection: Style,
+    /// The name of a file system operation, such as `copy`.
+    pub fs_op: Style,
+    /// The paths and other details of a file system operation.
+    pub fs_args: Style,
+}
+
+impl Theme {
+    const DEFAULT: Theme = Theme {
+        prefix: style::BRIGHT_BLACK,
+        program: style::BOLD_CYAN,
+        args: style::BOLD_UNDERLINE,
+        env_label: style::BRIGHT_BLUE,
+        env: style::UNDERLINE_BRIGHT_BLUE,
+        redirection: style::MAGENTA,
+        fs_op: style::BOLD_CYAN,
+        fs_args: style::BOLD_UNDERLINE,
+    };
+
+    /// A theme without any styling.
+    ///
+    /// Unlike [`set_enabled(false)`](set_enabled), which strips the escape sequences
+    /// from the echo, this is a theme like any other and can be a base for one that
+    /// only highlights a few parts.
+    pub const fn plain() -> Self {
+        let plain = Style::new();
+        Theme {
+            prefix: plain,
+            program: plain,
+            args: plain,
+            env_label: plain,
+            env: plain,
+            redirection: plain,
+            fs_op: plain,
+            fs_args: plain,
+        }
+    }
+}
+
+impl Default for Theme {
+    /// Scripty's usual look.
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Use `theme` for all echo output from now on.
+pub fn set_theme(theme: Theme) {
+    *THEME
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = theme;
+}
+
+/// The theme currently used for the echo; see [`set_theme`].
+pub fn theme() -> Theme {
+    *THEME
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
 
 // Basic colors
 pub const BLACK: Option<Color> = Some(Color::Ansi(AnsiColor::Black));
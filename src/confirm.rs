@@ -0,0 +1,90 @@
+//! Interactive confirmation before each command.
+
+use std::io::{BufRead, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Held while prompting so that commands started from several threads ask in turn.
+static PROMPT: Mutex<()> = Mutex::new(());
+
+/// Ask for confirmation before every command is run.
+///
+/// Turns any script into a reviewable runner for destructive operations. Before a
+/// command or pipeline is spawned, scripty names it on stderr and reads an answer
+/// from stdin:
+///
+/// - `y` (or `yes`) runs it;
+/// - `n` (or `no`) skips it: nothing is spawned, and running the command fails with
+///   [`CmdError::Skipped`](crate::CmdError::Skipped), so that a skipped check is never
+///   taken for a successful one;
+/// - `a` (or `always`) runs it and every following command without asking again;
+/// - `q` (or `quit`) aborts the whole program with exit code 1. So does the end of
+///   stdin.
+///
+/// Any other answer repeats the question. Answers are read from stdin, so this is
+/// meant for interactive use with stdin connected to a terminal. Commands that feed
+/// the parent's stdin to the child, such as
+/// [`inherit_input_timeout`](crate::Cmd::inherit_input_timeout), compete with the
+/// prompt for it. File system operations are not confirmed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// set_confirm_each(true);
+/// cmd!("rm", "-rf", "build").run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn set_confirm_each(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether a command about to run should be skipped, after asking if confirmation is on.
+///
+/// `description` names the command in the prompt. Does not return if the user quits.
+pub(crate) fn should_skip(description: &str) -> bool {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    let _guard = PROMPT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    // Another thread may have answered "always" while this one was waiting
+    if !ENABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    let stdin = std::io::stdin();
+    let mut answer = String::new();
+    loop {
+        eprint!(
+            "{}:confirm run `{}`? [y]es/[n]o/[a]lways/[q]uit: ",
+            env!("CARGO_PKG_NAME"),
+            description
+        );
+        let _ = std::io::stderr().flush();
+
+        answer.clear();
+        if stdin.lock().read_line(&mut answer).unwrap_or(0) == 0 {
+            quit();
+        }
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => return false,
+            "n" | "no" => return true,
+            "a" | "always" => {
+                ENABLED.store(false, Ordering::Relaxed);
+                return false;
+            }
+            "q" | "quit" => quit(),
+            _ => {}
+        }
+    }
+}
+
+fn quit() -> ! {
+    eprintln!("{}:confirm aborted", env!("CARGO_PKG_NAME"));
+    std::process::exit(1);
+}
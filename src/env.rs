@@ -0,0 +1,98 @@
+//! Scoped changes to the process environment.
+
+use std::ffi::{OsStr, OsString};
+
+/// Set a process environment variable while `f` runs, then restore it.
+///
+/// Afterwards the variable gets its previous value back, or is removed again if it was
+/// not set before. This also happens when `f` returns early or panics. Useful when a
+/// library reads an environment variable that cannot be passed per command; to set a
+/// variable for a single command, prefer [`Cmd::env`](crate::Cmd::env).
+///
+/// # Safety
+///
+/// **The environment is global to the process.** The change is visible to every thread
+/// while `f` runs, and modifying the environment while another thread reads or writes
+/// it is undefined behavior on most platforms. This function has the same safety
+/// requirements as [`std::env::set_var`]: the caller must make sure no other thread
+/// accesses the environment during the call, e.g. by calling it before spawning threads.
+/// Note that spawning a [`Cmd`](crate::Cmd) on another thread reads the environment.
+///
+/// # Examples
+///
+/// ```
+/// use scripty::*;
+///
+/// // SAFETY: no other threads are running
+/// let level = unsafe {
+///     with_env("SCRIPTY_DOC_LOG", "debug", || std::env::var("SCRIPTY_DOC_LOG"))
+/// };
+/// assert_eq!(level.as_deref(), Ok("debug"));
+/// assert!(std::env::var_os("SCRIPTY_DOC_LOG").is_none());
+/// ```
+pub unsafe fn with_env<R>(
+    key: impl AsRef<OsStr>,
+    value: impl AsRef<OsStr>,
+    f: impl FnOnce() -> R,
+) -> R {
+    /// Restores the previous value, also when `f` panics.
+    struct Restore {
+        key: OsString,
+        previous: Option<OsString>,
+    }
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            // SAFETY: upheld by the caller of `with_env` for the duration of the call.
+            unsafe {
+                match &self.previous {
+                    Some(value) => std::env::set_var(&self.key, value),
+                    None => std::env::remove_var(&self.key),
+                }
+            }
+        }
+    }
+
+    let key = key.as_ref();
+    let _restore = Restore {
+        key: key.to_os_string(),
+        previous: std::env::var_os(key),
+    };
+    // SAFETY: upheld by the caller.
+    unsafe {
+        std::env::set_var(key, value);
+    }
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn test_with_env_restores_previous_value() {
+        const KEY: &str = "SCRIPTY_WITH_ENV_TEST";
+
+        unsafe {
+            // Unset before: removed again afterwards
+            std::env::remove_var(KEY);
+            let seen = with_env(KEY, "scoped", || {
+                crate::cmd!("printenv", KEY).no_echo().output().unwrap()
+            });
+            assert_eq!(seen.trim(), "scoped");
+            assert!(std::env::var_os(KEY).is_none());
+
+            // Set before: previous value restored, also after a panic
+            std::env::set_var(KEY, "original");
+            let result = std::panic::catch_unwind(|| {
+                with_env(KEY, "scoped", || panic!("boom"));
+            });
+            assert!(result.is_err());
+            assert_eq!(std::env::var(KEY).as_deref(), Ok("original"));
+
+            std::env::remove_var(KEY);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Error types for command execution.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// Error returned when running a command or pipeline.
+///
+/// Match on the variants to tell apart a program that could not be started
+/// ([`Spawn`](Self::Spawn)), a program that ran but exited unsuccessfully
+/// ([`NonZeroExit`](Self::NonZeroExit)), a program killed by a signal
+/// ([`Signaled`](Self::Signaled)), and an I/O failure while talking to the
+/// child processes ([`Io`](Self::Io)).
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// match cmd!("grep", "pattern", "file.txt").output() {
+///     Ok(matches) => println!("{}", matches),
+///     Err(CmdError::Spawn { program, .. }) => {
+///         eprintln!("{} is not installed", program.to_string_lossy())
+///     }
+///     Err(CmdError::NonZeroExit { status, stderr, .. }) => {
+///         eprintln!("grep exited with {}", status);
+///         if let Some(stderr) = stderr {
+///             eprintln!("{}", String::from_utf8_lossy(&stderr));
+///         }
+///     }
+///     Err(CmdError::Signaled { signal, .. }) => eprintln!("grep was killed by signal {}", signal),
+///     Err(e) => eprintln!("I/O failure: {}", e),
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CmdError {
+    /// The program could not be spawned, e.g. because it does not exist.
+    ///
+    /// A program that cannot be found has a `source` of kind
+    /// [`NotFound`](std::io::ErrorKind::NotFound), and the message says so:
+    /// `Command not found: 'foo' (is it installed and on PATH?)`. The OS reports the
+    /// same error when the program exists but the interpreter named in its `#!` line
+    /// does not; the program is then looked up with [`which`](crate::which) rules and,
+    /// if it is found, the message says where instead: `Failed to run 'foo': found at
+    /// /usr/local/bin/foo, but its interpreter or a library it needs is missing`.
+    Spawn {
+        /// The program that failed to start.
+        program: OsString,
+        /// The underlying error reported by the OS.
+        source: std::io::Error,
+        /// Where the program was found when spawning failed with `NotFound`, meaning
+        /// that its interpreter or a library it needs is missing rather than the
+        /// program itself.
+        resolved: Option<PathBuf>,
+    },
+    /// The program ran but exited with a non-zero exit code.
+    NonZeroExit {
+        /// The program that failed.
+        program: OsString,
+        /// The exit status of the program; [`ExitStatus::code`] is always `Some`.
+        status: ExitStatus,
+        /// The program's stderr, when it was piped rather than inherited.
+        stderr: Option<Vec<u8>>,
+    },
+    /// The program was terminated by a signal, e.g. killed with `SIGKILL`.
+    Signaled {
+        /// The program that was terminated.
+        program: OsString,
+        /// The number of the signal that terminated the program.
+        signal: i32,
+        /// The program's stderr, when it was piped rather than inherited.
+        stderr: Option<Vec<u8>>,
+    },
+    /// A command of a pipeline of several commands exited unsuccessfully or was
+    /// terminated by a signal.
+    ///
+    /// A single command reports [`NonZeroExit`](Self::NonZeroExit) or
+    /// [`Signaled`](Self::Signaled) instead. The message names the stage, counting
+    /// from 1: `Pipeline stage 2 'grep' exited with code 2`.
+    PipelineStageFailed {
+        /// Zero-based position of the command among the pipeline's commands.
+        index: usize,
+        /// The program that failed.
+        program: OsString,
+        /// The exit status of the program.
+        status: ExitStatus,
+        /// The program's stderr, when it was piped rather than inherited.
+        stderr: Option<Vec<u8>>,
+    },
+    /// The program was still running when its timeout expired and has been terminated.
+    ///
+    /// Any output captured before the timeout is discarded.
+    Timeout {
+        /// The program that was still running.
+        program: OsString,
+        /// How long the program was allowed to run.
+        timeout: Duration,
+    },
+    /// The output given to [`output_bytes_limited`](crate::Cmd::output_bytes_limited)
+    /// grew past the limit.
+    ///
+    /// Reading stopped at the limit and the captured output is discarded.
+    OutputLimit {
+        /// The program whose output was too large.
+        program: OsString,
+        /// The maximum number of bytes that was allowed.
+        limit: usize,
+    },
+    /// Programs passed to [`require_commands`](crate::require_commands) could not be found.
+    MissingCommands {
+        /// Every program that was not found on `PATH`.
+        programs: Vec<OsString>,
+    },
+    /// The file given to [`input_file`](crate::Cmd::input_file) could not be opened.
+    InputFile {
+        /// The path of the input file.
+        path: PathBuf,
+        /// The underlying error reported by the OS.
+        source: std::io::Error,
+    },
+    /// The file given to [`output_to_file`](crate::Cmd::output_to_file),
+    /// [`append_to_file`](crate::Cmd::append_to_file) or
+    /// [`record_io`](crate::Cmd::record_io) could not be opened.
+    OutputFile {
+        /// The path of the output file.
+        path: PathBuf,
+        /// The underlying error reported by the OS.
+        source: std::io::Error,
+    },
+    /// The working directory given to [`current_dir`](crate::Cmd::current_dir) does
+    /// not exist or is not a directory.
+    ///
+    /// It is checked before anything is spawned, so no command of the pipeline runs.
+    CurrentDir {
+        /// The program that was to run in the directory.
+        program: OsString,
+        /// The working directory.
+        path: PathBuf,
+        /// Why the directory cannot be used.
+        source: std::io::Error,
+    },
+    /// The command line given to [`try_args_from_str`](crate::Cmd::try_args_from_str)
+    /// has an unterminated quote or a trailing backslash.
+    ///
+    /// It is checked while building the command, so nothing is spawned.
+    InvalidArgs {
+        /// The command line that could not be split.
+        args: String,
+        /// What is wrong with it, e.g. `unterminated single quote`.
+        reason: &'static str,
+    },
+    /// The output given to [`output_json`](crate::Cmd::output_json) is not valid JSON
+    /// for the requested type.
+    #[cfg(feature = "serde")]
+    Json {
+        /// The program whose output could not be parsed.
+        program: OsString,
+        /// The part of the output around the error, for debugging.
+        snippet: String,
+        /// The underlying parse error.
+        source: serde_json::Error,
+    },
+    /// The command was declined at the prompt of
+    /// [`set_confirm_each`](crate::set_confirm_each), so nothing was spawned.
+    Skipped {
+        /// The program that was not run, the first one of a pipeline.
+        program: OsString,
+    },
+    /// An I/O error occurred while communicating with the child processes.
+    Io(std::io::Error),
+}
+
+/// Alias of [`CmdError`] kept for backward compatibility.
+pub type Error = CmdError;
+
+impl std::fmt::Display for CmdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CmdError::Spawn {
+                program,
+                source,
+                resolved,
+            } if source.kind() == std::io::ErrorKind::NotFound => {
+                let program = std::path::Path::new(program);
+                match resolved {
+                    Some(path) => write!(
+                        f,
+                        "Failed to run '{}': found at {}, but its interpreter or a library it \
+                         needs is missing",
+                        program.display(),
+                        path.display()
+                    ),
+                    // A program given as a path is not looked up on PATH
+                    None if program.components().count() > 1 => write!(
+                        f,
+                        "Command not found: '{}' (program or its interpreter not found)",
+                        program.display()
+                    ),
+                    None => write!(
+                        f,
+                        "Command not found: '{}' (is it installed and on PATH?)",
+                        program.display()
+                    ),
+                }
+            }
+            CmdError::Spawn {
+                program, source, ..
+            } => write!(
+                f,
+                "Failed to spawn command: {}: {}",
+                program.to_string_lossy(),
+                source
+            ),
+            CmdError::NonZeroExit {
+                program, status, ..
+            } => write!(
+                f,
+                "Command '{}' failed with {}",
+                program.to_string_lossy(),
+                status
+            ),
+            CmdError::Signaled {
+                program, signal, ..
+            } => write!(
+                f,
+                "Command '{}' was terminated by signal {}",
+                program.to_string_lossy(),
+                signal
+            ),
+            CmdError::PipelineStageFailed {
+                index,
+                program,
+                status,
+                ..
+            } => {
+                write!(
+                    f,
+                    "Pipeline stage {} '{}' ",
+                    index + 1,
+                    program.to_string_lossy()
+                )?;
+                match status.code() {
+                    Some(code) => write!(f, "exited with code {}", code),
+                    None => write!(f, "failed with {}", status),
+                }
+            }
+            CmdError::Timeout { program, timeout } => write!(
+                f,
+                "Command '{}' timed out after {:?}",
+                program.to_string_lossy(),
+                timeout
+            ),
+            CmdError::OutputLimit { program, limit } => write!(
+                f,
+                "Output of '{}' exceeded the limit of {} bytes",
+                program.to_string_lossy(),
+                limit
+            ),
+            CmdError::MissingCommands { programs } => {
+                write!(f, "Required commands not found: ")?;
+                for (i, program) in programs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", program.to_string_lossy())?;
+                }
+                Ok(())
+            }
+            CmdError::InputFile { path, source } => write!(
+                f,
+                "Failed to open input file '{}': {}",
+                path.display(),
+                source
+            ),
+            CmdError::OutputFile { path, source } => write!(
+                f,
+                "Failed to open output file '{}': {}",
+                path.display(),
+                source
+            ),
+            CmdError::CurrentDir { path, source, .. }
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                write!(f, "Working directory does not exist: {}", path.display())
+            }
+            CmdError::CurrentDir {
+                program,
+                path,
+                source,
+            } => write!(
+                f,
+                "Invalid working directory '{}' for '{}': {}",
+                path.display(),
+                program.to_string_lossy(),
+                source
+            ),
+            CmdError::InvalidArgs { args, reason } => {
+                write!(f, "Invalid arguments `{}`: {}", args, reason)
+            }
+            #[cfg(feature = "serde")]
+            CmdError::Json {
+                program,
+                snippet,
+                source,
+            } => write!(
+                f,
+                "Failed to parse output of '{}' as JSON: {} near `{}`",
+                program.to_string_lossy(),
+                source,
+                snippet
+            ),
+            CmdError::Skipped { program } => write!(
+                f,
+                "Command '{}' was skipped at the confirmation prompt",
+                program.to_string_lossy()
+            ),
+            CmdError::Io(source) => write!(f, "Command I/O failed: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for CmdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CmdError::Spawn { source, .. }
+            | CmdError::InputFile { source, .. }
+            | CmdError::OutputFile { source, .. }
+            | CmdError::CurrentDir { source, .. }
+            | CmdError::Io(source) => Some(source),
+            #[cfg(feature = "serde")]
+            CmdError::Json { source, .. } => Some(source),
+            CmdError::NonZeroExit { .. }
+            | CmdError::Signaled { .. }
+            | CmdError::PipelineStageFailed { .. }
+            | CmdError::Timeout { .. }
+            | CmdError::OutputLimit { .. }
+            | CmdError::MissingCommands { .. }
+            | CmdError::InvalidArgs { .. }
+            | CmdError::Skipped { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CmdError {
+    fn from(err: std::io::Error) -> Self {
+        CmdError::Io(err)
+    }
+}
@@ -5,26 +5,79 @@
 //!
 //! For more information on the behavior of these functions, see the documentation for the corresponding
 //! functions in [`std::fs`].
+//!
+//! Every function of `std::fs` has a counterpart here, except for the deprecated
+//! `soft_link`, which [`symlink`] replaces, and `std::fs::exists`, whose role [`exists`]
+//! plays with a plain `bool`. Of the platform-specific functions, only the Unix
+//! [`symlink`] and [`chown`] are wrapped; `chroot`, `lchown` and the Windows
+//! `symlink_file`/`symlink_dir` are not.
 
-use crate::output::{conditional_eprintln, should_echo};
-use crate::style::{BOLD_CYAN, BOLD_UNDERLINE, BRIGHT_BLACK};
+use crate::output::{EchoKind, conditional_eprintln, should_echo, should_echo_verbose};
+use std::io::{Read, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 fn echo_operation(op: &str, details: &str) {
     if should_echo() {
-        let styled_fs = format!(
-            "  {BRIGHT_BLACK}{}:fs{BRIGHT_BLACK:#}",
-            env!("CARGO_PKG_NAME")
+        let theme = crate::color::theme();
+        let (prefix, fs_op, fs_args) = (theme.prefix, theme.fs_op, theme.fs_args);
+        let styled_fs = format!("  {prefix}{}:fs{prefix:#}", env!("CARGO_PKG_NAME"));
+        let styled_op = format!("{fs_op}{op}{fs_op:#}");
+        let styled_details = format!("{fs_args}{details}{fs_args:#}");
+        conditional_eprintln(
+            EchoKind::Fs,
+            format_args!("{} {} {}", styled_fs, styled_op, styled_details),
         );
-        let styled_op = format!("{BOLD_CYAN}{op}{BOLD_CYAN:#}");
-        let styled_details = format!("{BOLD_UNDERLINE}{details}{BOLD_UNDERLINE:#}");
-        conditional_eprintln(format_args!(
-            "{} {} {}",
-            styled_fs, styled_op, styled_details
-        ));
     }
 }
 
+/// Append a slice to the end of a file, creating the file if it does not exist.
+///
+/// Unlike reading the file and writing it back, this only writes the new bytes, and
+/// writes from several processes do not overwrite each other. Echoes the operation
+/// to the console.
+pub fn append(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let contents = contents.as_ref();
+    echo_operation(
+        "append",
+        &format!("{} bytes -> {}", contents.len(), path.display()),
+    );
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?
+        .write_all(contents)
+}
+
+/// Resolve a path to its absolute form, with all symlinks and `.` and `..` components
+/// resolved.
+///
+/// This is a wrapper around [`std::fs::canonicalize`] that echoes the operation to the
+/// console. The path must exist; the echoed line right before the error shows which
+/// path was not found.
+pub fn canonicalize(path: impl AsRef<Path>) -> std::io::Result<std::path::PathBuf> {
+    let path = path.as_ref();
+    echo_operation("canonicalize", &path.display().to_string());
+    std::fs::canonicalize(path)
+}
+
+/// Change the owner and group of a file or directory, following symlinks.
+///
+/// This is a wrapper around [`std::os::unix::fs::chown`] that echoes the operation to
+/// the console as `chown path uid:gid`, with `-` for an ID that is left unchanged.
+/// Unix only.
+#[cfg(unix)]
+pub fn chown(path: impl AsRef<Path>, uid: Option<u32>, gid: Option<u32>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let id = |id: Option<u32>| id.map_or_else(|| "-".to_string(), |id| id.to_string());
+    echo_operation(
+        "chown",
+        &format!("{} {}:{}", path.display(), id(uid), id(gid)),
+    );
+    std::os::unix::fs::chown(path, uid, gid)
+}
+
 /// Copy the contents of one file to another.
 ///
 /// This is a wrapper around [`std::fs::copy`] that echoes the operation to the console.
@@ -35,6 +88,137 @@ pub fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> std::io::Result<u64
     std::fs::copy(from, to)
 }
 
+/// Size of the chunks [`copy_with_progress`] copies between progress reports.
+const COPY_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Copy the contents of one file to another, reporting the progress along the way.
+///
+/// Like [`copy`], but the file is streamed in chunks of 256 KiB and `progress` is called
+/// with the number of bytes copied so far and the size of `from`, once before the first
+/// chunk and after every chunk. The size is `None` if `from` is not a regular file,
+/// e.g. a pipe. The permissions of `from` are copied to `to`, as with [`copy`].
+/// Returns the number of bytes copied.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::fs;
+///
+/// fs::copy_with_progress("disk.img", "/mnt/backup/disk.img", |copied, total| {
+///     if let Some(total) = total {
+///         eprint!("\r{}%", copied * 100 / total.max(1));
+///     }
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn copy_with_progress(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> std::io::Result<u64> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+    echo_operation("copy", &format!("{} -> {}", from.display(), to.display()));
+
+    let mut reader = std::fs::File::open(from)?;
+    let metadata = reader.metadata()?;
+    let total = metadata.is_file().then_some(metadata.len());
+    let mut writer = std::fs::File::create(to)?;
+
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    let mut copied = 0;
+    progress(copied, total);
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+        progress(copied, total);
+    }
+    writer.set_permissions(metadata.permissions())?;
+    Ok(copied)
+}
+
+/// Recursively copy a directory and all of its contents, returning the number of bytes copied.
+///
+/// The directory tree under `src` is recreated under `dst`, which is created if it does
+/// not exist; files already present in `dst` are overwritten. Symbolic links are copied
+/// as links rather than followed. Fails if `dst` exists but is not a directory, and must
+/// not be used to copy a directory into itself.
+///
+/// Echoes the operation to the console, and each copied file as well when the
+/// `SCRIPTY_VERBOSE` environment variable is set.
+pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<u64> {
+    let src = src.as_ref();
+    let dst = dst.as_ref();
+    echo_operation(
+        "copy_dir_all",
+        &format!("{} -> {}", src.display(), dst.display()),
+    );
+
+    if dst.exists() && !dst.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("destination '{}' is not a directory", dst.display()),
+        ));
+    }
+    copy_dir_contents(src, dst)
+}
+
+/// Create a symlink at `to` pointing to `target`, like the one at `from`.
+#[cfg(unix)]
+fn copy_symlink(target: &Path, _from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, to)
+}
+
+/// Create a symlink at `to` pointing to `target`, like the one at `from`.
+///
+/// Windows has distinct links to files and to directories, so the kind is taken from
+/// what `from` points to.
+#[cfg(windows)]
+fn copy_symlink(target: &Path, from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.metadata().is_ok_and(|meta| meta.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+fn copy_dir_contents(src: &Path, dst: &Path) -> std::io::Result<u64> {
+    std::fs::create_dir_all(dst)?;
+
+    let mut total = 0;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+
+        if file_type.is_dir() {
+            total += copy_dir_contents(&from, &to)?;
+        } else if file_type.is_symlink() {
+            let target = std::fs::read_link(&from)?;
+            if should_echo_verbose() {
+                echo_operation(
+                    "symlink",
+                    &format!("{} -> {}", to.display(), target.display()),
+                );
+            }
+            copy_symlink(&target, &from, &to)?;
+        } else {
+            if should_echo_verbose() {
+                echo_operation("copy", &format!("{} -> {}", from.display(), to.display()));
+            }
+            total += std::fs::copy(&from, &to)?;
+        }
+    }
+    Ok(total)
+}
+
 /// Create a new, empty directory at the provided path.
 ///
 /// This is a wrapper around [`std::fs::create_dir`] that echoes the operation to the console.
@@ -53,6 +237,98 @@ pub fn create_dir_all(path: impl AsRef<Path>) -> std::io::Result<()> {
     std::fs::create_dir_all(path)
 }
 
+/// Create a fresh, uniquely named directory in the system's temporary directory.
+///
+/// The returned [`TempDir`] removes the directory and everything in it when dropped,
+/// so a scratch directory is cleaned up even when the commands run in it fail. The
+/// directory is only accessible to the current user on Unix. Echoes the operation
+/// to the console.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// let scratch = fs::create_temp_dir()?;
+/// cmd!("git", "clone", "https://github.com/MozkTaberenai/scripty").current_dir(&scratch).run()?;
+/// cmd!("cargo", "test").current_dir(scratch.path().join("scripty")).run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn create_temp_dir() -> std::io::Result<TempDir> {
+    let path = create_unique_temp(|path| {
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(path)
+    })?;
+    echo_operation("create_temp_dir", &path.display().to_string());
+    Ok(TempDir { path })
+}
+
+/// Create a uniquely named entry in the system's temporary directory with `create`,
+/// which must fail with `AlreadyExists` if the path is taken.
+fn create_unique_temp(
+    create: impl Fn(&Path) -> std::io::Result<()>,
+) -> std::io::Result<std::path::PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let base = std::env::temp_dir();
+    loop {
+        let name = format!(
+            "{}-{}-{}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = base.join(name);
+        match create(&path) {
+            Ok(()) => return Ok(path),
+            // A leftover of a process that had the same ID
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A temporary directory that is removed, with its contents, when dropped.
+///
+/// Created with [`create_temp_dir`]. Pass it wherever a path is expected, e.g. to
+/// [`Cmd::current_dir`](crate::Cmd::current_dir). Errors while removing it are ignored.
+#[derive(Debug)]
+pub struct TempDir {
+    path: std::path::PathBuf,
+}
+
+impl TempDir {
+    /// The path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        echo_operation("remove_dir_all", &self.path.display().to_string());
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Returns `true` if the path points at an existing entity, following symlinks.
+///
+/// Like [`Path::exists`], but the check is echoed to the console along with the other
+/// file system operations. Since checks are frequent, they are only echoed when
+/// `SCRIPTY_VERBOSE` is set. Any error, e.g. permission denied, counts as `false`; use
+/// [`metadata`] to tell errors apart.
+pub fn exists(path: impl AsRef<Path>) -> bool {
+    probe("exists", path.as_ref(), |_| true)
+}
+
 /// Create a new hard link to a file.
 ///
 /// This is a wrapper around [`std::fs::hard_link`] that echoes the operation to the console.
@@ -66,6 +342,66 @@ pub fn hard_link(original: impl AsRef<Path>, link: impl AsRef<Path>) -> std::io:
     std::fs::hard_link(original, link)
 }
 
+/// Size of the buffer [`hash_file`] streams the file through.
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Stream a file into the given hasher and return the hasher.
+///
+/// The file is written to `hasher` in chunks of 64 KiB, so it is never loaded into memory
+/// as a whole. Any [`Write`] implementation can be used, such as `sha2::Sha256` from the
+/// RustCrypto crates: the hasher is handed back so the caller can finalize it. scripty
+/// does not ship hash algorithms itself, which keeps cryptographic dependencies out of
+/// the crate.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::fs;
+/// use sha2::{Digest, Sha256};
+///
+/// let digest = fs::hash_file("release.tar.gz", Sha256::new())?.finalize();
+/// println!("{digest:x}  release.tar.gz");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn hash_file<H: Write>(path: impl AsRef<Path>, mut hasher: H) -> std::io::Result<H> {
+    let path = path.as_ref();
+    echo_operation("hash_file", &path.display().to_string());
+
+    let mut file = std::fs::File::open(path)?;
+    let mut buf = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => hasher.write_all(&buf[..n])?,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hasher)
+}
+
+/// Returns `true` if the path exists and is a directory, following symlinks.
+///
+/// See [`exists`] for how the check is echoed and how errors are handled.
+pub fn is_dir(path: impl AsRef<Path>) -> bool {
+    probe("is_dir", path.as_ref(), std::fs::Metadata::is_dir)
+}
+
+/// Returns `true` if the path exists and is a regular file, following symlinks.
+///
+/// See [`exists`] for how the check is echoed and how errors are handled.
+pub fn is_file(path: impl AsRef<Path>) -> bool {
+    probe("is_file", path.as_ref(), std::fs::Metadata::is_file)
+}
+
+/// Echo a check on `path` in verbose mode and answer it from the path's metadata.
+fn probe(op: &str, path: &Path, check: impl FnOnce(&std::fs::Metadata) -> bool) -> bool {
+    if should_echo_verbose() {
+        echo_operation(op, &path.display().to_string());
+    }
+    std::fs::metadata(path).is_ok_and(|metadata| check(&metadata))
+}
+
 /// Given a path, query the file system to get information about a file, directory, etc.
 ///
 /// This is a wrapper around [`std::fs::metadata`] that echoes the operation to the console.
@@ -93,6 +429,146 @@ pub fn read_dir(path: impl AsRef<Path>) -> std::io::Result<std::fs::ReadDir> {
     std::fs::read_dir(path)
 }
 
+/// Returns the entries of a directory whose extension is `ext`, sorted by file name.
+///
+/// `ext` is compared without the leading dot, so `"rs"` matches `main.rs` but not
+/// `Makefile` or `.rs`. Like [`read_dir_sorted`], the whole directory is read at once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::fs;
+///
+/// for entry in fs::read_dir_ext("migrations", "sql")? {
+///     println!("{}", entry.path().display());
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read_dir_ext(
+    path: impl AsRef<Path>,
+    ext: impl AsRef<std::ffi::OsStr>,
+) -> std::io::Result<Vec<std::fs::DirEntry>> {
+    let path = path.as_ref();
+    let ext = ext.as_ref();
+    echo_operation(
+        "read_dir_ext",
+        &format!("{} *.{}", path.display(), ext.to_string_lossy()),
+    );
+    let mut entries = collect_sorted(path)?;
+    entries.retain(|entry| Path::new(&entry.file_name()).extension() == Some(ext));
+    Ok(entries)
+}
+
+/// Returns an iterator over every entry beneath a directory, walking it depth-first.
+///
+/// Each directory is yielded before its contents, in the order [`std::fs::read_dir`]
+/// returns them; the root itself is not yielded. Only the root is echoed to the console.
+///
+/// Symbolic links are yielded but never followed, even when they point to a directory,
+/// so link cycles cannot make the walk loop forever. Errors reading a subdirectory are
+/// yielded in place of its contents and the walk continues with the next entry.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::fs;
+///
+/// for entry in fs::read_dir_recursive("src")? {
+///     let entry = entry?;
+///     if entry.path().extension().is_some_and(|ext| ext == "rs") {
+///         println!("{}", entry.path().display());
+///     }
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read_dir_recursive(
+    path: impl AsRef<Path>,
+) -> std::io::Result<impl Iterator<Item = std::io::Result<std::fs::DirEntry>>> {
+    let path = path.as_ref();
+    echo_operation("read_dir_recursive", &path.display().to_string());
+    Ok(WalkDir {
+        stack: vec![std::fs::read_dir(path)?],
+        descend: None,
+    })
+}
+
+/// Depth-first walk behind [`read_dir_recursive`].
+struct WalkDir {
+    /// Open directories, from the root down to the one being read.
+    stack: Vec<std::fs::ReadDir>,
+    /// Directory yielded last, to be opened before reading on.
+    descend: Option<std::path::PathBuf>,
+}
+
+impl Iterator for WalkDir {
+    type Item = std::io::Result<std::fs::DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(dir) = self.descend.take() {
+            match std::fs::read_dir(dir) {
+                Ok(entries) => self.stack.push(entries),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        loop {
+            let entry = match self.stack.last_mut()?.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            // `file_type` does not follow symlinks
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => self.descend = Some(entry.path()),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+/// Returns the entries of a directory sorted by file name.
+///
+/// Unlike [`read_dir`], which streams entries in whatever order the OS returns
+/// them, this reads the whole directory up front so that the order is the same on
+/// every run. Prefer [`read_dir`] for very large directories.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::fs;
+///
+/// for entry in fs::read_dir_sorted("snapshots")? {
+///     println!("{}", entry.file_name().to_string_lossy());
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read_dir_sorted(path: impl AsRef<Path>) -> std::io::Result<Vec<std::fs::DirEntry>> {
+    let path = path.as_ref();
+    echo_operation("read_dir_sorted", &path.display().to_string());
+    collect_sorted(path)
+}
+
+/// Read every entry of a directory and sort them by file name.
+fn collect_sorted(path: &Path) -> std::io::Result<Vec<std::fs::DirEntry>> {
+    let mut entries = std::fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+    Ok(entries)
+}
+
+/// Read the target of a symbolic link.
+///
+/// This is a wrapper around [`std::fs::read_link`] that echoes the operation to the console.
+pub fn read_link(path: impl AsRef<Path>) -> std::io::Result<std::path::PathBuf> {
+    let path = path.as_ref();
+    echo_operation("read_link", &path.display().to_string());
+    std::fs::read_link(path)
+}
+
 /// Read the entire contents of a file into a string.
 ///
 /// This is a wrapper around [`std::fs::read_to_string`] that echoes the operation to the console.
@@ -120,6 +596,35 @@ pub fn remove_dir_all(path: impl AsRef<Path>) -> std::io::Result<()> {
     std::fs::remove_dir_all(path)
 }
 
+/// Removes a directory and all its contents if it exists, for idempotent cleanup.
+///
+/// Returns `Ok(true)` if the directory was removed and `Ok(false)` if there was
+/// nothing to remove. Other failures, such as permission denied, are still errors.
+/// Echoes the operation to the console, noting when the directory was absent.
+pub fn remove_dir_all_if_exists(path: impl AsRef<Path>) -> std::io::Result<bool> {
+    let path = path.as_ref();
+    let result = unless_absent(std::fs::remove_dir_all(path));
+    echo_removal("remove_dir_all_if_exists", path, &result);
+    result
+}
+
+/// Turn the result of a removal into whether something was removed, with a missing
+/// path counting as nothing removed rather than as an error.
+fn unless_absent(result: std::io::Result<()>) -> std::io::Result<bool> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn echo_removal(op: &str, path: &Path, result: &std::io::Result<bool>) {
+    match result {
+        Ok(false) => echo_operation(op, &format!("{} (absent)", path.display())),
+        _ => echo_operation(op, &path.display().to_string()),
+    }
+}
+
 /// Removes a file from the filesystem.
 ///
 /// This is a wrapper around [`std::fs::remove_file`] that echoes the operation to the console.
@@ -129,6 +634,18 @@ pub fn remove_file(path: impl AsRef<Path>) -> std::io::Result<()> {
     std::fs::remove_file(path)
 }
 
+/// Removes a file if it exists, for idempotent cleanup.
+///
+/// Returns `Ok(true)` if the file was removed and `Ok(false)` if there was nothing to
+/// remove. Other failures, such as permission denied, are still errors. Echoes the
+/// operation to the console, noting when the file was absent.
+pub fn remove_file_if_exists(path: impl AsRef<Path>) -> std::io::Result<bool> {
+    let path = path.as_ref();
+    let result = unless_absent(std::fs::remove_file(path));
+    echo_removal("remove_file_if_exists", path, &result);
+    result
+}
+
 /// Rename a file or directory to a new name, replacing the original file if `to` already exists.
 ///
 /// This is a wrapper around [`std::fs::rename`] that echoes the operation to the console.
@@ -139,6 +656,19 @@ pub fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> std::io::Result<(
     std::fs::rename(from, to)
 }
 
+/// Truncate or extend an existing file to `size` bytes, like `truncate -s size path`.
+///
+/// Extending fills the file with zeros. This opens the file for writing and calls
+/// [`File::set_len`](std::fs::File::set_len), echoing the operation to the console.
+pub fn set_len(path: impl AsRef<Path>, size: u64) -> std::io::Result<()> {
+    let path = path.as_ref();
+    echo_operation("set_len", &format!("{} {} bytes", path.display(), size));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .set_len(size)
+}
+
 /// Changes the permissions found on a file or a directory.
 ///
 /// This is a wrapper around [`std::fs::set_permissions`] that echoes the operation to the console.
@@ -148,6 +678,33 @@ pub fn set_permissions(path: impl AsRef<Path>, perm: std::fs::Permissions) -> st
     std::fs::set_permissions(path, perm)
 }
 
+/// Create a symbolic link at `link` pointing to `original`.
+///
+/// This is a wrapper around [`std::os::unix::fs::symlink`] that echoes the operation to
+/// the console as `symlink link -> original`, like `ls -l` shows it. `original` is stored
+/// as given, so a relative one is resolved against the directory of `link`. Unix only.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::fs;
+///
+/// // Switch the `current` release, as in `ln -s releases/42 current`
+/// fs::remove_file_if_exists("current")?;
+/// fs::symlink("releases/42", "current")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(unix)]
+pub fn symlink(original: impl AsRef<Path>, link: impl AsRef<Path>) -> std::io::Result<()> {
+    let original = original.as_ref();
+    let link = link.as_ref();
+    echo_operation(
+        "symlink",
+        &format!("{} -> {}", link.display(), original.display()),
+    );
+    std::os::unix::fs::symlink(original, link)
+}
+
 /// Query the metadata about a file without following symlinks.
 ///
 /// This is a wrapper around [`std::fs::symlink_metadata`] that echoes the operation to the console.
@@ -169,3 +726,393 @@ pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> std::io::Res
     );
     std::fs::write(path, contents)
 }
+
+/// Run `f` with the path of a fresh, empty temporary directory, and remove the
+/// directory with everything in it afterwards.
+///
+/// The directory is removed whether `f` succeeds, fails or panics; see
+/// [`create_temp_dir`] for a directory that outlives a single closure. Echoes the
+/// creation and removal to the console.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// let files = fs::with_temp_dir(|dir| -> Result<String> {
+///     cmd!("tar", "-xzf", "/tmp/release.tar.gz", "-C", dir).run()?;
+///     Ok(cmd!("ls", dir).output()?)
+/// })?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn with_temp_dir<R, E: From<std::io::Error>>(
+    f: impl FnOnce(&Path) -> Result<R, E>,
+) -> Result<R, E> {
+    let dir = create_temp_dir()?;
+    f(dir.path())
+}
+
+/// Run `f` with the path of a fresh, empty temporary file, and remove the file
+/// afterwards.
+///
+/// The file has a unique name in the system's temporary directory and is only
+/// accessible to the current user on Unix. It is removed whether `f` succeeds, fails
+/// or panics. Echoes the creation and removal to the console.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// let sorted = fs::with_temp_file(|path| -> Result<String> {
+///     cmd!("curl", "-o", path, "https://example.com/names.txt").run()?;
+///     Ok(cmd!("sort", path).output()?)
+/// })?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn with_temp_file<R, E: From<std::io::Error>>(
+    f: impl FnOnce(&Path) -> Result<R, E>,
+) -> Result<R, E> {
+    /// Removes the file, also when `f` panics.
+    struct Remove(std::path::PathBuf);
+
+    impl Drop for Remove {
+        fn drop(&mut self) {
+            echo_operation("remove_file", &self.0.display().to_string());
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    let path = create_unique_temp(|path| {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(path).map(drop)
+    })?;
+    echo_operation("create_temp_file", &path.display().to_string());
+    let file = Remove(path);
+    f(&file.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_all() {
+        let temp = create_temp_dir().unwrap();
+        let base = temp.path();
+        let src = base.join("src");
+        let dst = base.join("dst");
+        std::fs::create_dir_all(src.join("nested/deeper")).unwrap();
+        std::fs::write(src.join("top.txt"), "hello").unwrap();
+        std::fs::write(src.join("nested/deeper/file.bin"), [0u8; 100]).unwrap();
+        std::os::unix::fs::symlink("top.txt", src.join("link")).unwrap();
+
+        let bytes = copy_dir_all(&src, &dst).unwrap();
+        assert_eq!(bytes, 105);
+        assert_eq!(
+            std::fs::read_to_string(dst.join("top.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            std::fs::read(dst.join("nested/deeper/file.bin"))
+                .unwrap()
+                .len(),
+            100
+        );
+        assert_eq!(
+            std::fs::read_link(dst.join("link")).unwrap(),
+            Path::new("top.txt")
+        );
+
+        // A file in place of the destination directory is an error
+        let file = base.join("file");
+        std::fs::write(&file, "").unwrap();
+        let error = copy_dir_all(&src, &file).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_dir_recursive() {
+        let temp = create_temp_dir().unwrap();
+        let base = temp.path().join("walk");
+        std::fs::create_dir_all(base.join("a/b")).unwrap();
+        std::fs::create_dir_all(base.join("c")).unwrap();
+        std::fs::write(base.join("a/b/file.txt"), "").unwrap();
+        std::fs::write(base.join("top.txt"), "").unwrap();
+        // A link back to the root would loop forever if it were followed
+        std::os::unix::fs::symlink(&base, base.join("c/loop")).unwrap();
+
+        let mut paths: Vec<_> = read_dir_recursive(&base)
+            .unwrap()
+            .map(|entry| {
+                entry
+                    .unwrap()
+                    .path()
+                    .strip_prefix(&base)
+                    .unwrap()
+                    .to_path_buf()
+            })
+            .collect();
+        paths.sort();
+        let expected = ["a", "a/b", "a/b/file.txt", "c", "c/loop", "top.txt"];
+        assert_eq!(paths, expected.map(std::path::PathBuf::from));
+
+        std::fs::remove_dir_all(&base).unwrap();
+        assert!(read_dir_recursive(&base).is_err());
+    }
+
+    #[test]
+    fn test_read_dir_sorted_and_ext() {
+        let temp = create_temp_dir().unwrap();
+        let base = temp.path().join("sorted");
+        std::fs::create_dir_all(base.join("d.rs")).unwrap();
+        for name in ["c.txt", "a.rs", "b.RS", "e.rs", "Makefile", ".rs"] {
+            std::fs::write(base.join(name), "").unwrap();
+        }
+
+        let names = |entries: Vec<std::fs::DirEntry>| -> Vec<String> {
+            entries
+                .iter()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            names(read_dir_sorted(&base).unwrap()),
+            [".rs", "Makefile", "a.rs", "b.RS", "c.txt", "d.rs", "e.rs"]
+        );
+        assert_eq!(
+            names(read_dir_ext(&base, "rs").unwrap()),
+            ["a.rs", "d.rs", "e.rs"]
+        );
+
+        std::fs::remove_dir_all(&base).unwrap();
+        assert!(read_dir_sorted(&base).is_err());
+        assert!(read_dir_ext(&base, "rs").is_err());
+    }
+
+    #[test]
+    fn test_create_temp_dir() {
+        let first = create_temp_dir().unwrap();
+        let second = create_temp_dir().unwrap();
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().is_dir());
+
+        std::fs::create_dir(first.path().join("nested")).unwrap();
+        std::fs::write(first.path().join("nested/file.txt"), "data").unwrap();
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().is_dir());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        with_temp_dir(|dir| {
+            std::fs::create_dir(dir.join("sub"))?;
+            std::fs::write(dir.join("file.txt"), "data")?;
+
+            let resolved = canonicalize(dir.join("sub/../file.txt"))?;
+            assert!(resolved.is_absolute());
+            assert_eq!(resolved, std::fs::canonicalize(dir.join("file.txt"))?);
+
+            // The OS error is passed on unchanged
+            let error = canonicalize(dir.join("missing")).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+            assert_eq!(
+                error.raw_os_error(),
+                std::fs::canonicalize(dir.join("missing"))
+                    .unwrap_err()
+                    .raw_os_error()
+            );
+            std::io::Result::Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_set_len() {
+        with_temp_file(|path| {
+            std::fs::write(path, "hello world")?;
+            set_len(path, 5)?;
+            assert_eq!(std::fs::read(path)?, b"hello");
+            set_len(path, 8)?;
+            assert_eq!(std::fs::read(path)?, b"hello\0\0\0");
+            std::io::Result::Ok(())
+        })
+        .unwrap();
+        assert!(set_len("/nonexistent/file", 0).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_chown() {
+        with_temp_file(|path| {
+            // Leaving both IDs unchanged is allowed for the owner
+            chown(path, None, None)?;
+            let metadata = std::fs::metadata(path)?;
+            use std::os::unix::fs::MetadataExt;
+            chown(path, Some(metadata.uid()), Some(metadata.gid()))?;
+            std::io::Result::Ok(())
+        })
+        .unwrap();
+        assert!(chown("/nonexistent/file", None, None).is_err());
+    }
+
+    #[test]
+    fn test_with_temp_file_and_dir() {
+        let (path, contents) = with_temp_file(|path| {
+            assert_eq!(std::fs::read(path)?, b"");
+            std::fs::write(path, "data")?;
+            std::io::Result::Ok((path.to_path_buf(), std::fs::read_to_string(path)?))
+        })
+        .unwrap();
+        assert_eq!(contents, "data");
+        assert!(!path.exists());
+
+        // Removed on error too
+        let mut kept = None;
+        let result: std::io::Result<()> = with_temp_file(|path| {
+            kept = Some(path.to_path_buf());
+            Err(std::io::Error::other("failed"))
+        });
+        assert!(result.is_err());
+        assert!(!kept.unwrap().exists());
+
+        let mut kept = None;
+        let result: crate::Result<()> = with_temp_dir(|dir| {
+            kept = Some(dir.to_path_buf());
+            std::fs::write(dir.join("file.txt"), "data")?;
+            crate::cmd!("false").no_echo().run()?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(!kept.unwrap().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_and_read_link() {
+        let temp = create_temp_dir().unwrap();
+        let base = temp.path();
+        std::fs::create_dir_all(base.join("releases/42")).unwrap();
+        std::fs::write(base.join("releases/42/VERSION"), "42").unwrap();
+
+        symlink("releases/42", base.join("current")).unwrap();
+        assert_eq!(
+            read_link(base.join("current")).unwrap(),
+            Path::new("releases/42")
+        );
+        assert_eq!(
+            std::fs::read_to_string(base.join("current/VERSION")).unwrap(),
+            "42"
+        );
+
+        let error = symlink("releases/43", base.join("current")).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(read_link(base.join("releases")).is_err());
+    }
+
+    #[test]
+    fn test_append() {
+        let temp = create_temp_dir().unwrap();
+        let path = temp.path().join("append.txt");
+
+        // Creates the file on first use
+        append(&path, "first\n").unwrap();
+        append(&path, b"second\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    }
+
+    #[test]
+    fn test_exists_is_file_is_dir() {
+        let temp = create_temp_dir().unwrap();
+        let dir = temp.path();
+        let file = dir.join("file.txt");
+        std::fs::write(&file, "x").unwrap();
+
+        assert!(exists(dir) && is_dir(dir) && !is_file(dir));
+        assert!(exists(&file) && is_file(&file) && !is_dir(&file));
+
+        let missing = dir.join("missing");
+        assert!(!exists(&missing) && !is_file(&missing) && !is_dir(&missing));
+        // A path below a file is an error other than not found, still reported as false
+        assert!(!exists(file.join("below")));
+    }
+
+    #[test]
+    fn test_remove_if_exists() {
+        let temp = create_temp_dir().unwrap();
+        let dir = temp.path().join("dir");
+        let file = dir.join("file.txt");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(&file, "x").unwrap();
+
+        assert!(remove_file_if_exists(&file).unwrap());
+        assert!(!file.exists());
+        assert!(!remove_file_if_exists(&file).unwrap());
+        // Not being a file is a genuine failure
+        assert!(remove_file_if_exists(&dir).is_err());
+
+        let (removed, echo) = crate::capture_echo(|| remove_dir_all_if_exists(&dir).unwrap());
+        assert!(removed);
+        assert!(!dir.exists());
+        assert!(!echo.contains("(absent)"));
+        let (removed, echo) = crate::capture_echo(|| remove_dir_all_if_exists(&dir).unwrap());
+        assert!(!removed);
+        assert!(echo.contains("(absent)"), "{echo}");
+    }
+
+    #[test]
+    fn test_hash_file() {
+        use sha2::{Digest, Sha256};
+
+        let temp = create_temp_dir().unwrap();
+        let path = temp.path().join("file");
+
+        std::fs::write(&path, "foobar").unwrap();
+        let digest = hash_file(&path, Sha256::new()).unwrap().finalize();
+        assert_eq!(
+            format!("{digest:x}"),
+            "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2"
+        );
+
+        // Files larger than the buffer hash the same as hashing all bytes at once
+        let contents: Vec<u8> = (0..HASH_BUFFER_SIZE * 2 + 7).map(|i| i as u8).collect();
+        std::fs::write(&path, &contents).unwrap();
+        let digest = hash_file(&path, Sha256::new()).unwrap().finalize();
+        assert_eq!(digest, Sha256::digest(&contents));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(hash_file(&path, Sha256::new()).is_err());
+    }
+
+    #[test]
+    fn test_copy_with_progress() {
+        let temp = create_temp_dir().unwrap();
+        let src = temp.path().join("src");
+        let dst = temp.path().join("dst");
+        let contents: Vec<u8> = (0..COPY_BUFFER_SIZE * 2 + 7).map(|i| i as u8).collect();
+        std::fs::write(&src, &contents).unwrap();
+
+        let mut reports = Vec::new();
+        let bytes =
+            copy_with_progress(&src, &dst, |copied, total| reports.push((copied, total))).unwrap();
+        assert_eq!(bytes, contents.len() as u64);
+        assert_eq!(std::fs::read(&dst).unwrap(), contents);
+
+        let total = Some(contents.len() as u64);
+        assert_eq!(reports.first(), Some(&(0, total)));
+        assert_eq!(reports.last(), Some(&(bytes, total)));
+        assert!(reports.len() >= 4);
+        assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+        std::fs::remove_file(&src).unwrap();
+        std::fs::remove_file(&dst).unwrap();
+        assert!(copy_with_progress(&src, &dst, |_, _| {}).is_err());
+    }
+}
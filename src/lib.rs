@@ -15,7 +15,7 @@
 //! - **🔗 Easy piping**: Chain commands together naturally with stdout, stderr, or both
 //! - **📁 File operations**: Wrapper around `std::fs` with automatic logging
 //! - **🔧 Builder pattern**: Fluent API for command construction
-//! - **⚡ Minimal dependencies**: Only uses `anstyle` for colors
+//! - **⚡ Minimal dependencies**: Only uses `anstyle` for colors and `libc` for signals
 //! - **🛡️ Type safe**: All the safety of Rust with the convenience of shell scripts
 //! - **🚰 Streaming I/O**: Efficient handling of large data with readers and writers
 //! - **🔌 Reader Extensions**: Fluent piping from any `Read` implementation to commands
@@ -35,6 +35,8 @@
 //! Currently supported platforms:
 //! - **Linux** ✅ Full support with native pipe optimization
 //! - **macOS** ✅ Full support with native pipe optimization
+//! - **Windows** ⚠️ Core command execution, piping and capturing; timeouts kill the process
+//!   right away, and Unix-only features such as `umask()` are ignored
 //!
 //! Scripty is designed for Unix-like systems and uses Unix shell commands and utilities.
 //!
@@ -59,6 +61,10 @@
 //! // Command with multiple arguments
 //! cmd!("ls", "-la", "/tmp").run()?;
 //!
+//! // Arguments built at run time
+//! let files = vec!["a.txt", "b.txt"];
+//! Cmd::new("wc").arg("-l").args(&files).run()?;
+//!
 //! // Using the builder pattern
 //! cmd!("grep", "error")
 //!     .arg("logfile.txt")
@@ -102,6 +108,7 @@
 //!
 //! ```no_run
 //! use scripty::*;
+//! use std::io::{BufRead, Write};
 //!
 //! // Simple pipe (stdout)
 //! cmd!("echo", "hello world")
@@ -130,6 +137,18 @@
 //!     .pipe(cmd!("wc", "-l"))
 //!     .output()?;
 //! println!("Rust processes: {}", result.trim());
+//!
+//! // Rust code between two commands, instead of a throwaway awk or sed script
+//! let names = cmd!("cat", "/etc/passwd")
+//!     .pipe_fn(|input, output| {
+//!         for line in input.lines() {
+//!             let line = line?;
+//!             writeln!(output, "{}", line.split(':').next().unwrap_or_default())?;
+//!         }
+//!         Ok(())
+//!     })
+//!     .pipe(cmd!("sort"))
+//!     .output()?;
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
@@ -153,6 +172,10 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
+//! With the `flate2` feature, `.gzip()` and `.gunzip()` compress and decompress a stream in-process
+//! as a pipeline stage, without needing the external binaries. `.gzip_level(n)` picks a
+//! compression level from 0 to 9; `.gzip()` uses 6 like `gzip` does.
+//!
 //! ### Core API Reference
 //!
 //! #### The `cmd!` Macro
@@ -190,6 +213,14 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
+//! Interactive programs such as `vim` or `fzf` read the keyboard only after `.stdin_inherit()`:
+//! by default a command's stdin is a pipe that is closed when no input is given.
+//!
+//! Add `.combine_stderr_into_stdout()` to capture stderr together with stdout, in the order it
+//! was written, like `2>&1` in a shell.
+//! Commands run only for their side effects and exit code can drop their output with
+//! `.discard_stdout()` and `.discard_stderr()`, like `> /dev/null`, without buffering it in memory.
+//!
 //! #### Execution Methods
 //!
 //! Different ways to execute commands:
@@ -206,9 +237,22 @@
 //!
 //! // Capture binary output
 //! let bytes = cmd!("cat", "binary-file").output_bytes()?;
+//!
+//! // Capture output as a list of lines
+//! let files = cmd!("ls").output_lines()?;
+//!
+//! // Capture a single value without the trailing newline
+//! let commit = cmd!("git", "rev-parse", "HEAD").output_trimmed()?;
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
+//! With the `serde` feature, `output_json()` deserializes the output into any type implementing
+//! `DeserializeOwned`, reporting invalid output as `CmdError::Json` with the offending part.
+//!
+//! With the `tokio` feature, `run_async()`, `output_async()` and `output_bytes_async()` run commands
+//! and pipelines from async code on Tokio's blocking thread pool, keeping the runtime responsive.
+//! Dropping the future terminates the command.
+//!
 //! #### Output Streaming with Write Methods
 //!
 //! Stream command output directly to writers with precise control over stdout/stderr:
@@ -300,6 +344,21 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
+//! To start a background job such as a development server and stop it later, use
+//! `spawn()`. The job inherits the terminal's output:
+//!
+//! ```no_run
+//! use scripty::*;
+//!
+//! let server = cmd!("python3", "-m", "http.server", "8000").spawn()?;
+//! cmd!("curl", "--retry", "5", "--retry-connrefused", "http://localhost:8000/").run()?;
+//! server.kill();
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! On Unix, `signal()` sends any other signal to a running job, e.g. `signal::SIGHUP` to make
+//! it reload its configuration.
+//!
 //! #### Simple Reader-to-Writer Operations
 //!
 //! For straightforward input-to-output scenarios:
@@ -336,6 +395,7 @@
 //!
 //! // Basic file operations
 //! fs::write("config.txt", "debug=true\nport=8080")?;
+//! fs::append("config.txt", "\nverbose=false")?;
 //! let content = fs::read_to_string("config.txt")?;
 //! println!("Config: {}", content);
 //!
@@ -368,8 +428,19 @@
 //!     Err(e) => println!("Command failed: {}", e),
 //! }
 //!
+//! // Tell a missing program apart from a failing one
+//! match cmd!("git", "status").output() {
+//!     Ok(status) => println!("{}", status),
+//!     Err(CmdError::Spawn { .. }) => println!("git is not installed"),
+//!     Err(CmdError::NonZeroExit { status, .. }) => println!("git failed: {}", status),
+//!     Err(e) => return Err(e.into()),
+//! }
+//!
+//! // Fail fast when required tools are missing
+//! require_commands(&["cargo", "docker"])?;
+//!
 //! // Check command availability
-//! if cmd!("which", "git").no_echo().run().is_ok() {
+//! if which("git").is_some() {
 //!     println!("Git is available");
 //!     cmd!("git", "--version").run()?;
 //! }
@@ -390,12 +461,44 @@
 //! Control scripty's behavior with environment variables:
 //!
 //! - `NO_ECHO`: Set to any value to suppress command echoing globally
+//! - `NO_COLOR`: Set to a non-empty value to echo in plain text without colors; use
+//!   `color::set_enabled()` to choose programmatically, and `color::set_theme()` to change the
+//!   styling of the echo
+//! - `SCRIPTY_VERBOSE`: Set to any value to also echo the individual steps of bulk operations,
+//!   such as each file copied by `fs::copy_dir_all()`, and the working directory of every command
+//!   (`.verbose_echo()` does this for a single command)
 //!
 //! ```bash
 //! NO_ECHO=1 cargo run  # Run without command echoing
 //! ```
 //!
-//! Or use the `.no_echo()` method on individual commands.
+//! Or use the `.no_echo()` method on individual commands. Libraries that run commands internally
+//! can create them with `cmd_quiet!`, which takes the same arguments as `cmd!` but does not echo.
+//!
+//! To review a destructive script step by step, call `set_confirm_each(true)`: every command is
+//! then shown and run, skipped, or the script aborted depending on the answer read from stdin.
+//!
+//! To collect the echo of a section instead of printing it, wrap it in `capture_echo()`:
+//!
+//! ```no_run
+//! use scripty::*;
+//!
+//! let (result, echo) = capture_echo(|| cmd!("cargo", "build").run());
+//! println!("Executed:\n{}", echo);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! To echo to stdout or a log file instead of stderr, pass the writer to `set_echo_writer()`,
+//! e.g. `set_echo_writer(Box::new(std::io::stdout()))`. A single command can echo to a writer of
+//! its own with `.echo_to(writer)`.
+//!
+//! To send the echo through your application's logging instead of stderr, enable the `log`
+//! feature. Commands are then logged at info level with the target `scripty::cmd`, and file
+//! system operations at debug level with the target `scripty::fs`.
+//!
+//! With the `tracing` feature, every command runs inside a `cmd` span carrying its
+//! `program` and `args`, and its exit `status` once it has finished. The commands of a
+//! pipeline get their spans nested under a common `pipeline` span.
 //!
 //! ## Examples
 //!
@@ -533,8 +636,8 @@
 //! ```no_run
 //! use scripty::*;
 //!
-//! // Enable command echoing for debugging (set before running your program)
-//! // export SCRIPTY_DEBUG=1
+//! // Also echo the working directory of every command (set before running your program)
+//! // export SCRIPTY_VERBOSE=1
 //!
 //! // Commands are echoed by default unless .no_echo() is used
 //! cmd!("complex-command")
@@ -563,7 +666,7 @@
 //!
 //! - **Linux** ✅ Full support with native pipe optimization
 //! - **macOS** ✅ Full support with native pipe optimization
-//! - **Windows** ❌ Not supported (Unix-like systems only)
+//! - **Windows** ⚠️ Core features: `cmd!`, pipes, input and output capture and timeouts
 //!
 //! ## Contributing
 //!
@@ -583,15 +686,33 @@ pub use std::path::{Path, PathBuf};
 mod cmd;
 pub use cmd::*;
 
+mod env;
+pub use env::with_env;
+
+mod confirm;
+pub use confirm::set_confirm_each;
+
+pub mod error;
+pub use error::{CmdError, Error};
+
 pub mod fs;
 
+mod path;
+pub use path::{require_commands, which};
+
 mod io_ext;
 pub use io_ext::ReadExt;
 
 mod output;
+pub use output::{capture_echo, set_echo_writer};
 
 pub mod color;
 mod style;
 
+#[cfg(unix)]
+pub mod signal;
+
+mod trace;
+
 /// Result type with a boxed error for convenience
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
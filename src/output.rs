@@ -1,17 +1,209 @@
 //! Output utilities for scripty
 
+use crate::style::strip_ansi;
+use std::cell::RefCell;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
 /// Check if output should be echoed based on NO_ECHO environment variable
 pub(crate) fn should_echo() -> bool {
     std::env::var_os("NO_ECHO").is_none()
 }
 
-/// Print to stderr if echo is enabled
-pub(crate) fn conditional_eprintln(args: std::fmt::Arguments) {
+/// Check if detailed output should be echoed based on SCRIPTY_VERBOSE environment variable
+pub(crate) fn should_echo_verbose() -> bool {
+    should_echo() && std::env::var_os("SCRIPTY_VERBOSE").is_some()
+}
+
+/// Destination of the echo set with [`set_echo_writer`]; stderr when `None`.
+static ECHO_WRITER: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+thread_local! {
+    /// Buffer receiving echo output while inside [`capture_echo`] on this thread.
+    static ECHO_CAPTURE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// What an echoed line describes.
+///
+/// With the `log` feature, commands are logged at info level and file system
+/// operations at debug level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EchoKind {
+    Cmd,
+    Fs,
+}
+
+/// Print to the echo writer if echo is enabled
+pub(crate) fn conditional_eprintln(kind: EchoKind, args: std::fmt::Arguments) {
+    if should_echo() {
+        write_echo(kind, args);
+    }
+}
+
+/// Writer that the echo of a single command goes to, set with `echo_to()`.
+#[derive(Clone)]
+pub(crate) struct EchoWriter(pub(crate) Arc<Mutex<dyn Write + Send>>);
+
+impl EchoWriter {
+    pub(crate) fn new(writer: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(writer)))
+    }
+}
+
+impl std::fmt::Debug for EchoWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EchoWriter").field(&"<writer>").finish()
+    }
+}
+
+/// Print to `writer`, or like [`conditional_eprintln`] without one, if echo is enabled
+pub(crate) fn conditional_eprintln_to(
+    writer: Option<&EchoWriter>,
+    kind: EchoKind,
+    args: std::fmt::Arguments,
+) {
     if should_echo() {
-        eprintln!("{}", args);
+        write_echo_to(writer, kind, args);
+    }
+}
+
+/// Write one line of echo output to `writer`, or like [`write_echo`] without one.
+///
+/// A command's own writer takes precedence over [`capture_echo`], [`set_echo_writer`]
+/// and the `log` feature.
+pub(crate) fn write_echo_to(
+    writer: Option<&EchoWriter>,
+    kind: EchoKind,
+    args: std::fmt::Arguments,
+) {
+    match writer {
+        Some(EchoWriter(writer)) => {
+            let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = writeln!(writer, "{}", styled_line(args));
+            let _ = writer.flush();
+        }
+        None => write_echo(kind, args),
+    }
+}
+
+/// The echo line, with ANSI styling removed if colors are disabled.
+fn styled_line(args: std::fmt::Arguments) -> String {
+    if crate::color::is_enabled() {
+        args.to_string()
+    } else {
+        strip_ansi(&args.to_string())
     }
 }
 
+/// Write one line of echo output.
+///
+/// Goes to the current thread's [`capture_echo`] buffer if there is one, otherwise to the
+/// [`set_echo_writer`] writer or stderr, or to the `log` crate with the `log` feature.
+///
+/// ANSI styling is removed from captured and logged output, and from the writer when colors
+/// are disabled with [`crate::color::set_enabled`].
+pub(crate) fn write_echo(kind: EchoKind, args: std::fmt::Arguments) {
+    let captured = ECHO_CAPTURE.with_borrow_mut(|capture| match capture {
+        Some(buf) => {
+            buf.push_str(&strip_ansi(&args.to_string()));
+            buf.push('\n');
+            true
+        }
+        None => false,
+    });
+    if captured {
+        return;
+    }
+
+    #[cfg(feature = "log")]
+    {
+        let line = strip_ansi(&args.to_string());
+        let line = line.trim_start();
+        match kind {
+            EchoKind::Cmd => log::info!(target: "scripty::cmd", "{}", line),
+            EchoKind::Fs => log::debug!(target: "scripty::fs", "{}", line),
+        }
+    }
+
+    #[cfg(not(feature = "log"))]
+    {
+        let _ = kind;
+        let line = styled_line(args);
+        let mut writer = ECHO_WRITER.lock().unwrap_or_else(|e| e.into_inner());
+        match writer.as_mut() {
+            Some(writer) => {
+                let _ = writeln!(writer, "{}", line);
+                let _ = writer.flush();
+            }
+            None => eprintln!("{}", line),
+        }
+    }
+}
+
+/// Send scripty's echo output to `writer` instead of stderr.
+///
+/// Applies to all threads from now on, e.g. `Box::new(std::io::stdout())` when the
+/// tool's own output is on stderr, or a file for a log of the executed commands.
+/// Pass `Box::new(std::io::stderr())` to go back to the default. Colors are written
+/// as for stderr; turn them off with [`crate::color::set_enabled`] for plain text.
+///
+/// [`capture_echo`] still takes the echo of its closure, and with the `log` feature
+/// the echo goes to the logger and the writer is not used. A single command can echo
+/// elsewhere with [`Cmd::echo_to`](crate::Cmd::echo_to). Write errors are ignored.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// set_echo_writer(Box::new(std::io::stdout()));
+/// cmd!("echo", "hello").run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn set_echo_writer(writer: Box<dyn Write + Send>) {
+    *ECHO_WRITER.lock().unwrap_or_else(|e| e.into_inner()) = Some(writer);
+}
+
+/// Run `f` with scripty's echo output redirected to an in-memory buffer.
+///
+/// Returns the result of `f` together with everything scripty echoed while it ran,
+/// one line per command or file system operation, with ANSI styling removed. Useful
+/// for test assertions or for embedding the executed commands in a report.
+///
+/// The redirection is thread-local: only echo produced on the calling thread is
+/// captured, while other threads keep writing to stderr. Echo that is disabled, via
+/// `NO_ECHO` or `no_echo()`, is not captured either. Captures can be nested; the inner
+/// one takes the output produced inside it.
+///
+/// # Examples
+///
+/// ```
+/// use scripty::*;
+///
+/// let (output, echo) = capture_echo(|| cmd!("echo", "hello").output());
+/// assert_eq!(output?.trim(), "hello");
+/// if std::env::var_os("NO_ECHO").is_none() {
+///     assert!(echo.contains("echo hello"));
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn capture_echo<R>(f: impl FnOnce() -> R) -> (R, String) {
+    /// Restores the enclosing capture, also when `f` panics.
+    struct Restore(Option<String>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            ECHO_CAPTURE.with_borrow_mut(|capture| *capture = previous);
+        }
+    }
+
+    let _restore = Restore(ECHO_CAPTURE.with_borrow_mut(|capture| capture.replace(String::new())));
+    let result = f();
+    let captured = ECHO_CAPTURE.with_borrow_mut(|capture| capture.take().unwrap_or_default());
+    (result, captured)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,9 +253,159 @@ mod tests {
         }
     }
 
+    #[test]
+    #[serial]
+    fn test_capture_echo() {
+        let original = std::env::var_os("NO_ECHO");
+        unsafe {
+            std::env::remove_var("NO_ECHO");
+        }
+
+        let (result, echo) = capture_echo(|| {
+            crate::cmd!("echo", "captured").output().unwrap();
+            crate::cmd!("true").no_echo().run().unwrap();
+            42
+        });
+        assert_eq!(result, 42);
+        assert!(echo.contains("echo captured"));
+        assert!(!echo.contains("true"));
+        assert!(!echo.contains('\x1b'));
+
+        let (_, echo) = capture_echo(|| {
+            let _ = crate::cmd!("sort")
+                .input_file("/nonexistent/input.txt")
+                .run();
+        });
+        assert!(echo.contains("sort < /nonexistent/input.txt"));
+
+        // Nested captures take their own output and restore the outer one
+        let ((_, inner), outer) = capture_echo(|| {
+            conditional_eprintln(EchoKind::Fs, format_args!("outer before"));
+            let inner = capture_echo(|| conditional_eprintln(EchoKind::Fs, format_args!("inner")));
+            conditional_eprintln(EchoKind::Fs, format_args!("outer after"));
+            inner
+        });
+        assert_eq!(inner, "inner\n");
+        assert_eq!(outer, "outer before\nouter after\n");
+
+        unsafe {
+            if let Some(val) = original {
+                std::env::set_var("NO_ECHO", val);
+            }
+        }
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    #[serial]
+    fn test_echo_through_log() {
+        use std::sync::Mutex;
+
+        static RECORDS: Mutex<Vec<(log::Level, String, String)>> = Mutex::new(Vec::new());
+
+        struct TestLogger;
+
+        impl log::Log for TestLogger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                let entry = (
+                    record.level(),
+                    record.target().to_string(),
+                    record.args().to_string(),
+                );
+                RECORDS.lock().unwrap().push(entry);
+            }
+
+            fn flush(&self) {}
+        }
+
+        let original = std::env::var_os("NO_ECHO");
+        unsafe {
+            std::env::remove_var("NO_ECHO");
+        }
+
+        log::set_logger(&TestLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        crate::cmd!("echo", "logged").output().unwrap();
+        let temp = crate::fs::create_temp_dir().unwrap();
+        crate::fs::write(temp.path().join("log"), "").unwrap();
+
+        let records = RECORDS.lock().unwrap();
+        assert!(records.iter().any(|(level, target, message)| {
+            *level == log::Level::Info
+                && target == "scripty::cmd"
+                && message == "scripty:cmd echo logged"
+        }));
+        assert!(records.iter().any(|(level, target, message)| {
+            *level == log::Level::Debug && target == "scripty::fs" && message.contains("write")
+        }));
+        assert!(
+            records
+                .iter()
+                .all(|(_, _, message)| !message.contains('\x1b'))
+        );
+
+        unsafe {
+            if let Some(val) = original {
+                std::env::set_var("NO_ECHO", val);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "log"))]
+    #[test]
+    #[serial]
+    fn test_set_echo_writer() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone)]
+        struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let original = std::env::var_os("NO_ECHO");
+        unsafe {
+            std::env::remove_var("NO_ECHO");
+        }
+
+        let buf = SharedBuf(Arc::new(Mutex::new(Vec::new())));
+        set_echo_writer(Box::new(buf.clone()));
+        conditional_eprintln(EchoKind::Fs, format_args!("to writer"));
+        let (_, captured) = capture_echo(|| {
+            conditional_eprintln(EchoKind::Fs, format_args!("to capture"));
+        });
+        set_echo_writer(Box::new(std::io::stderr()));
+        conditional_eprintln(EchoKind::Fs, format_args!("to stderr"));
+
+        // Other tests may echo into the writer while it is set
+        let written = String::from_utf8_lossy(&buf.0.lock().unwrap()).into_owned();
+        assert!(written.lines().any(|line| line == "to writer"));
+        assert!(!written.contains("to capture"));
+        assert!(!written.contains("to stderr"));
+        assert_eq!(captured, "to capture\n");
+
+        unsafe {
+            if let Some(val) = original {
+                std::env::set_var("NO_ECHO", val);
+            }
+        }
+    }
+
     #[test]
     fn test_conditional_functions_compile() {
         // Test that the functions compile and don't panic
-        conditional_eprintln(format_args!("test"));
+        conditional_eprintln(EchoKind::Fs, format_args!("test"));
     }
 }
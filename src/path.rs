@@ -0,0 +1,209 @@
+//! Resolution of executables on `PATH`.
+
+use crate::color::Theme;
+use crate::error::{CmdError, Error};
+use crate::output::{EchoKind, conditional_eprintln, should_echo_verbose};
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Resolve `program` the way the OS would when spawning it.
+///
+/// A program containing a path separator is taken as a path to the executable
+/// itself; anything else is looked up in each directory of `PATH` in turn. On
+/// Windows, a program without an extension is also tried with each extension
+/// listed in `PATHEXT`.
+pub(crate) fn resolve(program: impl AsRef<OsStr>) -> Option<PathBuf> {
+    resolve_in(program.as_ref(), std::env::var_os("PATH").as_deref(), None)
+}
+
+/// Resolve `program` like [`resolve`], but searching `path` instead of `PATH` and
+/// taking a relative path to the executable from `dir` if given, as a child with
+/// that environment and working directory would.
+pub(crate) fn resolve_in(
+    program: &OsStr,
+    path: Option<&OsStr>,
+    dir: Option<&Path>,
+) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.as_os_str().is_empty() {
+        return None;
+    }
+    if program.components().count() > 1 || program.is_absolute() {
+        let program = match dir {
+            // `./foo` in `dir` is reported as `dir/foo`
+            Some(dir) => dir.join(program.strip_prefix(".").unwrap_or(program)),
+            None => program.to_path_buf(),
+        };
+        return candidates(program)
+            .into_iter()
+            .find(|candidate| is_executable(candidate));
+    }
+
+    std::env::split_paths(path?)
+        .flat_map(|dir| candidates(dir.join(program)))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Find the executable that running `program` would start.
+///
+/// A native `which`: each directory of `PATH` is searched in turn, and on Windows
+/// the extensions listed in `PATHEXT` are tried for a program without one. A program
+/// containing a path separator is checked as a path itself. Returns `None` if no
+/// executable is found. No process is spawned, so this works where a `which` command
+/// is not installed.
+///
+/// Like the probes in [`fs`](crate::fs), the lookup is only echoed when the
+/// `SCRIPTY_VERBOSE` environment variable is set.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// let compose = match which("docker-compose") {
+///     Some(_) => cmd!("docker-compose"),
+///     None => cmd!("docker", "compose"),
+/// };
+/// compose.arg("up").run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn which(program: impl AsRef<OsStr>) -> Option<PathBuf> {
+    let program = program.as_ref();
+    let resolved = resolve(program);
+    if should_echo_verbose() {
+        let Theme {
+            prefix,
+            program: program_style,
+            args,
+            ..
+        } = crate::color::theme();
+        let found = match &resolved {
+            Some(path) => format!("{args}{}{args:#}", path.display()),
+            None => "(not found)".to_string(),
+        };
+        conditional_eprintln(
+            EchoKind::Cmd,
+            format_args!(
+                " {prefix}{}:which{prefix:#} {program_style}{}{program_style:#} -> {}",
+                env!("CARGO_PKG_NAME"),
+                program.to_string_lossy(),
+                found
+            ),
+        );
+    }
+    resolved
+}
+
+/// Files that may hold the program at `path`.
+#[cfg(not(windows))]
+fn candidates(path: PathBuf) -> Vec<PathBuf> {
+    vec![path]
+}
+
+/// Files that may hold the program at `path`, trying the extensions of `PATHEXT`.
+#[cfg(windows)]
+fn candidates(path: PathBuf) -> Vec<PathBuf> {
+    if path.extension().is_some() {
+        return vec![path];
+    }
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+    extensions
+        .split(';')
+        .filter(|extension| !extension.is_empty())
+        .map(|extension| {
+            let mut candidate = path.clone().into_os_string();
+            candidate.push(extension);
+            PathBuf::from(candidate)
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+/// Without permission bits, any file found is taken to be executable.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Check that every program in `programs` can be found on `PATH`.
+///
+/// Call this at the start of a script to fail fast with one actionable error
+/// instead of failing halfway through. All missing programs are reported at
+/// once in [`CmdError::MissingCommands`].
+///
+/// Only the presence of an executable is checked; it is not run, so this says
+/// nothing about whether the program actually works.
+///
+/// # Examples
+///
+/// ```no_run
+/// use scripty::*;
+///
+/// require_commands(&["git", "docker", "jq"])?;
+/// cmd!("git", "pull").run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn require_commands<S: AsRef<OsStr>>(programs: &[S]) -> Result<(), Error> {
+    let missing: Vec<_> = programs
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|program| resolve(program).is_none())
+        .map(OsStr::to_os_string)
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(CmdError::MissingCommands { programs: missing })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert!(resolve("sh").is_some_and(|path| path.is_absolute()));
+        assert_eq!(resolve("/bin/sh"), Some(PathBuf::from("/bin/sh")));
+        assert_eq!(resolve("nonexistent_command_12345"), None);
+        assert_eq!(resolve(""), None);
+        // Directories and non-executable files are not programs
+        assert_eq!(resolve("/"), None);
+        assert_eq!(resolve("Cargo.toml"), None);
+    }
+
+    #[test]
+    fn test_which() {
+        assert_eq!(which("sh"), resolve("sh"));
+        assert!(which("sh").is_some_and(|path| path.ends_with("sh")));
+        assert_eq!(which("/bin/sh"), Some(PathBuf::from("/bin/sh")));
+        assert_eq!(which("nonexistent_command_12345"), None);
+    }
+
+    #[test]
+    fn test_require_commands() {
+        assert!(require_commands(&["sh", "echo"]).is_ok());
+        assert!(require_commands::<&str>(&[]).is_ok());
+
+        let error =
+            require_commands(&["sh", "missing_tool_a", "cat", "missing_tool_b"]).unwrap_err();
+        match &error {
+            CmdError::MissingCommands { programs } => {
+                assert_eq!(programs, &["missing_tool_a", "missing_tool_b"]);
+            }
+            other => panic!("expected a missing commands error, got {:?}", other),
+        }
+        assert_eq!(
+            error.to_string(),
+            "Required commands not found: missing_tool_a, missing_tool_b"
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! Signal numbers for [`PipelineHandle::signal`](crate::PipelineHandle::signal).
+//!
+//! Unix only. The values are those of the platform, so scripts can send signals
+//! without depending on `libc` themselves.
+
+/// Hangup; many daemons reload their configuration on it.
+pub const SIGHUP: i32 = libc::SIGHUP;
+/// Interrupt, as sent by Ctrl-C in a terminal.
+pub const SIGINT: i32 = libc::SIGINT;
+/// Quit, as sent by Ctrl-\ in a terminal.
+pub const SIGQUIT: i32 = libc::SIGQUIT;
+/// Kill; cannot be caught or ignored.
+pub const SIGKILL: i32 = libc::SIGKILL;
+/// User-defined signal 1.
+pub const SIGUSR1: i32 = libc::SIGUSR1;
+/// User-defined signal 2.
+pub const SIGUSR2: i32 = libc::SIGUSR2;
+/// Termination request, the polite way to stop a process.
+pub const SIGTERM: i32 = libc::SIGTERM;
+/// Continue a stopped process.
+pub const SIGCONT: i32 = libc::SIGCONT;
+/// Stop a process; cannot be caught or ignored.
+pub const SIGSTOP: i32 = libc::SIGSTOP;
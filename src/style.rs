@@ -39,3 +39,84 @@ const UNDERLINE_GREEN: Style = Style::new().underline().fg_color(color::GREEN);
 
 #[allow(dead_code)]
 const RESET: anstyle::Reset = anstyle::Reset;
+
+/// Remove ANSI escape sequences from `text`.
+///
+/// Handles CSI sequences (`ESC [ ... final`), OSC sequences terminated by `BEL` or
+/// `ESC \`, and the other escape sequences, `ESC x` optionally with intermediate bytes
+/// in between such as the `ESC ( B` emitted by `tput sgr0`. The sequences are only
+/// stripped, never interpreted, so cursor movements and the like are simply dropped.
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates up to a final byte in '@'..='~'
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST (ESC \)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // nF: intermediate bytes in ' '..='/' up to a final byte, e.g. `ESC ( B`
+            Some(' '..='/') => {
+                for c in chars.by_ref() {
+                    if !(' '..='/').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Two-byte escape sequence (or a lone trailing ESC)
+            _ => {}
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi_styles() {
+        let styled =
+            format!("{BOLD_CYAN}echo{BOLD_CYAN:#} {BOLD_UNDERLINE}hello{BOLD_UNDERLINE:#}");
+        assert_eq!(strip_ansi(&styled), "echo hello");
+    }
+
+    #[test]
+    fn test_strip_ansi_sequences() {
+        assert_eq!(strip_ansi("\x1b[1;31mred\x1b[0m"), "red");
+        assert_eq!(strip_ansi("\x1b[2Kclear"), "clear");
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+        assert_eq!(
+            strip_ansi("\x1b]8;;http://x\x1b\\link\x1b]8;;\x1b\\"),
+            "link"
+        );
+        assert_eq!(strip_ansi("\x1b=keypad"), "keypad");
+        assert_eq!(strip_ansi("\x1b(Bplain\x1b(B"), "plain");
+        assert_eq!(strip_ansi("\x1b[0m\x1b(Breset"), "reset");
+        assert_eq!(strip_ansi("\x1b$(Cwide"), "wide");
+        assert_eq!(strip_ansi("plain text\n"), "plain text\n");
+    }
+}
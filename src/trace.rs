@@ -0,0 +1,177 @@
+//! Optional `tracing` spans around command execution.
+//!
+//! Everything here compiles to nothing unless the `tracing` feature is enabled.
+
+use crate::cmd::Cmd;
+use std::process::ExitStatus;
+
+/// Spans of a spawned pipeline, closed when its handle is dropped.
+#[derive(Default)]
+pub(crate) struct Trace {
+    /// Parent span of the stages, only for pipelines of several commands.
+    #[cfg(feature = "tracing")]
+    pipeline: Option<tracing::Span>,
+    /// One `cmd` span per command, in spawn order.
+    #[cfg(feature = "tracing")]
+    stages: Vec<tracing::Span>,
+}
+
+impl Trace {
+    /// Open a `cmd` span for each command, nested in a `pipeline` span if there are several.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn start<'a>(cmds: impl IntoIterator<Item = &'a Cmd>) -> Self {
+        let cmds: Vec<&Cmd> = cmds.into_iter().collect();
+        let pipeline = (cmds.len() > 1).then(|| {
+            tracing::info_span!(
+                "pipeline",
+                stages = cmds.len(),
+                status = tracing::field::Empty,
+            )
+        });
+
+        let stages = cmds
+            .iter()
+            .map(|cmd| {
+                let program = cmd.program.to_string_lossy();
+                let args: Vec<String> = cmd
+                    .args
+                    .iter()
+                    .map(|arg| Cmd::quote_argument(arg))
+                    .collect();
+                let args = args.join(" ");
+                match &pipeline {
+                    Some(parent) => tracing::info_span!(
+                        parent: parent,
+                        "cmd",
+                        program = %program,
+                        args = %args,
+                        status = tracing::field::Empty,
+                    ),
+                    None => tracing::info_span!(
+                        "cmd",
+                        program = %program,
+                        args = %args,
+                        status = tracing::field::Empty,
+                    ),
+                }
+            })
+            .collect();
+
+        Self { pipeline, stages }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn start<'a>(_cmds: impl IntoIterator<Item = &'a Cmd>) -> Self {
+        Self::default()
+    }
+
+    /// Record the exit status of each command on its span, and the last one on the pipeline's.
+    pub(crate) fn record_statuses(&self, statuses: &[ExitStatus]) {
+        #[cfg(feature = "tracing")]
+        {
+            for (span, status) in self.stages.iter().zip(statuses) {
+                span.record("status", tracing::field::display(status));
+            }
+            if let (Some(span), Some(status)) = (&self.pipeline, statuses.last()) {
+                span.record("status", tracing::field::display(status));
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = statuses;
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::cmd;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    #[derive(Debug, Default)]
+    struct SpanData {
+        name: &'static str,
+        parent: Option<u64>,
+        fields: HashMap<&'static str, String>,
+    }
+
+    struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name(), format!("{:?}", value));
+        }
+    }
+
+    /// Subscriber keeping every span with its fields, indexed by id - 1.
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<SpanData>>>);
+
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut span = SpanData {
+                name: attrs.metadata().name(),
+                parent: attrs.parent().map(Id::into_u64),
+                ..SpanData::default()
+            };
+            attrs.record(&mut FieldVisitor(&mut span.fields));
+            let mut spans = self.0.lock().unwrap();
+            spans.push(span);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, id: &Id, values: &Record<'_>) {
+            let mut spans = self.0.lock().unwrap();
+            let span = &mut spans[id.into_u64() as usize - 1];
+            values.record(&mut FieldVisitor(&mut span.fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_spans() {
+        let recorder = SpanRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            cmd!("echo", "hello world").no_echo().output().unwrap();
+            cmd!("echo", "a")
+                .pipe(cmd!("sh", "-c", "cat; exit 3"))
+                .pipefail(false)
+                .no_echo()
+                .run()
+                .unwrap_err();
+        });
+
+        let spans = recorder.0.lock().unwrap();
+        let fields: Vec<_> = spans
+            .iter()
+            .map(|span| {
+                (
+                    span.name,
+                    span.parent,
+                    span.fields.get("program").map(String::as_str),
+                    span.fields.get("status").map(String::as_str),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("cmd", None, Some("echo"), Some("exit status: 0")),
+                ("pipeline", None, None, Some("exit status: 3")),
+                ("cmd", Some(2), Some("echo"), Some("exit status: 0")),
+                ("cmd", Some(2), Some("sh"), Some("exit status: 3")),
+            ]
+        );
+        assert_eq!(spans[0].fields["args"], "'hello world'");
+    }
+}
@@ -262,6 +262,10 @@ fn run_tests(project_root: &std::path::Path, verbose: bool) -> Result<()> {
         println!("🧪 Running tests...");
     }
     cmd!("cargo", "test").current_dir(project_root).run()?;
+    // Optional features such as `tokio` have their own tests
+    cmd!("cargo", "test", "--all-features")
+        .current_dir(project_root)
+        .run()?;
     if !verbose {
         println!("✅ Tests passed!");
     }